@@ -0,0 +1,203 @@
+use crate::pmf::Pmf;
+use crate::probability::Probability;
+use ibig::{IBig, UBig};
+
+/// An attack roll of a d20 plus a bonus against a target's armor class, as in D&D 5e. A natural
+/// 1 always misses, and a natural roll of `critical` or more always hits for critical damage.
+#[derive(Clone, Debug)]
+pub struct Attack<P = f64> {
+    pub bonus: IBig,
+    /// The lowest natural roll that is a critical hit, e.g. 19 for a crit range of 19-20.
+    pub critical: u8,
+    pub damage: Pmf<IBig, P>,
+    /// The damage of a critical hit, usually `damage` with its dice doubled.
+    pub critical_damage: Pmf<IBig, P>,
+}
+
+/// Extra damage dealt once per turn, on the first attack that hits, e.g. sneak attack.
+#[derive(Clone, Debug)]
+pub struct Rider<P = f64> {
+    pub damage: Pmf<IBig, P>,
+    pub critical_damage: Pmf<IBig, P>,
+}
+
+/// The chance an attack misses, hits or critically hits. `hit` excludes critical hits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Odds<P = f64> {
+    pub miss: P,
+    pub hit: P,
+    pub critical: P,
+}
+
+impl<P> Attack<P>
+where
+    P: Probability,
+{
+    /// The chance the attack misses, hits or critically hits a target with armor class `ac`.
+    pub fn odds(&self, ac: &IBig) -> Odds<P> {
+        let (mut miss, mut hit, mut critical) = (0u8, 0u8, 0u8);
+        for roll in 1..=20u8 {
+            if roll == 1 {
+                miss += 1;
+            } else if roll >= self.critical.max(2) {
+                critical += 1;
+            } else if IBig::from(roll) + &self.bonus >= *ac {
+                hit += 1;
+            } else {
+                miss += 1;
+            }
+        }
+
+        let twentieth = |count: u8| P::ratio(&UBig::from(count), &UBig::from(20u8));
+        Odds {
+            miss: twentieth(miss),
+            hit: twentieth(hit),
+            critical: twentieth(critical),
+        }
+    }
+
+    /// The distribution of the damage of the attack against a target with armor class `ac`.
+    pub fn pmf(&self, ac: &IBig) -> Pmf<IBig, P> {
+        let odds = self.odds(ac);
+
+        mixture(vec![
+            (odds.miss, Pmf::constant(IBig::from(0u8))),
+            (odds.hit, self.damage.clone()),
+            (odds.critical, self.critical_damage.clone()),
+        ])
+    }
+}
+
+/// The distribution of the total damage of a round of `attacks` against a target with armor
+/// class `ac`. The `rider` adds its damage to the first attack that hits, and its critical damage
+/// if that attack is a critical hit.
+pub fn round<P>(attacks: &[Attack<P>], rider: Option<&Rider<P>>, ac: &IBig) -> Pmf<IBig, P>
+where
+    P: Probability,
+{
+    let zero = || Pmf::constant(IBig::from(0u8));
+    let sum =
+        |left: &Pmf<IBig, P>, right: &Pmf<IBig, P>| left.cartesian_product(right, |l, r| l + r);
+
+    // The damage so far, and its probability, before and after the rider is used. No damage is
+    // dealt before it is used.
+    let (unused, mut unused_p) = (zero(), P::one());
+    let (mut used, mut used_p) = (zero(), P::zero());
+
+    for attack in attacks {
+        let odds = attack.odds(ac);
+        let (hit, critical) = match rider {
+            Some(rider) => (
+                sum(&attack.damage, &rider.damage),
+                sum(&attack.critical_damage, &rider.critical_damage),
+            ),
+            None => (attack.damage.clone(), attack.critical_damage.clone()),
+        };
+
+        used = mixture(vec![
+            (used_p.clone(), sum(&used, &attack.pmf(ac))),
+            (unused_p.clone() * odds.hit.clone(), sum(&unused, &hit)),
+            (
+                unused_p.clone() * odds.critical.clone(),
+                sum(&unused, &critical),
+            ),
+        ]);
+        used_p = used_p + unused_p.clone() * (odds.hit + odds.critical);
+        unused_p = unused_p * odds.miss;
+    }
+
+    mixture(vec![(unused_p, unused), (used_p, used)])
+}
+
+// A mixture of the components with a non-zero weight, so impossible outcomes are left out.
+fn mixture<P>(components: Vec<(P, Pmf<IBig, P>)>) -> Pmf<IBig, P>
+where
+    P: Probability,
+{
+    let components = components
+        .into_iter()
+        .filter(|(weight, _)| !weight.is_zero())
+        .collect::<Vec<_>>();
+
+    Pmf::mixture(&components)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::expression::Expression;
+    use crate::probability::Ratio;
+    use float_eq::assert_float_eq;
+    use num_traits::Zero;
+    use std::str::FromStr;
+
+    fn attack(bonus: i64, critical: u8, damage: &str) -> Attack<Ratio> {
+        let damage = Expression::from_str(damage).unwrap();
+
+        Attack {
+            bonus: IBig::from(bonus),
+            critical,
+            damage: damage.exact_pmf().unwrap(),
+            critical_damage: damage.double_dice().exact_pmf().unwrap(),
+        }
+    }
+
+    fn ratio(numerator: u8, denominator: u8) -> Ratio {
+        Ratio::new(UBig::from(numerator), UBig::from(denominator))
+    }
+
+    #[test]
+    fn odds() {
+        // Hits on 8 or more, and crits on 19 or 20.
+        let odds = attack(7, 19, "1d8 + 4").odds(&IBig::from(15));
+        assert_eq!(
+            odds,
+            Odds {
+                miss: ratio(7, 20),
+                hit: ratio(11, 20),
+                critical: ratio(2, 20),
+            }
+        );
+
+        // A natural 20 hits any armor class, and a natural 1 misses any.
+        let odds = attack(0, 20, "1").odds(&IBig::from(30));
+        assert_eq!(odds.critical, ratio(1, 20));
+        let odds = attack(30, 20, "1").odds(&IBig::from(1));
+        assert_eq!(odds.miss, ratio(1, 20));
+    }
+
+    #[test]
+    fn expected_damage() {
+        // 0.55 * 8.5 + 0.1 * 13
+        let attack = attack(7, 19, "1d8 + 4");
+        let pmf = attack.pmf(&IBig::from(15)).to_f64();
+        assert_float_eq!(pmf.expected_value(), 5.975, abs <= 1e-9);
+
+        // Two attacks deal twice the damage of one.
+        let pmf = round(&[attack.clone(), attack], None, &IBig::from(15)).to_f64();
+        assert_float_eq!(pmf.expected_value(), 11.95, abs <= 1e-9);
+    }
+
+    #[test]
+    fn rider_once_per_turn() {
+        let attack = attack(5, 20, "1");
+        let rider = Rider {
+            damage: Pmf::constant(IBig::from(10)),
+            critical_damage: Pmf::constant(IBig::from(20)),
+        };
+        let pmf = round(&[attack.clone(), attack], Some(&rider), &IBig::from(16));
+
+        // Each attack misses half of the time, hits 9/20 and crits 1/20. The rider lands on the
+        // first hit, and only once.
+        let p = |damage: i64| {
+            pmf.iter()
+                .find(|outcome| outcome.value == IBig::from(damage))
+                .map(|outcome| outcome.p.clone())
+                .unwrap_or_else(Ratio::zero)
+        };
+        assert_eq!(p(0), ratio(1, 4));
+        assert_eq!(p(11) + p(12), ratio(27, 40));
+        assert_eq!(p(21) + p(22), ratio(3, 40));
+        assert_eq!(p(2), Ratio::zero());
+    }
+}
@@ -1,12 +1,30 @@
-use crate::command::{Command, Pmf, Roll};
+use crate::command::{
+    read_trace, ArmorClasses, Chart as TerminalChart, ChartStyle, Command, Compare, Dpr, Faces,
+    Fairness, Fit, Pmf, PmfBins, PmfColumns, PmfMethod, Presets, Query, Rng, RngBackend, Roll,
+    Statistics, Sweep, SweepVariable, Table, TablePmf, Verify,
+};
+use crate::export::{Export, Format};
 use crate::json;
-use clap::Parser;
+use crate::svg::Chart;
+use clap::{CommandFactory, Parser, Subcommand};
+use ibig::{IBig, UBig};
 use log::warn;
+use owo_colors::OwoColorize;
 use roll::expression::Expression;
+use roll::pmf::Binning;
+use roll::query;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 pub struct Arguments {
     colors: bool,
     json: bool,
+    trace: bool,
+    format: Option<Format>,
+    precision: usize,
+    svg: Option<PathBuf>,
+    columns: PmfColumns,
+    chart: TerminalChart,
 }
 
 impl Arguments {
@@ -15,17 +33,337 @@ impl Arguments {
         let seed = args
             .seed
             .unwrap_or_else(|| rand::RngCore::next_u64(&mut rand::rngs::OsRng));
+        let rng = match args.rng {
+            Some(RngBackend::Os) => Rng::Os,
+            Some(RngBackend::Seeded) | None => Rng::Seeded(seed),
+        };
+        if rng == Rng::Os && args.seed.is_some() {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--seed applies only to the seeded rng",
+                )
+                .exit();
+        }
 
-        let command = match args.pmf {
-            true => CliCommand::Pmf(Pmf::new(args.expression)),
-            false => CliCommand::Roll(Roll::new(args.expression, seed)),
+        let columns = PmfColumns {
+            cdf: args.cdf,
+            at_least: args.at_least,
+        };
+        let pmf = args.pmf
+            || args.cdf
+            || args.at_least
+            || args.exact
+            || args.simulate.is_some()
+            || args.stats.is_some()
+            || args.given.is_some()
+            || args.svg.is_some()
+            || args.chart.is_some()
+            || args.bins.is_some()
+            || args.bin_width.is_some()
+            || args.tree;
+
+        let method = match (args.simulate, args.exact) {
+            (Some(samples), _) => PmfMethod::Simulate { samples, seed },
+            (None, true) => PmfMethod::Exact,
+            (None, false) => PmfMethod::Float,
+        };
+
+        let (format, precision) = (args.format, args.precision);
+        if args.bin_width == Some(UBig::from(0u8)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::InvalidValue,
+                    "--bin-width must be at least 1",
+                )
+                .exit();
+        }
+        if !(0.0..50.0).contains(&args.tail) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::InvalidValue,
+                    "--tail must be a percentage from 0 up to 50",
+                )
+                .exit();
+        }
+        let binning = match (args.bins, args.bin_width) {
+            (Some(count), _) => Some(Binning::Count(count.get())),
+            (None, Some(width)) => Some(Binning::Width(width)),
+            (None, None) => None,
+        };
+        let bins = PmfBins {
+            binning,
+            tail: args.tail / 100.0,
         };
+        let chart = TerminalChart {
+            style: args.chart,
+            ascii: args.ascii,
+            width: args.width,
+            height: args.height.get(),
+        };
+
+        let replay = args.replay.map(|path| {
+            read_trace(&path).unwrap_or_else(|err| {
+                RawArguments::command()
+                    .error(clap::ErrorKind::Io, format!("{:#}", err))
+                    .exit()
+            })
+        });
+        let faces = match (args.faces, &replay) {
+            (Some(faces), _) => Some(faces.0),
+            (None, Some(trace)) => Some(trace.faces()),
+            (None, None) => None,
+        };
+
+        let query = args.query.is_some();
+        let stats = args.stats.is_some();
+        let given = args.given.is_some();
+        let command = match (args.command, args.expression) {
+            (Some(RawCommand::Compare { expressions }), _) => {
+                CliCommand::Compare(Compare::new(expressions, args.stats, args.given))
+            }
+            (
+                Some(RawCommand::Dpr {
+                    bonus,
+                    damage,
+                    crit,
+                    ac,
+                    attacks,
+                    rider,
+                }),
+                _,
+            ) => CliCommand::Dpr(Dpr::new(bonus, damage, crit, ac, attacks, rider, args.pmf)),
+            (
+                Some(RawCommand::Fairness {
+                    expression,
+                    observed,
+                    alpha,
+                }),
+                _,
+            ) => {
+                if !(alpha > 0.0 && alpha < 1.0) {
+                    RawArguments::command()
+                        .error(
+                            clap::ErrorKind::InvalidValue,
+                            "--alpha must be a significance level between 0 and 1, e.g. 0.05",
+                        )
+                        .exit();
+                }
+                CliCommand::Fairness(Fairness::new(expression, observed, alpha))
+            }
+            (
+                Some(RawCommand::Fit {
+                    mean,
+                    stddev,
+                    observed,
+                    max_dice,
+                    top,
+                }),
+                _,
+            ) => CliCommand::Fit(Fit::new(mean, stddev, observed, max_dice, top)),
+            (Some(RawCommand::Presets), _) => CliCommand::Presets(Presets),
+            (Some(RawCommand::Table { file }), _) => match pmf {
+                true => CliCommand::TablePmf(TablePmf::new(file)),
+                false => CliCommand::Table(Table::new(file, rng)),
+            },
+            (
+                Some(RawCommand::Verify {
+                    expression,
+                    samples,
+                    alpha,
+                }),
+                _,
+            ) => {
+                if !(alpha > 0.0 && alpha < 1.0) {
+                    RawArguments::command()
+                        .error(
+                            clap::ErrorKind::InvalidValue,
+                            "--alpha must be a significance level between 0 and 1, e.g. 0.01",
+                        )
+                        .exit();
+                }
+                CliCommand::Verify(Verify::new(expression, samples, seed, alpha))
+            }
+            // Clap requires an expression when there is no subcommand or saved roll.
+            (None, expression) => {
+                let expression = expression
+                    .or_else(|| replay.map(|trace| trace.expression))
+                    .expect("An expression is required.");
+
+                match (args.query, pmf) {
+                    (query, _) if !args.sweep.is_empty() => CliCommand::Sweep(Sweep::new(
+                        expression, args.sweep, args.stats, query, args.given,
+                    )),
+                    (Some(query), _) => {
+                        CliCommand::Query(Query::new(expression, query, args.given, args.exact))
+                    }
+                    (None, true) => CliCommand::Pmf(Pmf::new(
+                        expression,
+                        columns,
+                        args.stats,
+                        args.given,
+                        method,
+                        bins.clone(),
+                        args.tree,
+                    )),
+                    (None, false) => CliCommand::Roll(Roll::new(expression, rng, faces.clone())),
+                }
+            }
+        };
+
+        if format.is_some() && !matches!(command, CliCommand::Roll(_) | CliCommand::Pmf(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--format applies only to rolls and distributions",
+                )
+                .exit();
+        }
+
+        if args.svg.is_some() && !matches!(command, CliCommand::Pmf(_) | CliCommand::Compare(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--svg applies only to distributions and comparisons",
+                )
+                .exit();
+        }
+
+        if bins.binning.is_some() && !matches!(command, CliCommand::Pmf(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--bins and --bin-width apply only to distributions",
+                )
+                .exit();
+        }
+
+        if chart.style.is_some() && !matches!(command, CliCommand::Pmf(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--chart applies only to distributions",
+                )
+                .exit();
+        }
+
+        if rng == Rng::Os && !matches!(command, CliCommand::Roll(_) | CliCommand::Table(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--rng os applies only to rolls and tables",
+                )
+                .exit();
+        }
+
+        if faces.is_some() && !matches!(command, CliCommand::Roll(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--faces and --replay apply only to rolls",
+                )
+                .exit();
+        }
+
+        if query && !matches!(command, CliCommand::Query(_) | CliCommand::Sweep(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--query applies only to rolls and sweeps",
+                )
+                .exit();
+        }
+
+        if args.exact && !matches!(command, CliCommand::Pmf(_) | CliCommand::Query(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--exact applies only to distributions and queries",
+                )
+                .exit();
+        }
+
+        let comparison_chart = matches!(command, CliCommand::Compare(_)) && args.svg.is_some();
+        if (columns.cdf || columns.at_least)
+            && !(matches!(command, CliCommand::Pmf(_)) || comparison_chart)
+        {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--cdf and --at-least apply only to distributions and comparison charts",
+                )
+                .exit();
+        }
+
+        if stats
+            && !matches!(
+                command,
+                CliCommand::Pmf(_) | CliCommand::Compare(_) | CliCommand::Sweep(_)
+            )
+        {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--stats applies only to distributions, comparisons and sweeps",
+                )
+                .exit();
+        }
+
+        if given
+            && !matches!(
+                command,
+                CliCommand::Pmf(_)
+                    | CliCommand::Query(_)
+                    | CliCommand::Compare(_)
+                    | CliCommand::Sweep(_)
+            )
+        {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--given applies only to distributions, queries, comparisons and sweeps",
+                )
+                .exit();
+        }
+
+        if args.simulate.is_some() && !matches!(command, CliCommand::Pmf(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--simulate applies only to distributions",
+                )
+                .exit();
+        }
+
+        if args.tree && !matches!(command, CliCommand::Pmf(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--tree applies only to distributions",
+                )
+                .exit();
+        }
+
+        if args.trace && !matches!(command, CliCommand::Roll(_)) {
+            RawArguments::command()
+                .error(
+                    clap::ErrorKind::ArgumentConflict,
+                    "--trace applies only to rolls",
+                )
+                .exit();
+        }
 
         (
             command,
             Self {
                 colors: args.colors,
                 json: args.json,
+                trace: args.trace,
+                format,
+                precision,
+                svg: args.svg,
+                columns,
+                chart,
             },
         )
     }
@@ -33,47 +371,234 @@ impl Arguments {
     pub fn use_colors(&self) -> bool {
         atty::is(atty::Stream::Stdout) || self.colors
     }
+
+    /// Whether a roll is written with the dice rolled, as on a TTY, instead of just its value.
+    pub fn use_trace(&self) -> bool {
+        self.use_colors() || self.trace
+    }
+
+    pub fn chart(&self) -> TerminalChart {
+        self.chart
+    }
 }
 
 #[derive(Debug)]
 pub enum CliCommand {
+    Compare(Compare),
+    Dpr(Dpr),
+    Fairness(Fairness),
+    Fit(Fit),
     Pmf(Pmf),
+    Presets(Presets),
+    Query(Query),
     Roll(Roll),
+    Sweep(Sweep),
+    Table(Table),
+    TablePmf(TablePmf),
+    Verify(Verify),
 }
 
 impl CliCommand {
     pub fn exec(self) -> Result<CliOutput, anyhow::Error> {
         match self {
+            CliCommand::Compare(compare) => {
+                let output = compare.exec()?;
+                Ok(CliOutput::Compare(compare, output))
+            }
+            CliCommand::Dpr(dpr) => {
+                let output = dpr.exec()?;
+                Ok(CliOutput::Dpr(dpr, output))
+            }
+            CliCommand::Fairness(fairness) => {
+                let output = fairness.exec()?;
+                Ok(CliOutput::Fairness(fairness, output))
+            }
+            CliCommand::Fit(fit) => {
+                let output = fit.exec()?;
+                Ok(CliOutput::Fit(fit, output))
+            }
             CliCommand::Pmf(pmf) => {
                 let output = pmf.exec()?;
 
                 Ok(CliOutput::Pmf(pmf, output))
             }
+            CliCommand::Presets(presets) => {
+                let output = presets.exec()?;
+                Ok(CliOutput::Presets(presets, output))
+            }
+            CliCommand::Query(query) => {
+                let output = query.exec()?;
+                Ok(CliOutput::Query(query, output))
+            }
             CliCommand::Roll(roll) => {
                 let output = roll.exec()?;
                 Ok(CliOutput::Roll(roll, output))
             }
+            CliCommand::Sweep(sweep) => {
+                let output = sweep.exec()?;
+                Ok(CliOutput::Sweep(sweep, output))
+            }
+            CliCommand::Table(table) => {
+                let output = table.exec()?;
+                Ok(CliOutput::Table(table, output))
+            }
+            CliCommand::TablePmf(table) => {
+                let output = table.exec()?;
+                Ok(CliOutput::TablePmf(table, output))
+            }
+            CliCommand::Verify(verify) => {
+                let output = verify.exec()?;
+                Ok(CliOutput::Verify(verify, output))
+            }
         }
     }
 }
 
 pub enum CliOutput {
+    Compare(Compare, <Compare as Command>::Output),
+    Dpr(Dpr, <Dpr as Command>::Output),
+    Fairness(Fairness, <Fairness as Command>::Output),
+    Fit(Fit, <Fit as Command>::Output),
     Roll(Roll, <Roll as Command>::Output),
     Pmf(Pmf, <Pmf as Command>::Output),
+    Presets(Presets, <Presets as Command>::Output),
+    Query(Query, <Query as Command>::Output),
+    Sweep(Sweep, <Sweep as Command>::Output),
+    Table(Table, <Table as Command>::Output),
+    TablePmf(TablePmf, <TablePmf as Command>::Output),
+    Verify(Verify, <Verify as Command>::Output),
 }
 
 impl CliOutput {
+    /// Whether the output passed the test it reports, if any. The process exits with a failure
+    /// status after a failed test is reported.
+    pub fn passed(&self) -> bool {
+        match self {
+            CliOutput::Fairness(_, output) => output.fair(),
+            CliOutput::Verify(_, output) => output.passed(),
+            _ => true,
+        }
+    }
+
+    /// Reports where a roll's or table's random results came from on stderr, so stdout holds
+    /// only the output. JSON output reports it in its `rng` field instead.
+    pub fn write_rng(&self, args: &Arguments) {
+        let rng = match self {
+            CliOutput::Roll(command, _) => match command.rng() {
+                Some(rng) => rng.to_string(),
+                None => String::from("replayed faces"),
+            },
+            CliOutput::Table(command, _) => command.rng().to_string(),
+            _ => return,
+        };
+
+        if args.json {
+            return;
+        }
+
+        match args.use_colors() {
+            true => eprintln!("{} {}", "Rng:".magenta().bold(), rng.blue()),
+            false => eprintln!("Rng: {}", rng),
+        }
+    }
+
+    /// Draws the distributions to the `--svg` file, if there is one.
+    pub fn write_svg(&self, args: &Arguments) -> Result<(), anyhow::Error> {
+        let path = match &args.svg {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let chart = match self {
+            CliOutput::Pmf(command, distribution) => Chart::new(
+                vec![(command.expression().to_string(), &distribution.pmf)],
+                command.columns(),
+                command
+                    .statistics()
+                    .map(|statistics| statistics.0.as_slice())
+                    .unwrap_or(&[]),
+            ),
+            CliOutput::Compare(command, comparison) => Chart::new(
+                comparison
+                    .expressions
+                    .iter()
+                    .map(|expression| expression.to_string())
+                    .zip(comparison.pmfs.iter())
+                    .collect(),
+                args.columns,
+                &command.statistics(),
+            ),
+            // Other commands are rejected with `--svg` when the arguments are parsed.
+            _ => return Ok(()),
+        };
+
+        std::fs::write(path, chart.render())
+            .map_err(|err| anyhow::anyhow!(err).context(format!("Could not write {:?}.", path)))
+    }
+
     pub fn formatter(self, args: Arguments) -> Box<dyn std::fmt::Display> {
         if args.json {
             return match self {
-                CliOutput::Roll(_, output) => Box::from(JsonFormatter(json::Evaluand::new(output))),
-                CliOutput::Pmf(_, output) => Box::from(JsonFormatter(json::Pmf::new(output))),
+                CliOutput::Compare(command, output) => {
+                    Box::from(JsonFormatter(json::Compare::new(output, &command)))
+                }
+                CliOutput::Dpr(command, output) => {
+                    Box::from(JsonFormatter(json::Dpr::new(output, &command)))
+                }
+                CliOutput::Fairness(command, output) => {
+                    Box::from(JsonFormatter(json::Fairness::new(output, &command)))
+                }
+                CliOutput::Fit(_, output) => Box::from(JsonFormatter(json::Fit::new(output))),
+                CliOutput::Roll(command, output) => {
+                    Box::from(JsonFormatter(json::Evaluand::new(output, command.rng())))
+                }
+                CliOutput::Pmf(command, output) => {
+                    Box::from(JsonFormatter(json::Pmf::new(output, &command)))
+                }
+                CliOutput::Presets(_, output) => {
+                    Box::from(JsonFormatter(json::Presets::new(output)))
+                }
+                CliOutput::Query(_, output) => Box::from(JsonFormatter(json::Query::new(output))),
+                CliOutput::Sweep(command, output) => {
+                    Box::from(JsonFormatter(json::Sweep::new(output, &command)))
+                }
+                CliOutput::Table(command, output) => {
+                    Box::from(JsonFormatter(json::Table::new(output, command.rng())))
+                }
+                CliOutput::TablePmf(_, output) => {
+                    Box::from(JsonFormatter(json::TablePmf::new(output)))
+                }
+                CliOutput::Verify(command, output) => {
+                    Box::from(JsonFormatter(json::Verify::new(output, &command)))
+                }
             };
         }
 
+        if let Some(format) = args.format {
+            let export = match &self {
+                CliOutput::Roll(_, output) => Some(Export::roll(output)),
+                CliOutput::Pmf(_, output) => Some(Export::pmf(output, args.precision)),
+                _ => None,
+            };
+
+            if let Some(export) = export {
+                return Box::new(export.render(format));
+            }
+        }
+
         match self {
+            CliOutput::Compare(command, output) => command.formatter(args, output),
+            CliOutput::Dpr(command, output) => command.formatter(args, output),
+            CliOutput::Fairness(command, output) => command.formatter(args, output),
+            CliOutput::Fit(command, output) => command.formatter(args, output),
             CliOutput::Roll(command, output) => command.formatter(args, output),
             CliOutput::Pmf(command, output) => command.formatter(args, output),
+            CliOutput::Presets(command, output) => command.formatter(args, output),
+            CliOutput::Query(command, output) => command.formatter(args, output),
+            CliOutput::Sweep(command, output) => command.formatter(args, output),
+            CliOutput::Table(command, output) => command.formatter(args, output),
+            CliOutput::TablePmf(command, output) => command.formatter(args, output),
+            CliOutput::Verify(command, output) => command.formatter(args, output),
         }
     }
 }
@@ -95,33 +620,240 @@ where
 }
 
 #[derive(Debug, Parser)]
-#[clap(author, version, about)]
+#[clap(author, version, about, subcommand_negates_reqs = true)]
 struct RawArguments {
     /// Forces color output (even if stdout is not a TTY)
-    #[clap(long)]
+    #[clap(long, global = true)]
     colors: bool,
 
     /// Print JSON to stdout
-    #[clap(long)]
+    #[clap(long, global = true)]
     json: bool,
 
+    /// Write a roll with the dice rolled, e.g. "1d20 (14) + 3 = 17", even if stdout is not a TTY
+    #[clap(long, global = true)]
+    trace: bool,
+
+    /// Print a roll or distribution as a table for spreadsheets and wikis instead
+    #[clap(long, global = true, arg_enum, conflicts_with = "json")]
+    format: Option<Format>,
+
+    /// The digits after the decimal point of probabilities in --format tables
+    #[clap(long, global = true, default_value = "6")]
+    precision: usize,
+
+    /// Draw the distribution, or the distributions compared, as a bar chart in an SVG file
+    #[clap(long, global = true)]
+    svg: Option<PathBuf>,
+
     /// Display the distribution instead of rolling
-    #[clap(long)]
+    #[clap(long, global = true)]
     pmf: bool,
 
+    /// Draw the distribution this way, even if stdout is not a TTY
+    #[clap(long, global = true, arg_enum)]
+    chart: Option<ChartStyle>,
+
+    /// Draw charts with ASCII characters only, for terminals without Unicode
+    #[clap(long, global = true)]
+    ascii: bool,
+
+    /// The width of a chart's lines, instead of the terminal's up to 75 characters
+    #[clap(long, global = true)]
+    width: Option<usize>,
+
+    /// The rows of a vertical chart
+    #[clap(long, global = true, default_value = "10")]
+    height: NonZeroUsize,
+
+    /// Group the distribution's outcomes into at most this many ranges of equal width
+    #[clap(long, global = true, conflicts_with = "bin-width")]
+    bins: Option<NonZeroUsize>,
+
+    /// Group the distribution's outcomes into ranges this wide
+    #[clap(long, global = true)]
+    bin_width: Option<UBig>,
+
+    /// Collapse the outcomes at either end of a binned distribution with at most this total
+    /// percent chance into one range
+    #[clap(long, global = true, default_value = "0.01")]
+    tail: f64,
+
+    /// Display the distribution with the chance of rolling at most each value
+    #[clap(long, global = true)]
+    cdf: bool,
+
+    /// Display the distribution with the chance of rolling at least each value
+    #[clap(long, global = true)]
+    at_least: bool,
+
+    /// Display the distribution with statistics, e.g. "mean,stddev,p90" or "all"
+    #[clap(long, global = true)]
+    stats: Option<Statistics>,
+
+    /// Display the distribution of each subexpression beneath the distribution
+    #[clap(long, global = true, conflicts_with_all = &["given", "simulate"])]
+    tree: bool,
+
+    /// Display the probability of outcomes, e.g. ">= 15", "1..4 or 20" or "P(>= 15 | > 10)"
+    #[clap(long, global = true)]
+    query: Option<query::Query>,
+
+    /// Display the distribution given an event occurs, e.g. ">= 10" or "1..4 or 20"
+    #[clap(long, global = true)]
+    given: Option<query::Event>,
+
+    /// Display the distribution or query with exact fractions instead of floating point
+    #[clap(long, global = true)]
+    exact: bool,
+
+    /// Estimate the distribution from this many rolls, for expressions too large to compute
+    #[clap(long, global = true, conflicts_with_all = &["exact", "query"])]
+    simulate: Option<u64>,
+
+    /// Tabulate statistics or the query as a variable varies, e.g. "m=0..10" for "1d20 + $m"
+    #[clap(long, global = true, multiple_occurrences = true, conflicts_with_all = &["exact", "simulate"])]
+    sweep: Vec<SweepVariable>,
+
     /// Seeds the rng
-    #[clap(long)]
+    #[clap(long, global = true)]
     seed: Option<u64>,
 
+    /// Roll with a PRNG, seeded with --seed or a random seed, or the operating system's entropy
+    /// for every die
+    #[clap(long, global = true, arg_enum)]
+    rng: Option<RngBackend>,
+
+    /// Roll with these faces instead of random ones, in the order the expression rolls them, e.g.
+    /// "14,3,5" for "1d20 + 2d6"
+    #[clap(long, global = true, conflicts_with_all = &["replay", "rng"])]
+    faces: Option<Faces>,
+
+    /// Roll again with the faces of a roll saved with --json, e.g. to audit it. The expression
+    /// defaults to the saved roll's
+    #[clap(long, global = true, conflicts_with = "rng")]
+    replay: Option<PathBuf>,
+
     /// The dice expression to evaluate.
-    expression: Expression,
+    #[clap(required_unless_present = "replay")]
+    expression: Option<Expression>,
+
+    #[clap(subcommand)]
+    command: Option<RawCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+enum RawCommand {
+    /// Compare the distributions of several expressions side by side
+    Compare {
+        /// The dice expressions to compare.
+        #[clap(required = true, min_values = 2)]
+        expressions: Vec<Expression>,
+    },
+
+    /// Tabulate the expected damage per round of attacks against a range of armor classes
+    Dpr {
+        /// The attack bonus added to the d20, e.g. "7"
+        #[clap(long, allow_hyphen_values = true)]
+        bonus: IBig,
+
+        /// The damage of a hit, e.g. "1d8 + 4". Critical hits roll its dice twice
+        #[clap(long)]
+        damage: Expression,
+
+        /// The lowest natural roll that is a critical hit, e.g. "19" for 19-20
+        #[clap(long, default_value = "20")]
+        crit: u8,
+
+        /// The armor classes to target, e.g. "10..20" or "13,15,17"
+        #[clap(long, default_value = "10..20")]
+        ac: ArmorClasses,
+
+        /// The number of attacks per round
+        #[clap(long, default_value = "1")]
+        attacks: usize,
+
+        /// Damage added once per turn to the first hit, e.g. "3d6" for sneak attack
+        #[clap(long)]
+        rider: Option<Expression>,
+    },
+
+    /// Test whether recorded rolls of physical dice fit an expression's distribution
+    Fairness {
+        /// The dice expression the results were rolled with, e.g. "1d20".
+        expression: Expression,
+
+        /// A file of the results, separated by whitespace or commas
+        #[clap(long)]
+        observed: PathBuf,
+
+        /// The significance level the results are tested at, between 0 and 1
+        #[clap(long, default_value = "0.05")]
+        alpha: f64,
+    },
+
+    /// Search for small dice expressions that match a mean and standard deviation or results
+    Fit {
+        /// The mean to aim for
+        #[clap(long, allow_hyphen_values = true)]
+        mean: Option<f64>,
+
+        /// The standard deviation to aim for
+        #[clap(long)]
+        stddev: Option<f64>,
+
+        /// A file of results to match the distribution of, separated by whitespace or commas
+        #[clap(long, conflicts_with_all = &["mean", "stddev"])]
+        observed: Option<PathBuf>,
+
+        /// The most dice in a candidate
+        #[clap(long, default_value = "6")]
+        max_dice: u32,
+
+        /// The number of candidates to list
+        #[clap(long, default_value = "10")]
+        top: usize,
+    },
+
+    /// List the game system presets available in expressions
+    Presets,
+
+    /// Roll on a random table stored in a JSON file
+    Table {
+        /// The table file to roll on.
+        file: PathBuf,
+    },
+
+    /// Check that rolling an expression agrees with its distribution, using a G-test
+    Verify {
+        /// The dice expression to check.
+        expression: Expression,
+
+        /// The number of times to roll the expression
+        #[clap(long, default_value = "100000")]
+        samples: u64,
+
+        /// The significance level the rolls are tested at, between 0 and 1
+        #[clap(long, default_value = "0.001")]
+        alpha: f64,
+    },
 }
 
 impl std::fmt::Debug for CliOutput {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
+            CliOutput::Compare(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Dpr(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Fairness(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Fit(_, output) => std::fmt::Debug::fmt(output, formatter),
             CliOutput::Roll(_, output) => std::fmt::Debug::fmt(output, formatter),
             CliOutput::Pmf(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Presets(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Query(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Sweep(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Table(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::TablePmf(_, output) => std::fmt::Debug::fmt(output, formatter),
+            CliOutput::Verify(_, output) => std::fmt::Debug::fmt(output, formatter),
         }
     }
 }
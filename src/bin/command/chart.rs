@@ -0,0 +1,252 @@
+use super::percent;
+use clap::ArgEnum;
+use terminal_size::terminal_size;
+
+// The widest line drawn when neither `--width` nor the terminal sets one.
+const MAX_WIDTH: usize = 75;
+
+// Eighths of a cell, from empty to full.
+const BLOCKS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const ASCII_LEVELS: [char; 8] = ['_', '.', ',', '-', '=', '+', '*', '#'];
+
+/// How a distribution is drawn in the terminal.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartStyle {
+    /// A bar beside each outcome's probability.
+    Horizontal,
+    /// A column above each outcome.
+    Vertical,
+    /// A single line with a block for each outcome.
+    Sparkline,
+    /// Only the probabilities.
+    None,
+}
+
+/// The `--chart` options of a distribution.
+#[derive(Clone, Copy, Debug)]
+pub struct Chart {
+    /// The style asked for, if any. Horizontal bars are drawn by default on a terminal.
+    pub style: Option<ChartStyle>,
+    /// Draws with ASCII characters only, for terminals without Unicode.
+    pub ascii: bool,
+    /// The width of a line, if not the terminal's.
+    pub width: Option<usize>,
+    /// The rows of a vertical chart.
+    pub height: usize,
+}
+
+impl Chart {
+    /// The style to draw, which is horizontal bars on a terminal unless `--chart` says otherwise.
+    pub fn style(&self, colors: bool) -> ChartStyle {
+        match (self.style, terminal_size()) {
+            (Some(style), _) => style,
+            (None, Some(_)) if colors => ChartStyle::Horizontal,
+            (None, _) => ChartStyle::None,
+        }
+    }
+
+    /// The width of a line, at most 75 characters unless `--width` says otherwise.
+    pub fn width(&self) -> usize {
+        match (self.width, terminal_size()) {
+            (Some(width), _) => width,
+            (None, Some((width, _))) => usize::from(width.0).min(MAX_WIDTH),
+            (None, None) => MAX_WIDTH,
+        }
+    }
+
+    /// The character of a horizontal bar.
+    pub fn bar(&self) -> &'static str {
+        match self.ascii {
+            true => "#",
+            false => "▬",
+        }
+    }
+
+    /// Draws columns of `probabilities` over an axis labelled with the first and last `values`.
+    /// Outcomes are grouped into columns when there are more than fit on a line.
+    pub fn vertical(&self, values: &[String], probabilities: &[f64]) -> Vec<String> {
+        // The labels and axis take up the indent, the label, a space and the axis. Grouping
+        // outcomes raises the top label, which may narrow the plot and group them again.
+        let fit = |label_width: usize| {
+            let plot_width = self.width().saturating_sub(label_width + 4).max(1);
+            let columns = group(probabilities, plot_width);
+            let max_p = columns.iter().copied().fold(0.0, f64::max);
+
+            (plot_width, columns, max_p, percent(&max_p))
+        };
+        let (mut plot_width, mut columns, mut max_p, mut max_label) = fit(0);
+        while plot_width > self.width().saturating_sub(max_label.len() + 4).max(1) {
+            (plot_width, columns, max_p, max_label) = fit(max_label.len());
+        }
+        let label_width = max_label.len();
+        // Columns are spaced out when there is room, up to four characters each.
+        let cell = (plot_width / columns.len()).clamp(1, 4);
+        let bar = match cell {
+            1 => 1,
+            cell => cell - 1,
+        };
+
+        let mut lines = (0..self.height)
+            .rev()
+            .map(|row| {
+                let label = match row + 1 == self.height {
+                    true => max_label.as_str(),
+                    false => "",
+                };
+                let cells = columns
+                    .iter()
+                    .map(|p| {
+                        let fill = self.fill(p / max_p, row);
+                        format!("{}{}", fill.to_string().repeat(bar), " ".repeat(cell - bar))
+                    })
+                    .collect::<String>();
+
+                format!(
+                    "  {:>width$} {}{}",
+                    label,
+                    self.axis().0,
+                    cells.trim_end(),
+                    width = label_width
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let axis_width = cell * columns.len();
+        lines.push(format!(
+            "  {:>width$} {}{}",
+            "0%",
+            self.axis().1,
+            self.axis().2.to_string().repeat(axis_width),
+            width = label_width
+        ));
+
+        let (first, last) = (&values[0], &values[values.len() - 1]);
+        // The last label ends under the last column.
+        let gap = (axis_width - (cell - bar))
+            .saturating_sub(first.len() + last.len())
+            .max(1);
+        lines.push(match values.len() {
+            1 => format!("  {:width$} {}", "", first, width = label_width + 1),
+            _ => format!(
+                "  {:width$} {}{}{}",
+                "",
+                first,
+                " ".repeat(gap),
+                last,
+                width = label_width + 1
+            ),
+        });
+
+        lines
+    }
+
+    /// Draws `probabilities` as a line of blocks between the first and last `values`.
+    pub fn sparkline(&self, values: &[String], probabilities: &[f64]) -> String {
+        let (first, last) = (&values[0], &values[values.len() - 1]);
+        let plot_width = self
+            .width()
+            .saturating_sub(first.len() + last.len() + 4)
+            .max(1);
+        let columns = group(probabilities, plot_width);
+        let max_p = columns.iter().copied().fold(0.0, f64::max);
+
+        let line = columns
+            .iter()
+            .map(|p| {
+                let level = ((p / max_p) * 7.0).round() as usize;
+                match self.ascii {
+                    true => ASCII_LEVELS[level],
+                    false => BLOCKS[level + 1],
+                }
+            })
+            .collect::<String>();
+
+        format!("  {} {} {}", first, line, last)
+    }
+
+    // The character of a vertical column `height` tall, as a fraction of the chart, at `row`.
+    fn fill(&self, height: f64, row: usize) -> char {
+        let rows = height * self.height as f64;
+
+        match self.ascii {
+            true => match rows.round() as usize > row {
+                true => '#',
+                false => ' ',
+            },
+            false => {
+                let eighths = ((rows - row as f64) * 8.0).round().clamp(0.0, 8.0);
+                BLOCKS[eighths as usize]
+            }
+        }
+    }
+
+    // The vertical axis, its corner and the horizontal axis.
+    fn axis(&self) -> (char, char, char) {
+        match self.ascii {
+            true => ('|', '+', '-'),
+            false => ('│', '└', '─'),
+        }
+    }
+}
+
+// Sums adjacent probabilities so there are at most `width` of them.
+fn group(probabilities: &[f64], width: usize) -> Vec<f64> {
+    let size = probabilities.len().div_ceil(width);
+
+    probabilities
+        .chunks(size.max(1))
+        .map(|chunk| chunk.iter().sum())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chart(ascii: bool, width: usize, height: usize) -> Chart {
+        Chart {
+            style: None,
+            ascii,
+            width: Some(width),
+            height,
+        }
+    }
+
+    #[test]
+    fn groups_outcomes_to_fit() {
+        assert_eq!(group(&[0.25, 0.25, 0.5], 3), vec![0.25, 0.25, 0.5]);
+        assert_eq!(group(&[0.25, 0.25, 0.25, 0.25], 2), vec![0.5, 0.5]);
+        assert_eq!(group(&[0.125; 5], 2), vec![0.375, 0.25]);
+    }
+
+    #[test]
+    fn vertical() {
+        let values = ["1", "2", "3"].map(String::from);
+        let lines = chart(true, 20, 2).vertical(&values, &[0.25, 0.25, 0.5]);
+
+        assert_eq!(
+            lines,
+            vec![
+                "  50.00% |      ##",
+                "         |## ## ##",
+                "      0% +---------",
+                "          1      3",
+            ]
+        );
+    }
+
+    #[test]
+    fn sparkline() {
+        let values = ["2", "4"].map(String::from);
+        let probabilities = [0.25, 0.5, 0.25];
+
+        assert_eq!(
+            chart(false, 20, 1).sparkline(&values, &probabilities),
+            "  2 ▅█▅ 4"
+        );
+        assert_eq!(
+            chart(true, 20, 1).sparkline(&values, &probabilities),
+            "  2 =#= 4"
+        );
+    }
+}
@@ -0,0 +1,351 @@
+use super::statistics::{Statistic, Statistics};
+use super::{percent, Chart, Command};
+use ibig::IBig;
+use owo_colors::{AnsiColors, OwoColorize};
+use roll::{expression::Expression, pmf::Pmf, query::Event};
+use std::fmt::{Display, Formatter};
+use terminal_size::terminal_size;
+
+// Each expression keeps its colour in every section, so its bars are easy to pick out.
+const COLORS: [AnsiColors; 6] = [
+    AnsiColors::Blue,
+    AnsiColors::Green,
+    AnsiColors::Yellow,
+    AnsiColors::Magenta,
+    AnsiColors::Cyan,
+    AnsiColors::Red,
+];
+
+const DEFAULT_STATISTICS: [Statistic; 4] = [
+    Statistic::Min,
+    Statistic::Max,
+    Statistic::Mean,
+    Statistic::StandardDeviation,
+];
+
+#[derive(Debug)]
+pub struct Compare {
+    expressions: Vec<Expression>,
+    statistics: Option<Statistics>,
+    given: Option<Event>,
+}
+
+impl Compare {
+    pub fn new(
+        expressions: Vec<Expression>,
+        statistics: Option<Statistics>,
+        given: Option<Event>,
+    ) -> Self {
+        Self {
+            expressions,
+            statistics,
+            given,
+        }
+    }
+
+    /// The statistics chosen on the command line, or the minimum, maximum, mean and standard
+    /// deviation.
+    pub fn statistics(&self) -> Vec<Statistic> {
+        match &self.statistics {
+            Some(statistics) => statistics.0.clone(),
+            None => DEFAULT_STATISTICS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    pub expressions: Vec<Expression>,
+    pub given: Option<Event>,
+    pub pmfs: Vec<Pmf<IBig>>,
+    /// Every ordered pair of distinct expressions.
+    pub pairs: Vec<Pair>,
+}
+
+/// How the distribution of the `left` expression relates to that of the `right` expression.
+#[derive(Debug)]
+pub struct Pair {
+    pub left: usize,
+    pub right: usize,
+    /// P(left > right), when both are rolled independently.
+    pub p_greater: f64,
+    pub total_variation: f64,
+    /// The divergence of right from left, in bits.
+    pub kl_divergence: f64,
+}
+
+impl Command for Compare {
+    type Output = Comparison;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        let pmfs = self
+            .expressions
+            .iter()
+            .map(|expression| match &self.given {
+                Some(given) => given.condition(&expression.pmf()?),
+                None => expression.pmf(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut pairs = vec![];
+        for (left, a) in pmfs.iter().enumerate() {
+            for (right, b) in pmfs.iter().enumerate() {
+                if left != right {
+                    pairs.push(Pair {
+                        left,
+                        right,
+                        p_greater: a.probability_greater(b),
+                        total_variation: a.total_variation(b),
+                        kl_divergence: a.kl_divergence(b),
+                    });
+                }
+            }
+        }
+
+        Ok(Comparison {
+            expressions: self.expressions.clone(),
+            given: self.given.clone(),
+            pmfs,
+            pairs,
+        })
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        comparison: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            chart: args.chart(),
+            statistics: self.statistics(),
+            comparison,
+        })
+    }
+}
+
+/// The name of the expression in the tables, e.g. `A`. Expressions after the 26th are numbered.
+pub fn label(i: usize) -> String {
+    match u8::try_from(i) {
+        Ok(i) if i < 26 => char::from(b'A' + i).to_string(),
+        _ => format!("#{}", i + 1),
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    chart: Chart,
+    statistics: Vec<Statistic>,
+    comparison: Comparison,
+}
+
+impl TextFormatter {
+    fn heading(&self, formatter: &mut Formatter, heading: &str) -> Result<(), std::fmt::Error> {
+        if self.colors {
+            formatter.write_fmt(format_args!("{}\n", heading.magenta().bold()))
+        } else {
+            formatter.write_fmt(format_args!("{}\n", heading))
+        }
+    }
+
+    fn label(&self, i: usize, width: usize) -> String {
+        let label = format!("{:>width$}", label(i), width = width);
+
+        match self.colors {
+            true => label.color(COLORS[i % COLORS.len()]).bold().to_string(),
+            false => label,
+        }
+    }
+
+    // Prints a table with a column per expression. Cells are right aligned.
+    fn table(
+        &self,
+        formatter: &mut Formatter,
+        rows: Vec<(String, Vec<String>)>,
+    ) -> Result<(), std::fmt::Error> {
+        let n = self.comparison.expressions.len();
+        let row_width = rows.iter().map(|(row, _)| row.len()).max().unwrap_or(0);
+        let width = rows
+            .iter()
+            .flat_map(|(_, cells)| cells.iter().map(|cell| cell.chars().count()))
+            .max()
+            .unwrap_or(0)
+            .max(6);
+
+        formatter.write_fmt(format_args!("  {:row_width$}", "", row_width = row_width))?;
+        for i in 0..n {
+            formatter.write_fmt(format_args!("  {}", self.label(i, width)))?;
+        }
+        formatter.write_str("\n")?;
+
+        for (row, cells) in rows.iter() {
+            formatter.write_fmt(format_args!("  {:row_width$}", row, row_width = row_width))?;
+            for cell in cells.iter() {
+                formatter.write_fmt(format_args!("  {:>width$}", cell, width = width))?;
+            }
+            formatter.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+
+    // A square table of a measure between each row and column expression.
+    fn matrix<F>(&self, formatter: &mut Formatter, measure: F) -> Result<(), std::fmt::Error>
+    where
+        F: Fn(&Pair) -> String,
+    {
+        let n = self.comparison.expressions.len();
+        let rows = (0..n)
+            .map(|left| {
+                let cells = (0..n)
+                    .map(|right| {
+                        self.comparison
+                            .pairs
+                            .iter()
+                            .find(|pair| pair.left == left && pair.right == right)
+                            .map(&measure)
+                            .unwrap_or_else(|| String::from("-"))
+                    })
+                    .collect();
+
+                (label(left), cells)
+            })
+            .collect();
+
+        self.table(formatter, rows)
+    }
+
+    fn distributions(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let pmfs = &self.comparison.pmfs;
+
+        let mut values = pmfs
+            .iter()
+            .flat_map(|pmf| pmf.iter().map(|outcome| outcome.value.clone()))
+            .collect::<Vec<_>>();
+        values.sort();
+        values.dedup();
+
+        let max_digits = values
+            .iter()
+            .map(|value| value.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let label_width = (0..pmfs.len()).map(|i| label(i).len()).max().unwrap_or(0);
+        let max_p = pmfs
+            .iter()
+            .flat_map(|pmf| pmf.iter().map(|outcome| outcome.p))
+            .fold(0.0, f64::max);
+
+        let padding = " ";
+        let percent_chars = 7;
+        let bar_width = match terminal_size() {
+            Some((width, _)) if self.colors => {
+                let other_chars = 2
+                    + max_digits
+                    + padding.len()
+                    + label_width
+                    + padding.len()
+                    + padding.len()
+                    + percent_chars;
+
+                Some(Into::<usize>::into(width.0).min(75).max(other_chars) - other_chars)
+            }
+            _ => None,
+        };
+
+        for value in values.iter() {
+            for (i, pmf) in pmfs.iter().enumerate() {
+                let p = pmf
+                    .iter()
+                    .find(|outcome| &outcome.value == value)
+                    .map(|outcome| outcome.p)
+                    .unwrap_or(0.0);
+
+                // The value is only written once, above the outcomes of the other expressions.
+                let value = match i {
+                    0 => value.to_string(),
+                    _ => String::new(),
+                };
+
+                formatter.write_fmt(format_args!(
+                    "  {:>max_digits$}{}{}",
+                    value.if_supports_color(owo_colors::Stream::Stdout, |text| text
+                        .style(owo_colors::Style::new().blue().bold())),
+                    padding,
+                    self.label(i, label_width),
+                    max_digits = max_digits,
+                ))?;
+
+                if let Some(max_width) = bar_width {
+                    let bar_width = ((p / max_p) * max_width as f64) as usize;
+
+                    formatter.write_fmt(format_args!(
+                        "{}{}{}{}",
+                        padding,
+                        self.chart
+                            .bar()
+                            .repeat(bar_width)
+                            .color(COLORS[i % COLORS.len()]),
+                        " ".repeat(max_width - bar_width),
+                        padding,
+                    ))?;
+                } else {
+                    formatter.write_str(padding)?;
+                }
+
+                formatter.write_fmt(format_args!("{:>7}\n", percent(&p)))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let comparison = &self.comparison;
+
+        self.heading(formatter, "Expressions:")?;
+        for (i, expression) in comparison.expressions.iter().enumerate() {
+            formatter.write_fmt(format_args!("  {} {}\n", self.label(i, 0), expression))?;
+        }
+        if let Some(given) = &comparison.given {
+            formatter.write_str("\n")?;
+            self.heading(formatter, "Given:")?;
+            formatter.write_fmt(format_args!("  {}\n", given))?;
+        }
+
+        formatter.write_str("\n")?;
+        self.heading(formatter, "Statistics:")?;
+        let rows = self
+            .statistics
+            .iter()
+            .map(|statistic| {
+                let cells = comparison
+                    .pmfs
+                    .iter()
+                    .map(|pmf| statistic.value(pmf).to_string())
+                    .collect();
+
+                (statistic.label().trim_end_matches(':').to_string(), cells)
+            })
+            .collect();
+        self.table(formatter, rows)?;
+
+        formatter.write_str("\n")?;
+        self.heading(formatter, "P(row > column):")?;
+        self.matrix(formatter, |pair| format!("{:.2}%", pair.p_greater * 100.0))?;
+
+        formatter.write_str("\n")?;
+        self.heading(formatter, "Total variation distance:")?;
+        self.matrix(formatter, |pair| format!("{:.4}", pair.total_variation))?;
+
+        formatter.write_str("\n")?;
+        self.heading(formatter, "KL divergence of column from row (bits):")?;
+        self.matrix(formatter, |pair| format!("{:.4}", pair.kl_divergence))?;
+
+        formatter.write_str("\n")?;
+        self.heading(formatter, "Distributions:")?;
+        self.distributions(formatter)
+    }
+}
@@ -0,0 +1,269 @@
+use super::sweep::{parse_values, ValuesError};
+use super::{percent, Command};
+use ibig::IBig;
+use owo_colors::OwoColorize;
+use roll::{
+    attack::{self, Attack, Odds, Rider},
+    expression::Expression,
+    pmf::Pmf,
+};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Bounds the number of attacks convolved together.
+const MAX_ATTACKS: usize = 100;
+
+/// The armor classes to target, e.g. `10..20` or `13,15,17`.
+#[derive(Clone, Debug)]
+pub struct ArmorClasses(pub Vec<IBig>);
+
+#[derive(Debug)]
+pub struct Dpr {
+    bonus: IBig,
+    damage: Expression,
+    critical: u8,
+    armor_classes: ArmorClasses,
+    attacks: usize,
+    rider: Option<Expression>,
+    distributions: bool,
+}
+
+impl Dpr {
+    pub fn new(
+        bonus: IBig,
+        damage: Expression,
+        critical: u8,
+        armor_classes: ArmorClasses,
+        attacks: usize,
+        rider: Option<Expression>,
+        distributions: bool,
+    ) -> Self {
+        Self {
+            bonus,
+            damage,
+            critical,
+            armor_classes,
+            attacks,
+            rider,
+            distributions,
+        }
+    }
+
+    pub fn bonus(&self) -> &IBig {
+        &self.bonus
+    }
+
+    pub fn damage(&self) -> &Expression {
+        &self.damage
+    }
+
+    pub fn critical(&self) -> u8 {
+        self.critical
+    }
+
+    pub fn attacks(&self) -> usize {
+        self.attacks
+    }
+
+    pub fn rider(&self) -> Option<&Expression> {
+        self.rider.as_ref()
+    }
+}
+
+/// The damage of a round of attacks against an armor class.
+#[derive(Debug)]
+pub struct Target {
+    pub ac: IBig,
+    /// The odds of each attack.
+    pub odds: Odds,
+    pub pmf: Pmf<IBig>,
+}
+
+impl Command for Dpr {
+    type Output = Vec<Target>;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        if !(2..=20).contains(&self.critical) {
+            return Err(anyhow::anyhow!(
+                "Critical hits start at a natural roll from 2 to 20, not {}.",
+                self.critical
+            ));
+        }
+        if !(1..=MAX_ATTACKS).contains(&self.attacks) {
+            return Err(anyhow::anyhow!(
+                "A round has from 1 to {} attacks, not {}.",
+                MAX_ATTACKS,
+                self.attacks
+            ));
+        }
+
+        let attack = Attack {
+            bonus: self.bonus.clone(),
+            critical: self.critical,
+            damage: self.damage.pmf()?,
+            critical_damage: self.damage.double_dice().pmf()?,
+        };
+        let attacks = vec![attack; self.attacks];
+        let rider = match &self.rider {
+            Some(rider) => Some(Rider {
+                damage: rider.pmf()?,
+                critical_damage: rider.double_dice().pmf()?,
+            }),
+            None => None,
+        };
+
+        Ok(self
+            .armor_classes
+            .0
+            .iter()
+            .map(|ac| Target {
+                ac: ac.clone(),
+                odds: attacks[0].odds(ac),
+                pmf: attack::round(&attacks, rider.as_ref(), ac),
+            })
+            .collect())
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        targets: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            dpr: self,
+            targets,
+        })
+    }
+}
+
+impl FromStr for ArmorClasses {
+    type Err = anyhow::Error;
+
+    fn from_str(armor_classes: &str) -> Result<Self, Self::Err> {
+        parse_values(armor_classes)
+            .map(ArmorClasses)
+            .map_err(|err| match err {
+                ValuesError::Invalid => anyhow::anyhow!(
+                    "{:?} is not a list of armor classes, e.g. \"10..20\" or \"13,15,17\".",
+                    armor_classes
+                ),
+                ValuesError::TooMany => anyhow::anyhow!("There are too many armor classes."),
+            })
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    dpr: Dpr,
+    targets: Vec<Target>,
+}
+
+impl TextFormatter {
+    fn heading(&self, heading: &str) -> String {
+        match self.colors {
+            true => heading.magenta().bold().to_string(),
+            false => heading.to_string(),
+        }
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let dpr = &self.dpr;
+        let critical_range = match dpr.critical {
+            20 => String::from("20"),
+            critical => format!("{}-20", critical),
+        };
+
+        formatter.write_fmt(format_args!(
+            "{} {:+} to hit, {} damage, critical on {} for {}\n",
+            self.heading("Attack:"),
+            dpr.bonus,
+            dpr.damage,
+            critical_range,
+            dpr.damage.double_dice()
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} {} per round\n",
+            self.heading("Attacks:"),
+            dpr.attacks
+        ))?;
+        if let Some(rider) = &dpr.rider {
+            formatter.write_fmt(format_args!(
+                "{} {} once per turn, {} on a critical\n",
+                self.heading("Rider:"),
+                rider,
+                rider.double_dice()
+            ))?;
+        }
+        formatter.write_str("\n")?;
+
+        let rows = self
+            .targets
+            .iter()
+            .map(|target| {
+                [
+                    target.ac.to_string(),
+                    percent(&(target.odds.hit + target.odds.critical)),
+                    percent(&target.odds.critical),
+                    format!("{:.2}", target.pmf.expected_value()),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let header = ["AC", "hit", "critical", "DPR"];
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].len())
+                    .fold(header[i].len(), usize::max)
+            })
+            .collect::<Vec<_>>();
+
+        for (i, cell) in header.iter().enumerate() {
+            let cell = format!("  {:>width$}", cell, width = widths[i]);
+            match self.colors {
+                true => formatter.write_fmt(format_args!("{}", cell.cyan().bold()))?,
+                false => formatter.write_str(&cell)?,
+            }
+        }
+        formatter.write_str("\n")?;
+        for row in rows.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                let cell = format!("  {:>width$}", cell, width = widths[i]);
+                match (i, self.colors) {
+                    (0, true) => formatter.write_fmt(format_args!("{}", cell.blue().bold()))?,
+                    _ => formatter.write_str(&cell)?,
+                }
+            }
+            formatter.write_str("\n")?;
+        }
+
+        // With `--pmf`, the damage of each armor class follows.
+        if dpr.distributions {
+            for target in self.targets.iter() {
+                formatter.write_fmt(format_args!(
+                    "\n{}\n",
+                    self.heading(&format!("Damage against AC {}:", target.ac))
+                ))?;
+
+                let width = target
+                    .pmf
+                    .iter()
+                    .map(|outcome| outcome.value.to_string().len())
+                    .max()
+                    .unwrap_or(0);
+                for outcome in target.pmf.iter() {
+                    formatter.write_fmt(format_args!(
+                        "  {:>width$}  {:>7}\n",
+                        outcome.value,
+                        percent(&outcome.p),
+                        width = width
+                    ))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
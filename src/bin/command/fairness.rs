@@ -0,0 +1,149 @@
+use super::verify::{write_buckets, write_impossible};
+use super::Command;
+use ibig::IBig;
+use owo_colors::OwoColorize;
+use roll::{expression::Expression, fairness::Fairness as Test, simulation::Simulation};
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub struct Fairness {
+    expression: Expression,
+    observed: PathBuf,
+    alpha: f64,
+}
+
+impl Fairness {
+    pub fn new(expression: Expression, observed: PathBuf, alpha: f64) -> Self {
+        Self {
+            expression,
+            observed,
+            alpha,
+        }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+}
+
+impl Command for Fairness {
+    type Output = Test;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        let observed = read_results(&self.observed)?;
+
+        Ok(Test::new(&observed, &self.expression.pmf()?, self.alpha))
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        fairness: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            expression: self.expression,
+            observed: self.observed,
+            fairness,
+        })
+    }
+}
+
+// Reads the results of rolling a die or dice, e.g. a d20's faces. Results are integers separated
+// by whitespace or commas, and `#` starts a comment.
+pub(super) fn read_results(path: &Path) -> Result<Simulation, anyhow::Error> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!(err).context(format!("Could not open {:?}.", path)))?;
+
+    let mut results = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+
+        for result in line.split(|c: char| c.is_whitespace() || c == ',') {
+            if result.is_empty() {
+                continue;
+            }
+
+            results.push(result.parse::<IBig>().map_err(|_| {
+                anyhow::anyhow!("{:?} line {}: {:?} is not an integer.", path, i + 1, result)
+            })?);
+        }
+    }
+
+    if results.is_empty() {
+        return Err(anyhow::anyhow!("{:?} has no results.", path));
+    }
+
+    Ok(Simulation::from_samples(results))
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    expression: Expression,
+    observed: PathBuf,
+    fairness: Test,
+}
+
+impl TextFormatter {
+    fn heading(&self, heading: &str) -> String {
+        match self.colors {
+            true => heading.magenta().bold().to_string(),
+            false => heading.to_string(),
+        }
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let fairness = &self.fairness;
+
+        formatter.write_fmt(format_args!(
+            "{} {}\n",
+            self.heading("Expression:"),
+            self.expression
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} {} results from {}\n",
+            self.heading("Observed:"),
+            fairness.results,
+            self.observed.display()
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} χ² = {:.2}, {} degrees of freedom, p = {:.4}\n",
+            self.heading("Chi-square:"),
+            fairness.chi_squared,
+            fairness.degrees_of_freedom,
+            fairness.chi_squared_p_value
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} D = {:.4}, p = {:.4}\n",
+            self.heading("Kolmogorov-Smirnov:"),
+            fairness.kolmogorov_smirnov,
+            fairness.kolmogorov_smirnov_p_value
+        ))?;
+
+        let verdict = match (fairness.fair(), self.colors) {
+            (true, true) => "fair".green().bold().to_string(),
+            (true, false) => String::from("fair"),
+            (false, true) => "UNFAIR".red().bold().to_string(),
+            (false, false) => String::from("UNFAIR"),
+        };
+        formatter.write_fmt(format_args!(
+            "{} {} at a significance level of {}\n",
+            self.heading("Verdict:"),
+            verdict,
+            fairness.alpha
+        ))?;
+
+        formatter.write_fmt(format_args!("\n{}\n", self.heading("Buckets:")))?;
+        write_buckets(formatter, &fairness.buckets, self.colors)?;
+
+        if !fairness.impossible.is_empty() {
+            formatter.write_fmt(format_args!("\n{}\n", self.heading("Impossible outcomes:")))?;
+            write_impossible(formatter, &fairness.impossible)?;
+        }
+
+        Ok(())
+    }
+}
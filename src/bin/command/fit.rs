@@ -0,0 +1,205 @@
+use super::fairness::read_results;
+use super::Command;
+use owo_colors::OwoColorize;
+use roll::fit::{self, Candidate, Space, Target};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+// Bounds the number of dice in a candidate, since the search grows with its square.
+const MAX_DICE: u32 = 12;
+
+#[derive(Debug)]
+pub struct Fit {
+    mean: Option<f64>,
+    standard_deviation: Option<f64>,
+    observed: Option<PathBuf>,
+    max_dice: u32,
+    top: usize,
+}
+
+impl Fit {
+    pub fn new(
+        mean: Option<f64>,
+        standard_deviation: Option<f64>,
+        observed: Option<PathBuf>,
+        max_dice: u32,
+        top: usize,
+    ) -> Self {
+        Self {
+            mean,
+            standard_deviation,
+            observed,
+            max_dice,
+            top,
+        }
+    }
+}
+
+/// The candidates nearest the target, nearest first.
+#[derive(Debug)]
+pub struct Fitted {
+    pub target: Target,
+    /// The number of results the target was estimated from, with `--observed`.
+    pub results: Option<u64>,
+    pub candidates: Vec<Candidate>,
+}
+
+impl Command for Fit {
+    type Output = Fitted;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        if !(1..=MAX_DICE).contains(&self.max_dice) {
+            return Err(anyhow::anyhow!(
+                "Candidates have from 1 to {} dice, not {}.",
+                MAX_DICE,
+                self.max_dice
+            ));
+        }
+        if let Some(standard_deviation) = self.standard_deviation {
+            if standard_deviation < 0.0 {
+                return Err(anyhow::anyhow!("A standard deviation is never negative."));
+            }
+        }
+
+        let (target, results) = match (&self.observed, self.mean, self.standard_deviation) {
+            (Some(observed), _, _) => {
+                let simulation = read_results(observed)?;
+                (Target::Pmf(simulation.pmf()), Some(simulation.samples()))
+            }
+            (None, None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Nothing to fit. Give a --mean, a --stddev or --observed results."
+                ))
+            }
+            (None, mean, standard_deviation) => (
+                Target::Statistics {
+                    mean,
+                    standard_deviation,
+                },
+                None,
+            ),
+        };
+
+        let space = Space {
+            max_dice: self.max_dice,
+            ..Space::default()
+        };
+        let candidates = fit::fit(&target, &space, self.top)?;
+
+        Ok(Fitted {
+            target,
+            results,
+            candidates,
+        })
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        fitted: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            observed: self.observed,
+            fitted,
+        })
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    observed: Option<PathBuf>,
+    fitted: Fitted,
+}
+
+impl TextFormatter {
+    fn heading(&self, heading: &str) -> String {
+        match self.colors {
+            true => heading.magenta().bold().to_string(),
+            false => heading.to_string(),
+        }
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let target = match (&self.fitted.target, &self.observed, self.fitted.results) {
+            (Target::Pmf(pmf), Some(observed), Some(results)) => format!(
+                "{} results from {}, mean {:.2}, std dev {:.2}",
+                results,
+                observed.display(),
+                pmf.expected_value(),
+                pmf.standard_deviation()
+            ),
+            (
+                Target::Statistics {
+                    mean,
+                    standard_deviation,
+                },
+                _,
+                _,
+            ) => mean
+                .map(|mean| format!("mean {}", mean))
+                .into_iter()
+                .chain(standard_deviation.map(|sd| format!("std dev {}", sd)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            (Target::Pmf(_), _, _) => String::from("a distribution"),
+        };
+        formatter.write_fmt(format_args!("{} {}\n", self.heading("Target:"), target))?;
+        // Statistics are compared by their Euclidean distance, distributions by the largest
+        // difference in the probability of any event.
+        let distance = match self.fitted.target {
+            Target::Statistics { .. } => "distance",
+            Target::Pmf(_) => "total variation",
+        };
+        formatter.write_str("\n")?;
+
+        let rows = self
+            .fitted
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(i, candidate)| {
+                [
+                    (i + 1).to_string(),
+                    candidate.expression.to_string(),
+                    format!("{:.2}", candidate.pmf.expected_value()),
+                    format!("{:.2}", candidate.pmf.standard_deviation()),
+                    format!("{:.4}", candidate.distance),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let header = ["rank", "expression", "mean", "std dev", distance];
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].len())
+                    .fold(header[i].len(), usize::max)
+            })
+            .collect::<Vec<_>>();
+
+        // The expressions are left aligned, and the numbers right aligned.
+        let cells = |row: &[&str]| {
+            row.iter()
+                .enumerate()
+                .map(|(i, cell)| match i {
+                    1 => format!("  {:<width$}", cell, width = widths[i]),
+                    _ => format!("  {:>width$}", cell, width = widths[i]),
+                })
+                .collect::<String>()
+        };
+
+        let header = cells(&header);
+        match self.colors {
+            true => formatter.write_fmt(format_args!("{}\n", header.trim_end().cyan().bold()))?,
+            false => formatter.write_fmt(format_args!("{}\n", header.trim_end()))?,
+        }
+        for row in rows.iter() {
+            let row = row.iter().map(String::as_str).collect::<Vec<_>>();
+            formatter.write_fmt(format_args!("{}\n", cells(&row)))?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,34 @@
+mod chart;
+mod compare;
+mod dpr;
+mod fairness;
+mod fit;
 mod pmf;
+mod presets;
+mod query;
+mod rng;
 mod roll;
+mod statistics;
+mod sweep;
+mod table;
+mod verify;
 
-pub use self::roll::Roll;
-pub use pmf::Pmf;
+pub use self::roll::{read_trace, Faces, Roll};
+pub use chart::{Chart, ChartStyle};
+pub use compare::{Compare, Comparison};
+pub use dpr::{ArmorClasses, Dpr, Target};
+pub use fairness::Fairness;
+pub use fit::{Fit, Fitted};
+pub use pmf::{Bins as PmfBins, Columns as PmfColumns, Distribution, Method as PmfMethod, Pmf};
+pub use presets::{PresetListing, Presets};
+pub use query::{Answer, Query};
+pub use rng::{Generator, Rng, RngBackend};
+pub use statistics::{Statistic, StatisticValue, Statistics};
+pub use sweep::{Sweep, SweepResult, Variable as SweepVariable};
+pub use table::{Table, TableDistribution, TablePmf, TableRoll};
+pub use verify::Verify;
+
+use ::roll::probability::Probability;
 
 pub trait Command {
     type Output;
@@ -15,3 +41,24 @@ pub trait Command {
         output: Self::Output,
     ) -> Box<dyn std::fmt::Display>;
 }
+
+/// Formats a probability as a percentage, e.g. `2.78%`. Probabilities that would round to
+/// `0.00%` are written in scientific notation, e.g. `1.23e-15%`, which is computed from the
+/// logarithm so that exact probabilities smaller than any `f64` still show their magnitude.
+pub fn percent<P: Probability>(p: &P) -> String {
+    let percent = p.to_f64() * 100.0;
+
+    if p.is_zero() || percent >= 0.005 {
+        return format!("{:.2}%", percent);
+    }
+
+    let log = p.log10() + 2.0;
+    let mut exponent = log.floor();
+    let mut mantissa = 10_f64.powf(log - exponent);
+    if mantissa >= 9.995 {
+        mantissa /= 10.0;
+        exponent += 1.0;
+    }
+
+    format!("{:.2}e{}%", mantissa, exponent)
+}
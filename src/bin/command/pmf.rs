@@ -1,111 +1,638 @@
-use super::Command;
+use super::statistics::{Statistic, Statistics};
+use super::{percent, Chart, ChartStyle, Command};
 use ibig::IBig;
 use owo_colors::OwoColorize;
 use roll::{
-    expression::Expression,
-    pmf::{Outcome, Pmf as ExpressionPmf},
+    expression::{Expression, PmfTree},
+    pmf::{Bin, Binning, Pmf as ExpressionPmf, Tail},
+    probability::{Probability, Ratio},
+    query::Event,
+    simulation::Simulation,
 };
 use std::fmt::{Display, Formatter};
-use terminal_size::terminal_size;
+
+// Text output bins distributions with more outcomes than this, unless asked not to.
+const AUTO_OUTCOMES: usize = 200;
+const AUTO_BINS: usize = 40;
 
 #[derive(Debug)]
 pub struct Pmf {
     expression: Expression,
+    columns: Columns,
+    statistics: Option<Statistics>,
+    given: Option<Event>,
+    method: Method,
+    bins: Bins,
+    tree: bool,
+}
+
+/// How the distribution is computed.
+#[derive(Clone, Copy, Debug)]
+pub enum Method {
+    /// Floating point probabilities.
+    Float,
+    /// Exact fractions, with `--exact`.
+    Exact,
+    /// Estimates from rolling the expression many times, with `--simulate`.
+    Simulate { samples: u64, seed: u64 },
+}
+
+/// Cumulative columns shown alongside the probability of each outcome.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Columns {
+    /// P(X <= x)
+    pub cdf: bool,
+    /// P(X >= x)
+    pub at_least: bool,
+}
+
+/// How the outcomes of a wide distribution are grouped into ranges. See `roll::pmf::Pmf::bin`.
+#[derive(Clone, Debug)]
+pub struct Bins {
+    /// The binning asked for, if any.
+    pub binning: Option<Binning>,
+    /// The most probability a tail collapsed into one bin has.
+    pub tail: f64,
+}
+
+impl Bins {
+    /// The binning of a distribution with `outcomes` outcomes. The text output bins more than
+    /// fit on a screen when no binning was asked for.
+    pub fn binning(&self, outcomes: usize, text: bool) -> Option<Binning> {
+        match &self.binning {
+            Some(binning) => Some(binning.clone()),
+            None if text && outcomes > AUTO_OUTCOMES => Some(Binning::Count(AUTO_BINS)),
+            None => None,
+        }
+    }
 }
 
 impl Pmf {
-    pub fn new(expression: Expression) -> Self {
-        Self { expression }
+    pub fn new(
+        expression: Expression,
+        columns: Columns,
+        statistics: Option<Statistics>,
+        given: Option<Event>,
+        method: Method,
+        bins: Bins,
+        tree: bool,
+    ) -> Self {
+        Self {
+            expression,
+            columns,
+            statistics,
+            given,
+            method,
+            bins,
+            tree,
+        }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn columns(&self) -> Columns {
+        self.columns
+    }
+
+    /// The statistics chosen on the command line, if any. Each output has its own default.
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.statistics.as_ref()
+    }
+
+    /// The event the distribution is conditioned on, if any.
+    pub fn given(&self) -> Option<&Event> {
+        self.given.as_ref()
+    }
+
+    pub fn bins(&self) -> &Bins {
+        &self.bins
     }
 }
 
 pub struct TextFormatter {
     colors: bool,
-    pmf: ExpressionPmf<IBig>,
+    chart: Chart,
+    columns: Columns,
+    bins: Bins,
+    statistics: Vec<Statistic>,
+    tree_statistics: Vec<Statistic>,
+    distribution: Distribution,
     expression: Expression,
+    given: Option<Event>,
+}
+
+/// The distribution of an expression. With `--exact`, its probabilities are also kept as exact
+/// ratios, and `pmf` is their nearest `f64`s. With `--simulate`, `pmf` is the distribution of the
+/// samples. With `--tree`, the distribution of each subexpression is kept too.
+#[derive(Debug)]
+pub struct Distribution {
+    pub pmf: ExpressionPmf<IBig>,
+    pub exact: Option<ExpressionPmf<IBig, Ratio>>,
+    pub simulation: Option<Simulation>,
+    pub tree: Option<PmfTree>,
+}
+
+impl Distribution {
+    /// The distribution grouped into bins, and its exact bins when there are exact probabilities.
+    /// The bins of both are the same, since the exact probabilities decide the tails.
+    pub fn bin(
+        &self,
+        binning: &Binning,
+        tail: f64,
+    ) -> (ExpressionPmf<Bin>, Option<ExpressionPmf<Bin, Ratio>>) {
+        match &self.exact {
+            Some(exact) => {
+                let exact = exact.bin(binning, tail);
+                (exact.to_f64(), Some(exact))
+            }
+            None => (self.pmf.bin(binning, tail), None),
+        }
+    }
 }
 
 impl Command for Pmf {
-    type Output = ExpressionPmf<IBig>;
+    type Output = Distribution;
 
     fn exec(&self) -> Result<Self::Output, anyhow::Error> {
-        self.expression.pmf()
+        match self.method {
+            // Clap keeps `--tree` apart from `--given`, so the tree's root is the distribution.
+            Method::Float if self.tree => {
+                let tree = self.expression.pmf_tree()?;
+
+                Ok(Distribution {
+                    pmf: tree.pmf.clone(),
+                    exact: None,
+                    simulation: None,
+                    tree: Some(tree),
+                })
+            }
+            Method::Float => {
+                let pmf = self.expression.pmf()?;
+
+                Ok(Distribution {
+                    pmf: match &self.given {
+                        Some(given) => given.condition(&pmf)?,
+                        None => pmf,
+                    },
+                    exact: None,
+                    simulation: None,
+                    tree: None,
+                })
+            }
+            Method::Exact if self.tree => {
+                let tree = self.expression.exact_pmf_tree()?;
+
+                Ok(Distribution {
+                    pmf: tree.pmf.to_f64(),
+                    exact: Some(tree.pmf.clone()),
+                    simulation: None,
+                    tree: Some(tree.to_f64()),
+                })
+            }
+            Method::Exact => {
+                let mut exact = self.expression.exact_pmf()?;
+                if let Some(given) = &self.given {
+                    exact = given.condition(&exact)?;
+                }
+
+                Ok(Distribution {
+                    pmf: exact.to_f64(),
+                    exact: Some(exact),
+                    simulation: None,
+                    tree: None,
+                })
+            }
+            Method::Simulate { samples, seed } => {
+                if samples == 0 {
+                    return Err(anyhow::anyhow!("A simulation needs at least one sample."));
+                }
+
+                let mut simulation = self.expression.simulate(samples, seed)?;
+                if let Some(given) = &self.given {
+                    simulation = simulation.condition(|value| given.contains(value));
+                    if simulation.samples() == 0 {
+                        return Err(anyhow::anyhow!(
+                            "The condition {} never occurred in {} samples.",
+                            given,
+                            samples
+                        ));
+                    }
+                }
+
+                Ok(Distribution {
+                    pmf: simulation.pmf(),
+                    exact: None,
+                    simulation: Some(simulation),
+                    tree: None,
+                })
+            }
+        }
     }
 
     fn formatter(
         self,
         args: crate::cli::Arguments,
-        pmf: Self::Output,
+        distribution: Self::Output,
     ) -> Box<dyn std::fmt::Display> {
+        // A single mean says little about each subexpression, so the tree shows a few more.
+        let tree_statistics = match &self.statistics {
+            Some(statistics) => statistics.0.clone(),
+            None => vec![
+                Statistic::Min,
+                Statistic::Mean,
+                Statistic::StandardDeviation,
+                Statistic::Max,
+            ],
+        };
+
+        // Without colors, only the statistics asked for are printed above the distribution.
+        let colors = args.use_colors();
+        let statistics = match self.statistics {
+            Some(statistics) => statistics.0,
+            None if colors => vec![Statistic::Mean],
+            None => vec![],
+        };
+
         Box::from(TextFormatter {
-            colors: args.use_colors(),
-            pmf,
+            colors,
+            chart: args.chart(),
+            columns: self.columns,
+            bins: self.bins,
+            statistics,
+            tree_statistics,
+            distribution,
             expression: self.expression,
+            given: self.given,
         })
     }
 }
 
 impl Display for TextFormatter {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        self.write_distribution(formatter)?;
+        if let Some(tree) = &self.distribution.tree {
+            self.write_tree(formatter, tree)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl TextFormatter {
+    fn write_distribution(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
         if self.colors {
             formatter.write_fmt(format_args!(
                 "{} {}\n",
                 "Expression:".magenta().bold(),
                 self.expression.to_string().blue(),
             ))?;
-            formatter.write_fmt(format_args!(
-                "  {} {:.2}\n\n",
-                "Mean:".cyan().bold(),
-                self.pmf.expected_value(),
-            ))?;
+            if let Some(given) = &self.given {
+                formatter.write_fmt(format_args!(
+                    "{} {}\n",
+                    "Given:".magenta().bold(),
+                    given.to_string().blue(),
+                ))?;
+            }
+            for statistic in self.statistics.iter() {
+                formatter.write_fmt(format_args!(
+                    "  {} {}\n",
+                    statistic.label().cyan().bold(),
+                    statistic.value(&self.distribution.pmf),
+                ))?;
+            }
+            if let Some(simulation) = &self.distribution.simulation {
+                formatter.write_fmt(format_args!(
+                    "{} {}\n",
+                    "Samples:".magenta().bold(),
+                    simulation.samples().to_string().blue(),
+                ))?;
+                formatter.write_fmt(format_args!(
+                    "  {} {:.4}\n",
+                    "Std error of mean:".cyan().bold(),
+                    simulation.standard_error(),
+                ))?;
+                formatter.write_fmt(format_args!(
+                    "  {} ±{}\n",
+                    "Max error (95%):".cyan().bold(),
+                    percent(&simulation.max_error()),
+                ))?;
+            }
+            formatter.write_str("\n")?;
+        } else if !self.statistics.is_empty() {
+            for statistic in self.statistics.iter() {
+                formatter.write_fmt(format_args!(
+                    "  {} {}\n",
+                    statistic.label(),
+                    statistic.value(&self.distribution.pmf),
+                ))?;
+            }
+            formatter.write_str("\n")?;
         }
 
-        let max_digits = self
-            .pmf
-            .iter()
-            .last()
-            .map(|outcome| outcome.value.to_string().len())
-            .unwrap_or(0);
-        let max_p = self
-            .pmf
+        let distribution = &self.distribution;
+        let binned = self
+            .bins
+            .binning(distribution.pmf.len(), true)
+            .map(|binning| distribution.bin(&binning, self.bins.tail));
+
+        // The exact probabilities are more precise, when there are any. Exact fractions or
+        // confidence intervals follow the percentages, though binned outcomes have no intervals.
+        let (labels, ps, (probabilities, cdf, at_least), notes) = match &binned {
+            Some((bins, exact)) => (
+                bins.iter().map(|outcome| label(&outcome.value)).collect(),
+                bins.iter().map(|outcome| outcome.p).collect(),
+                match exact {
+                    Some(exact) => percents(exact, self.columns),
+                    None => percents(bins, self.columns),
+                },
+                exact.as_ref().map(fractions),
+            ),
+            None => (
+                distribution
+                    .pmf
+                    .iter()
+                    .map(|outcome| outcome.value.to_string())
+                    .collect::<Vec<_>>(),
+                distribution
+                    .pmf
+                    .iter()
+                    .map(|outcome| outcome.p)
+                    .collect::<Vec<_>>(),
+                match &distribution.exact {
+                    Some(exact) => percents(exact, self.columns),
+                    None => percents(&distribution.pmf, self.columns),
+                },
+                match (&distribution.exact, &distribution.simulation) {
+                    (Some(exact), _) => Some(fractions(exact)),
+                    (None, Some(simulation)) => Some(
+                        simulation
+                            .confidence_intervals()
+                            .iter()
+                            .map(|(low, high)| format!("[{}, {}]", percent(low), percent(high)))
+                            .collect(),
+                    ),
+                    (None, None) => None,
+                },
+            ),
+        };
+        let max_digits = labels.iter().map(String::len).max().unwrap_or(0);
+        let max_p = ps
             .iter()
-            .map(|outcome| outcome.p)
+            .copied()
             .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap();
+        let extra_columns = cdf.is_some() as usize + at_least.is_some() as usize;
+
+        // Percentages of 100% or in scientific notation fill the usual width, and need a space
+        // before them when there are no bars.
+        let width = |percents: &[String]| percents.iter().map(String::len).max().unwrap_or(0);
+        let p_width = match width(&probabilities) {
+            0..=6 => 7,
+            width => width + 1,
+        };
+        let column_width = cdf
+            .iter()
+            .chain(at_least.iter())
+            .map(|percents| width(percents))
+            .fold(7, usize::max);
+
+        let style = self.chart.style(self.colors);
+        if let ChartStyle::Vertical | ChartStyle::Sparkline = style {
+            let lines = match style {
+                ChartStyle::Vertical => self.chart.vertical(&labels, &ps),
+                _ => vec![self.chart.sparkline(&labels, &ps)],
+            };
+            for line in lines {
+                formatter.write_fmt(format_args!("{}\n", line))?;
+            }
 
-        for Outcome { p, value: roll } in self.pmf.iter() {
-            let padding = "  ";
+            // The table follows only for the columns the chart can't show.
+            if extra_columns == 0 && notes.is_none() {
+                return Ok(());
+            }
+            formatter.write_str("\n")?;
+        }
+
+        let padding = " ";
+        let percent_chars = p_width + 1;
+        let bar_width = match style {
+            ChartStyle::Horizontal => {
+                let other_chars = padding.len()
+                    + max_digits
+                    + padding.len()
+                    + padding.len()
+                    + percent_chars
+                    + (column_width + 2) * extra_columns;
+
+                Some(self.chart.width().max(other_chars) - other_chars)
+            }
+            _ => None,
+        };
+
+        // Pads the bars to a fixed width so the cumulative columns line up under their headers.
+        let bar_column = match bar_width {
+            Some(width) if extra_columns > 0 => width + 2 * padding.len(),
+            Some(_) | None => 0,
+        };
+
+        if self.colors && extra_columns > 0 {
             formatter.write_fmt(format_args!(
-                "{}{:>align$}",
-                padding,
-                roll.if_supports_color(owo_colors::Stream::Stdout, |text| text
+                "  {:width$}{:>p_width$}",
+                "",
+                "exactly",
+                width = max_digits + bar_column,
+                p_width = p_width,
+            ))?;
+            if cdf.is_some() {
+                formatter.write_fmt(format_args!(
+                    "{:>width$}",
+                    "at most",
+                    width = column_width + 2
+                ))?;
+            }
+            if at_least.is_some() {
+                formatter.write_fmt(format_args!(
+                    "{:>width$}",
+                    "at least",
+                    width = column_width + 2
+                ))?;
+            }
+            formatter.write_str("\n")?;
+        }
+
+        for (i, (label, p)) in labels.iter().zip(ps.iter()).enumerate() {
+            formatter.write_fmt(format_args!(
+                "  {:>align$}",
+                label.if_supports_color(owo_colors::Stream::Stdout, |text| text
                     .style(owo_colors::Style::new().blue().bold())),
                 align = max_digits
             ))?;
 
+            if let Some(max_width) = bar_width {
+                let bar_width = ((p / max_p) * max_width as f64) as usize;
+                let fill = match extra_columns {
+                    0 => 0,
+                    _ => max_width - bar_width,
+                };
+
+                let bar = self.chart.bar().repeat(bar_width);
+                formatter.write_fmt(format_args!(
+                    "{}{}{}{}",
+                    padding,
+                    match self.colors {
+                        true => bar.blue().to_string(),
+                        false => bar,
+                    },
+                    " ".repeat(fill),
+                    padding
+                ))?;
+            }
+
+            formatter.write_fmt(format_args!(
+                "{:>width$}",
+                probabilities[i],
+                width = p_width
+            ))?;
+            if let Some(cdf) = &cdf {
+                formatter.write_fmt(format_args!("  {:>width$}", cdf[i], width = column_width))?;
+            }
+            if let Some(at_least) = &at_least {
+                formatter.write_fmt(format_args!(
+                    "  {:>width$}",
+                    at_least[i],
+                    width = column_width
+                ))?;
+            }
+            if let Some(notes) = &notes {
+                formatter.write_fmt(format_args!("  {}", notes[i]))?;
+            }
+            formatter.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+
+    // Each subexpression indented under the one it is an operand of, beside its statistics.
+    fn write_tree(&self, formatter: &mut Formatter, tree: &PmfTree) -> Result<(), std::fmt::Error> {
+        let mut rows = vec![];
+        self.tree_rows(tree, "", "", &mut rows);
+
+        let headers = self
+            .tree_statistics
+            .iter()
+            .map(Statistic::name)
+            .collect::<Vec<_>>();
+        let label_width = rows
+            .iter()
+            .map(|(label, _)| label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let widths = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                rows.iter()
+                    .map(|(_, values)| values[i].len())
+                    .fold(header.len(), usize::max)
+            })
+            .collect::<Vec<_>>();
+
+        // The header names the columns, so it is written even without colors.
+        formatter.write_str("\n")?;
+        if self.colors {
+            formatter.write_fmt(format_args!("{}\n", "Subexpressions:".magenta().bold()))?;
+        }
+        formatter.write_fmt(format_args!("  {:width$}", "", width = label_width))?;
+        for (header, width) in headers.iter().zip(widths.iter()) {
+            let header = format!("{:>width$}", header, width = width);
             if self.colors {
-                if let Some(width) = terminal_size().map(|(width, _)| width) {
-                    let padding = " ";
-                    let percent_chars = 8;
-                    let other_chars =
-                        padding.len() + max_digits + padding.len() + padding.len() + percent_chars;
-
-                    let max_width =
-                        Into::<usize>::into(width.0).min(75).max(other_chars) - other_chars;
-                    let bar_width = (p / max_p) * max_width as f64;
-
-                    formatter.write_fmt(format_args!(
-                        "{}{}{}",
-                        padding,
-                        "▬".repeat(bar_width as usize).blue(),
-                        padding
-                    ))?;
-                }
+                formatter.write_fmt(format_args!("  {}", header.cyan().bold()))?;
+            } else {
+                formatter.write_fmt(format_args!("  {}", header))?;
             }
+        }
+        formatter.write_str("\n")?;
 
-            formatter.write_fmt(format_args!("{:>6.2}%\n", p * 100.0))?;
+        for (label, values) in rows {
+            let padding = label_width - label.chars().count();
+            formatter.write_fmt(format_args!("  {}{}", label, " ".repeat(padding)))?;
+            for (value, width) in values.iter().zip(widths.iter()) {
+                formatter.write_fmt(format_args!("  {:>width$}", value, width = width))?;
+            }
+            formatter.write_str("\n")?;
         }
 
         Ok(())
     }
+
+    // Adds a row for `tree` and its operands after `rows`. Literals are left out, since their
+    // statistics are the literal itself.
+    fn tree_rows(
+        &self,
+        tree: &PmfTree,
+        connector: &str,
+        indent: &str,
+        rows: &mut Vec<(String, Vec<String>)>,
+    ) {
+        rows.push((
+            format!("{}{}", connector, tree.expression),
+            self.tree_statistics
+                .iter()
+                .map(|statistic| statistic.value(&tree.pmf).to_string())
+                .collect(),
+        ));
+
+        let (branch, last, line, space) = match self.chart.ascii {
+            true => ("|- ", "`- ", "|  ", "   "),
+            false => ("├─ ", "└─ ", "│  ", "   "),
+        };
+        let operands = tree
+            .operands
+            .iter()
+            .filter(|operand| !matches!(operand.expression, Expression::Literal(_)))
+            .collect::<Vec<_>>();
+        for (i, operand) in operands.iter().enumerate() {
+            let (connector, next) = match i + 1 == operands.len() {
+                true => (last, space),
+                false => (branch, line),
+            };
+
+            self.tree_rows(
+                operand,
+                &format!("{}{}", indent, connector),
+                &format!("{}{}", indent, next),
+                rows,
+            );
+        }
+    }
+}
+
+// A bin's outcomes, e.g. `10..19`, `< 5` for the tail below the others or `> 95` above them.
+fn label(bin: &Bin) -> String {
+    match bin.tail {
+        Some(Tail::Below) => format!("< {}", &bin.max + IBig::from(1u8)),
+        Some(Tail::Above) => format!("> {}", &bin.min - IBig::from(1u8)),
+        None if bin.min == bin.max => bin.min.to_string(),
+        None => format!("{}..{}", bin.min, bin.max),
+    }
+}
+
+fn fractions<A: Ord>(exact: &ExpressionPmf<A, Ratio>) -> Vec<String> {
+    exact.iter().map(|outcome| outcome.p.to_string()).collect()
+}
+
+// The probability of each outcome, and the chosen cumulative columns, as percentages.
+fn percents<A: Ord, P: Probability>(
+    pmf: &ExpressionPmf<A, P>,
+    columns: Columns,
+) -> (Vec<String>, Option<Vec<String>>, Option<Vec<String>>) {
+    let format = |probabilities: Vec<P>| probabilities.iter().map(percent).collect::<Vec<_>>();
+
+    (
+        pmf.iter().map(|outcome| percent(&outcome.p)).collect(),
+        columns.cdf.then(|| format(pmf.cdf())),
+        columns.at_least.then(|| format(pmf.at_least())),
+    )
 }
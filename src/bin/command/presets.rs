@@ -0,0 +1,73 @@
+use super::Command;
+use owo_colors::OwoColorize;
+use roll::expression::{Expression, Preset, PRESETS};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub struct Presets;
+
+#[derive(Debug)]
+pub struct PresetListing {
+    pub preset: &'static Preset,
+    pub expansion: String,
+}
+
+impl Command for Presets {
+    type Output = Vec<PresetListing>;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        PRESETS
+            .iter()
+            .map(|preset| {
+                let expansion = Expression::from_str(preset.example)
+                    .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+
+                Ok(PresetListing {
+                    preset,
+                    expansion: expansion.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        output: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            presets: output,
+        })
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    presets: Vec<PresetListing>,
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        for (i, listing) in self.presets.iter().enumerate() {
+            if i > 0 {
+                formatter.write_str("\n")?;
+            }
+
+            if self.colors {
+                formatter.write_fmt(format_args!("{}\n", listing.preset.usage.magenta().bold()))?;
+            } else {
+                formatter.write_fmt(format_args!("{}\n", listing.preset.usage))?;
+            }
+
+            formatter.write_fmt(format_args!("  {}\n", listing.preset.description))?;
+            formatter.write_fmt(format_args!(
+                "  {} = {}\n",
+                listing.preset.example, listing.expansion
+            ))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use super::{percent, Command};
+use owo_colors::OwoColorize;
+use roll::{
+    expression::Expression,
+    probability::{Probability, Ratio},
+    query::{self, Event},
+};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub struct Query {
+    expression: Expression,
+    query: query::Query,
+    given: Option<Event>,
+    exact: bool,
+}
+
+impl Query {
+    pub fn new(
+        expression: Expression,
+        query: query::Query,
+        given: Option<Event>,
+        exact: bool,
+    ) -> Self {
+        Self {
+            expression,
+            query,
+            given,
+            exact,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Answer {
+    pub expression: Expression,
+    pub query: query::Query,
+    pub given: Option<Event>,
+    pub p: f64,
+    /// The exact probability, with `--exact`.
+    pub exact: Option<Ratio>,
+}
+
+impl Command for Query {
+    type Output = Answer;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        let (p, exact) = if self.exact {
+            let mut pmf = self.expression.exact_pmf()?;
+            if let Some(given) = &self.given {
+                pmf = given.condition(&pmf)?;
+            }
+
+            let exact = self.query.probability(&pmf)?;
+            (exact.to_f64(), Some(exact))
+        } else {
+            let mut pmf = self.expression.pmf()?;
+            if let Some(given) = &self.given {
+                pmf = given.condition(&pmf)?;
+            }
+
+            (self.query.probability(&pmf)?, None)
+        };
+
+        Ok(Answer {
+            expression: self.expression.clone(),
+            query: self.query.clone(),
+            given: self.given.clone(),
+            p,
+            exact,
+        })
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        output: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            answer: output,
+        })
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    answer: Answer,
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let p = match &self.answer.exact {
+            Some(exact) => format!("{} ({})", percent(exact), exact),
+            None => percent(&self.answer.p),
+        };
+
+        if !self.colors {
+            return formatter.write_str(&p);
+        }
+
+        formatter.write_fmt(format_args!(
+            "{} {}\n",
+            "Expression:".magenta().bold(),
+            self.answer.expression.to_string().blue()
+        ))?;
+        if let Some(given) = &self.answer.given {
+            formatter.write_fmt(format_args!(
+                "{} {}\n",
+                "Given:".magenta().bold(),
+                given.to_string().blue()
+            ))?;
+        }
+        formatter.write_fmt(format_args!(
+            "{} {}\n\n",
+            "Query:".magenta().bold(),
+            self.answer.query.to_string().blue()
+        ))?;
+
+        formatter.write_fmt(format_args!("{}", p.blue()))
+    }
+}
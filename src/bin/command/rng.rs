@@ -0,0 +1,79 @@
+use clap::ArgEnum;
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+use roll::expression::{Evaluand, Expression};
+use std::fmt::{Display, Formatter};
+
+/// The generators `--rng` chooses between.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RngBackend {
+    /// A PRNG seeded with `--seed`, or a random seed, so rolls can be reproduced.
+    Seeded,
+    /// The operating system's entropy for every die.
+    Os,
+}
+
+/// Where random rolls come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rng {
+    Seeded(u64),
+    Os,
+}
+
+/// A generator for many rolls, e.g. the rolls on nested tables.
+pub enum Generator {
+    Seeded(Box<StdRng>),
+    Os,
+}
+
+impl Rng {
+    pub fn eval(&self, expression: &Expression) -> Result<Evaluand, anyhow::Error> {
+        match self {
+            Rng::Seeded(seed) => expression.eval(*seed),
+            Rng::Os => expression.eval_with_rng(&mut OsRng),
+        }
+    }
+
+    pub fn generator(&self) -> Generator {
+        match self {
+            Rng::Seeded(seed) => Generator::Seeded(Box::new(StdRng::seed_from_u64(*seed))),
+            Rng::Os => Generator::Os,
+        }
+    }
+
+    /// The backend's name, as `--rng` takes it.
+    pub fn backend(&self) -> &'static str {
+        match self {
+            Rng::Seeded(_) => "seeded",
+            Rng::Os => "os",
+        }
+    }
+
+    /// The seed, which rolls the same again, if seeded.
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            Rng::Seeded(seed) => Some(*seed),
+            Rng::Os => None,
+        }
+    }
+}
+
+impl Generator {
+    /// Rolls `expression`. A seeded generator seeds each roll in turn, so each can be reproduced
+    /// on its own.
+    pub fn eval(&mut self, expression: &Expression) -> Result<Evaluand, anyhow::Error> {
+        match self {
+            Generator::Seeded(rng) => expression.eval(rng.next_u64()),
+            Generator::Os => expression.eval_with_rng(&mut OsRng),
+        }
+    }
+}
+
+impl Display for Rng {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Rng::Seeded(seed) => formatter.write_fmt(format_args!("seeded (seed {})", seed)),
+            Rng::Os => formatter.write_str("os entropy"),
+        }
+    }
+}
@@ -1,27 +1,149 @@
-use super::Command;
-use ibig::UBig;
+use super::{Command, Rng};
+use ibig::{IBig, UBig};
 use num_traits::One;
 use owo_colors::OwoColorize;
-use roll::expression::{Evaluand, Expression};
+use roll::expression::{Evaluand, Expression, Trace};
+use serde::Deserialize;
 use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct Roll {
     expression: Expression,
-    seed: u64,
+    rng: Rng,
+    faces: Option<Vec<IBig>>,
 }
 
 impl Roll {
-    pub fn new(expression: Expression, seed: u64) -> Self {
-        Self { expression, seed }
+    /// Rolls `expression`, or replays `faces` in place of random rolls when there are any.
+    pub fn new(expression: Expression, rng: Rng, faces: Option<Vec<IBig>>) -> Self {
+        Self {
+            expression,
+            rng,
+            faces,
+        }
+    }
+
+    /// The generator rolled with, unless the faces were replayed.
+    pub fn rng(&self) -> Option<Rng> {
+        match self.faces {
+            Some(_) => None,
+            None => Some(self.rng),
+        }
+    }
+}
+
+/// The faces of dice rolled elsewhere, e.g. `"14, 3, 5"`, in the order the expression rolls them.
+#[derive(Clone, Debug)]
+pub struct Faces(pub Vec<IBig>);
+
+impl FromStr for Faces {
+    type Err = anyhow::Error;
+
+    fn from_str(faces: &str) -> Result<Self, Self::Err> {
+        faces
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|face| !face.is_empty())
+            .map(|face| {
+                IBig::from_str(face).map_err(|_| {
+                    anyhow::anyhow!(
+                        "{:?} is not a face. Expected integers separated by commas, e.g. \"14,3,5\".",
+                        face
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Faces)
+    }
+}
+
+// A roll saved with `--json`. Only its trace is needed to replay it.
+#[derive(Deserialize)]
+struct SavedRoll {
+    trace: SavedTrace,
+}
+
+#[derive(Deserialize)]
+struct SavedTrace {
+    expression: String,
+    value: Integer,
+    #[serde(default)]
+    rolls: Vec<Integer>,
+    #[serde(default)]
+    draws: Vec<Integer>,
+    operands: Vec<SavedTrace>,
+}
+
+// Integers too large for JSON numbers are saved as strings.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Integer {
+    Number(i64),
+    String(String),
+}
+
+impl Integer {
+    fn to_ibig(&self) -> Result<IBig, anyhow::Error> {
+        match self {
+            Integer::Number(n) => Ok(IBig::from(*n)),
+            Integer::String(n) => {
+                IBig::from_str(n).map_err(|_| anyhow::anyhow!("{:?} is not an integer.", n))
+            }
+        }
+    }
+}
+
+impl SavedTrace {
+    fn to_trace(&self) -> Result<Trace, anyhow::Error> {
+        let rolls = self
+            .rolls
+            .iter()
+            .map(|roll| {
+                let roll = roll.to_ibig()?;
+                UBig::try_from(&roll)
+                    .map_err(|_| anyhow::anyhow!("{} is not a roll of a die.", roll))
+            })
+            .collect::<Result<_, anyhow::Error>>()?;
+
+        Ok(Trace {
+            expression: Expression::from_str(&self.expression)?,
+            value: self.value.to_ibig()?,
+            rolls,
+            draws: self
+                .draws
+                .iter()
+                .map(Integer::to_ibig)
+                .collect::<Result<_, _>>()?,
+            operands: self
+                .operands
+                .iter()
+                .map(SavedTrace::to_trace)
+                .collect::<Result<_, _>>()?,
+        })
     }
 }
 
+/// Reads the trace of a roll saved with `--json`, to replay it.
+pub fn read_trace(path: &Path) -> Result<Trace, anyhow::Error> {
+    let file = std::fs::File::open(path)
+        .map_err(|err| anyhow::anyhow!(err).context(format!("Could not open {:?}.", path)))?;
+    let invalid = || format!("{:?} is not a roll saved with --json.", path);
+
+    let saved: SavedRoll = serde_json::from_reader(std::io::BufReader::new(file))
+        .map_err(|err| anyhow::anyhow!(err).context(invalid()))?;
+
+    saved.trace.to_trace().map_err(|err| err.context(invalid()))
+}
+
 impl Command for Roll {
     type Output = Evaluand;
 
     fn exec(&self) -> Result<Self::Output, anyhow::Error> {
-        self.expression.eval(self.seed)
+        match &self.faces {
+            Some(faces) => self.expression.replay(faces.clone()),
+            None => self.rng.eval(&self.expression),
+        }
     }
 
     fn formatter(
@@ -31,6 +153,7 @@ impl Command for Roll {
     ) -> Box<dyn std::fmt::Display> {
         Box::from(TextFormatter {
             colors: args.use_colors(),
+            trace: args.use_trace(),
             evaluand: output,
             expression: self.expression,
         })
@@ -39,15 +162,20 @@ impl Command for Roll {
 
 pub struct TextFormatter {
     colors: bool,
+    trace: bool,
     evaluand: Evaluand,
     expression: Expression,
 }
 
 impl Display for TextFormatter {
     fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
-        if !self.colors {
+        if !self.trace {
             return formatter.write_fmt(format_args!("{}", self.evaluand.value));
         }
+        if !self.colors {
+            let rolls = self.evaluand.trace.annotate(&|roll, _| roll.to_string());
+            return formatter.write_fmt(format_args!("{} = {}", rolls, self.evaluand.value));
+        }
 
         let red = owo_colors::Style::new().fg::<owo_colors::colors::Red>();
         let default = owo_colors::Style::new();
@@ -59,38 +187,24 @@ impl Display for TextFormatter {
             self.expression.to_string().blue()
         ))?;
 
-        formatter.write_fmt(format_args!("{}\n", "Rolls:".magenta().bold()))?;
-        for (i, (dice, dice_rolls)) in self.evaluand.rolls.iter().enumerate() {
-            formatter.write_fmt(format_args!("  d{}: {{", dice))?;
-
-            let mut dice_rolls = dice_rolls.clone();
-            dice_rolls.sort_unstable();
-
-            for (i, roll) in dice_rolls.iter().enumerate() {
-                let style = if *roll == UBig::one() {
-                    red
-                } else if roll == dice {
-                    green
-                } else {
-                    default
-                };
+        // Each die is written beside the dice it was rolled with, ones in red and the highest
+        // roll in green.
+        let rolls = self.evaluand.trace.annotate(&|roll, sides| {
+            let style = if *roll == UBig::one() {
+                red
+            } else if roll == sides {
+                green
+            } else {
+                default
+            };
 
-                formatter.write_fmt(format_args!("{}", style.style(roll)))?;
-
-                if i < dice_rolls.len() - 1 {
-                    formatter.write_str(", ")?;
-                }
-            }
-
-            formatter.write_str("}")?;
-
-            if i < self.evaluand.rolls.len() - 1 {
-                formatter.write_str("\n")?;
-            }
-        }
+            style.style(roll).to_string()
+        });
 
         formatter.write_fmt(format_args!(
-            "\n\n{}",
+            "{}\n  {} = {}",
+            "Rolls:".magenta().bold(),
+            rolls,
             self.evaluand.value.to_string().blue()
         ))?;
 
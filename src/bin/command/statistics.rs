@@ -0,0 +1,181 @@
+use ibig::IBig;
+use roll::pmf::Pmf;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A summary of a distribution, named as it is on the command line, e.g. `stddev` or `p90`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Statistic {
+    Min,
+    Max,
+    Mean,
+    Variance,
+    StandardDeviation,
+    Median,
+    Modes,
+    /// A percentile, from 0 to 100.
+    Quantile(f64),
+    Skewness,
+    Kurtosis,
+    Entropy,
+}
+
+/// The statistics to display, parsed from a comma separated list, e.g. `mean,stddev,p90`.
+#[derive(Clone, Debug)]
+pub struct Statistics(pub Vec<Statistic>);
+
+#[derive(Clone, Debug)]
+pub enum StatisticValue {
+    Integer(Option<IBig>),
+    Integers(Vec<IBig>),
+    /// Some statistics, e.g. the skewness of a constant, are undefined.
+    Real(Option<f64>),
+}
+
+const ALL: [Statistic; 10] = [
+    Statistic::Min,
+    Statistic::Max,
+    Statistic::Mean,
+    Statistic::Variance,
+    Statistic::StandardDeviation,
+    Statistic::Median,
+    Statistic::Modes,
+    Statistic::Skewness,
+    Statistic::Kurtosis,
+    Statistic::Entropy,
+];
+
+impl Statistic {
+    pub fn name(&self) -> String {
+        match self {
+            Statistic::Min => String::from("min"),
+            Statistic::Max => String::from("max"),
+            Statistic::Mean => String::from("mean"),
+            Statistic::Variance => String::from("variance"),
+            Statistic::StandardDeviation => String::from("stddev"),
+            Statistic::Median => String::from("median"),
+            Statistic::Modes => String::from("modes"),
+            Statistic::Quantile(percent) => format!("p{}", percent),
+            Statistic::Skewness => String::from("skewness"),
+            Statistic::Kurtosis => String::from("kurtosis"),
+            Statistic::Entropy => String::from("entropy"),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Statistic::Min => String::from("Min:"),
+            Statistic::Max => String::from("Max:"),
+            Statistic::Mean => String::from("Mean:"),
+            Statistic::Variance => String::from("Variance:"),
+            Statistic::StandardDeviation => String::from("Std dev:"),
+            Statistic::Median => String::from("Median:"),
+            Statistic::Modes => String::from("Mode:"),
+            Statistic::Quantile(percent) => format!("{}th percentile:", percent),
+            Statistic::Skewness => String::from("Skewness:"),
+            Statistic::Kurtosis => String::from("Kurtosis:"),
+            Statistic::Entropy => String::from("Entropy:"),
+        }
+    }
+
+    pub fn value(&self, pmf: &Pmf<IBig>) -> StatisticValue {
+        match self {
+            Statistic::Min => StatisticValue::Integer(pmf.iter().next().map(|o| o.value.clone())),
+            Statistic::Max => StatisticValue::Integer(pmf.iter().last().map(|o| o.value.clone())),
+            Statistic::Mean => StatisticValue::Real(Some(pmf.expected_value())),
+            Statistic::Variance => StatisticValue::Real(Some(pmf.variance())),
+            Statistic::StandardDeviation => StatisticValue::Real(Some(pmf.standard_deviation())),
+            Statistic::Median => StatisticValue::Integer(pmf.median().cloned()),
+            Statistic::Modes => {
+                StatisticValue::Integers(pmf.modes().into_iter().cloned().collect())
+            }
+            Statistic::Quantile(percent) => {
+                StatisticValue::Integer(pmf.quantile(percent / 100.0).cloned())
+            }
+            Statistic::Skewness => StatisticValue::Real(pmf.skewness()),
+            Statistic::Kurtosis => StatisticValue::Real(pmf.kurtosis()),
+            Statistic::Entropy => StatisticValue::Real(Some(pmf.entropy())),
+        }
+    }
+}
+
+impl FromStr for Statistic {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let statistic = match name.trim() {
+            "min" => Statistic::Min,
+            "max" => Statistic::Max,
+            "mean" => Statistic::Mean,
+            "variance" => Statistic::Variance,
+            "stddev" => Statistic::StandardDeviation,
+            "median" => Statistic::Median,
+            "mode" | "modes" => Statistic::Modes,
+            "skewness" => Statistic::Skewness,
+            "kurtosis" => Statistic::Kurtosis,
+            "entropy" => Statistic::Entropy,
+            name => match name.strip_prefix('p').map(f64::from_str) {
+                Some(Ok(percent)) if (0.0..=100.0).contains(&percent) => {
+                    Statistic::Quantile(percent)
+                }
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unknown statistic {:?}. Expected all, min, max, mean, variance, stddev, \
+                        median, mode, skewness, kurtosis, entropy or a percentile, e.g. p90.",
+                        name
+                    ))
+                }
+            },
+        };
+
+        Ok(statistic)
+    }
+}
+
+impl FromStr for Statistics {
+    type Err = anyhow::Error;
+
+    fn from_str(names: &str) -> Result<Self, Self::Err> {
+        let mut statistics = vec![];
+
+        for name in names.split(',') {
+            match name.trim() {
+                "all" => statistics.extend(ALL),
+                name => statistics.push(name.parse()?),
+            }
+        }
+
+        Ok(Self(statistics))
+    }
+}
+
+impl StatisticValue {
+    /// The value as a number, if it is a single number.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            StatisticValue::Integer(n) => n.as_ref().map(IBig::to_f64),
+            StatisticValue::Integers(ns) => match ns.as_slice() {
+                [n] => Some(n.to_f64()),
+                _ => None,
+            },
+            StatisticValue::Real(x) => *x,
+        }
+    }
+}
+
+impl Display for StatisticValue {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            StatisticValue::Integer(Some(n)) => formatter.write_fmt(format_args!("{}", n)),
+            StatisticValue::Integers(ns) => {
+                let ns = ns.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+                formatter.write_str(&ns.join(", "))
+            }
+            StatisticValue::Real(Some(x)) => formatter.write_fmt(format_args!("{:.2}", x)),
+            StatisticValue::Integer(None) | StatisticValue::Real(None) => {
+                formatter.write_str("undefined")
+            }
+        }
+    }
+}
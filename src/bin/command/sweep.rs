@@ -0,0 +1,459 @@
+use super::statistics::{Statistic, Statistics};
+use super::{percent, Command};
+use ibig::IBig;
+use owo_colors::{AnsiColors, OwoColorize};
+use roll::{
+    expression::Expression,
+    pmf::Pmf,
+    query::{self, Event},
+};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+// Bounds the number of distributions a sweep computes.
+const MAX_VALUES: usize = 1000;
+
+// Heatmap cells from the lowest to the highest value.
+const HEAT: [AnsiColors; 5] = [
+    AnsiColors::Blue,
+    AnsiColors::Cyan,
+    AnsiColors::Green,
+    AnsiColors::Yellow,
+    AnsiColors::Red,
+];
+
+/// A variable and the values a sweep gives it, e.g. `m=0..10` or `m=1,3,5`.
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: String,
+    pub values: Vec<IBig>,
+}
+
+#[derive(Debug)]
+pub struct Sweep {
+    expression: Expression,
+    variables: Vec<Variable>,
+    statistics: Option<Statistics>,
+    query: Option<query::Query>,
+    given: Option<Event>,
+}
+
+impl Sweep {
+    pub fn new(
+        expression: Expression,
+        variables: Vec<Variable>,
+        statistics: Option<Statistics>,
+        query: Option<query::Query>,
+        given: Option<Event>,
+    ) -> Self {
+        Self {
+            expression,
+            variables,
+            statistics,
+            query,
+            given,
+        }
+    }
+
+    /// The statistics chosen on the command line. Without a query, the mean is the default.
+    pub fn statistics(&self) -> Vec<Statistic> {
+        match (&self.statistics, &self.query) {
+            (Some(statistics), _) => statistics.0.clone(),
+            (None, Some(_)) => vec![],
+            (None, None) => vec![Statistic::Mean],
+        }
+    }
+}
+
+/// The distribution of the expression at each combination of values, the first variable varying
+/// slowest.
+#[derive(Debug)]
+pub struct SweepResult {
+    pub expression: Expression,
+    pub variables: Vec<Variable>,
+    pub query: Option<query::Query>,
+    pub given: Option<Event>,
+    pub points: Vec<Point>,
+}
+
+#[derive(Debug)]
+pub struct Point {
+    /// The value of each variable, in the order of `SweepResult::variables`.
+    pub values: Vec<IBig>,
+    pub pmf: Pmf<IBig>,
+    /// The probability of the query, if there is one.
+    pub p: Option<f64>,
+}
+
+impl Command for Sweep {
+    type Output = SweepResult;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        let names = self
+            .variables
+            .iter()
+            .map(|variable| variable.name.as_str())
+            .collect::<Vec<_>>();
+
+        if names.len() > 2 {
+            return Err(anyhow::anyhow!("A sweep varies at most two variables."));
+        }
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(anyhow::anyhow!("The variable ${} is swept twice.", name));
+            }
+            if !self.expression.variables().contains(name) {
+                return Err(anyhow::anyhow!("The expression has no variable ${}.", name));
+            }
+        }
+        if let Some(name) = self
+            .expression
+            .variables()
+            .into_iter()
+            .find(|name| !names.contains(name))
+        {
+            return Err(anyhow::anyhow!(
+                "The variable ${} has no values. Give it some with --sweep {}=0..10.",
+                name,
+                name
+            ));
+        }
+
+        let mut combinations: Vec<Vec<IBig>> = vec![vec![]];
+        for variable in self.variables.iter() {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|values| {
+                    variable.values.iter().map(move |value| {
+                        let mut values = values.clone();
+                        values.push(value.clone());
+                        values
+                    })
+                })
+                .collect();
+        }
+
+        let points = combinations
+            .into_iter()
+            .map(|values| {
+                let expression = self
+                    .variables
+                    .iter()
+                    .zip(values.iter())
+                    .fold(self.expression.clone(), |expression, (variable, value)| {
+                        expression.substitute(&variable.name, value)
+                    });
+
+                let mut pmf = expression.pmf()?;
+                if let Some(given) = &self.given {
+                    pmf = given.condition(&pmf)?;
+                }
+                let p = match &self.query {
+                    Some(query) => Some(query.probability(&pmf)?),
+                    None => None,
+                };
+
+                Ok(Point { values, pmf, p })
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+        Ok(SweepResult {
+            expression: self.expression.clone(),
+            variables: self.variables.clone(),
+            query: self.query.clone(),
+            given: self.given.clone(),
+            points,
+        })
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        sweep: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            statistics: self.statistics(),
+            sweep,
+        })
+    }
+}
+
+impl FromStr for Variable {
+    type Err = anyhow::Error;
+
+    fn from_str(sweep: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            anyhow::anyhow!(
+                "{:?} is not a sweep. Expected a variable and its values, e.g. \"m=0..10\" or \
+                \"m=1,3,5\".",
+                sweep
+            )
+        };
+
+        let (name, values) = sweep.split_once('=').ok_or_else(invalid)?;
+        let name = name.trim().trim_start_matches('$');
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(invalid());
+        }
+
+        let values = parse_values(values).map_err(|err| match err {
+            ValuesError::Invalid => invalid(),
+            ValuesError::TooMany => {
+                anyhow::anyhow!("A variable may take at most {} values.", MAX_VALUES)
+            }
+        })?;
+
+        Ok(Variable {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+pub(super) enum ValuesError {
+    Invalid,
+    TooMany,
+}
+
+// Parses a comma separated list of integers and inclusive ranges of integers, e.g. "0..3,5".
+pub(super) fn parse_values(values: &str) -> Result<Vec<IBig>, ValuesError> {
+    let mut parsed = vec![];
+    for value in values.split(',') {
+        match value.split_once("..") {
+            Some((min, max)) => {
+                let min = min
+                    .trim()
+                    .parse::<IBig>()
+                    .map_err(|_| ValuesError::Invalid)?;
+                let max = max
+                    .trim()
+                    .parse::<IBig>()
+                    .map_err(|_| ValuesError::Invalid)?;
+
+                let mut value = min;
+                while value <= max && parsed.len() <= MAX_VALUES {
+                    parsed.push(value.clone());
+                    value += 1;
+                }
+            }
+            None => parsed.push(value.trim().parse().map_err(|_| ValuesError::Invalid)?),
+        }
+
+        if parsed.len() > MAX_VALUES {
+            return Err(ValuesError::TooMany);
+        }
+    }
+
+    match parsed.is_empty() {
+        true => Err(ValuesError::Invalid),
+        false => Ok(parsed),
+    }
+}
+
+/// A column of a sweep: a statistic or the probability of the query.
+enum Measure<'a> {
+    Statistic(Statistic),
+    Query(&'a query::Query),
+}
+
+impl<'a> Measure<'a> {
+    fn label(&self) -> String {
+        match self {
+            Measure::Statistic(statistic) => statistic.label().trim_end_matches(':').to_string(),
+            Measure::Query(query) => query.to_string(),
+        }
+    }
+
+    // The cell's text, and its value for colouring heatmaps.
+    fn cell(&self, point: &Point) -> (String, Option<f64>) {
+        match self {
+            Measure::Statistic(statistic) => {
+                let value = statistic.value(&point.pmf);
+                (value.to_string(), value.to_f64())
+            }
+            Measure::Query(_) => match point.p {
+                Some(p) => (percent(&p), Some(p)),
+                None => (String::new(), None),
+            },
+        }
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    statistics: Vec<Statistic>,
+    sweep: SweepResult,
+}
+
+impl TextFormatter {
+    fn heading(&self, heading: &str) -> String {
+        match self.colors {
+            true => heading.magenta().bold().to_string(),
+            false => heading.to_string(),
+        }
+    }
+
+    fn measures(&self) -> Vec<Measure<'_>> {
+        self.sweep
+            .query
+            .iter()
+            .map(Measure::Query)
+            .chain(self.statistics.iter().copied().map(Measure::Statistic))
+            .collect()
+    }
+
+    // One row per value, and one column per measure.
+    fn table(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let variable = &self.sweep.variables[0];
+        let measures = self.measures();
+
+        let header = std::iter::once(variable.name.clone())
+            .chain(measures.iter().map(Measure::label))
+            .collect::<Vec<_>>();
+        let rows = self
+            .sweep
+            .points
+            .iter()
+            .map(|point| {
+                std::iter::once(point.values[0].to_string())
+                    .chain(measures.iter().map(|measure| measure.cell(point).0))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].chars().count())
+                    .chain(std::iter::once(header[i].chars().count()))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+
+        for (i, cell) in header.iter().enumerate() {
+            let cell = format!("  {:>width$}", cell, width = widths[i]);
+            match self.colors {
+                true => formatter.write_fmt(format_args!("{}", cell.cyan().bold()))?,
+                false => formatter.write_str(&cell)?,
+            }
+        }
+        formatter.write_str("\n")?;
+
+        for row in rows.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                let cell = format!("  {:>width$}", cell, width = widths[i]);
+                match (i, self.colors) {
+                    (0, true) => formatter.write_fmt(format_args!("{}", cell.blue().bold()))?,
+                    _ => formatter.write_str(&cell)?,
+                }
+            }
+            formatter.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+
+    // A grid of a measure with the first variable down the side and the second across the top.
+    // On terminals, cells are coloured from blue, the lowest, to red, the highest.
+    fn heatmap(&self, formatter: &mut Formatter, measure: &Measure) -> Result<(), std::fmt::Error> {
+        let (rows, columns) = (&self.sweep.variables[0], &self.sweep.variables[1]);
+        let cells = self
+            .sweep
+            .points
+            .iter()
+            .map(|point| measure.cell(point))
+            .collect::<Vec<_>>();
+
+        let numbers = cells.iter().filter_map(|(_, number)| *number);
+        let min = numbers.clone().fold(f64::INFINITY, f64::min);
+        let max = numbers.fold(f64::NEG_INFINITY, f64::max);
+
+        let corner = format!("{} \\ {}", rows.name, columns.name);
+        let row_width = rows
+            .values
+            .iter()
+            .map(|value| value.to_string().len())
+            .chain(std::iter::once(corner.len()))
+            .max()
+            .unwrap_or(0);
+        let width = cells
+            .iter()
+            .map(|(text, _)| text.chars().count())
+            .chain(columns.values.iter().map(|value| value.to_string().len()))
+            .max()
+            .unwrap_or(0);
+
+        formatter.write_fmt(format_args!("  {:>width$}", corner, width = row_width))?;
+        for value in columns.values.iter() {
+            let value = format!("  {:>width$}", value, width = width);
+            match self.colors {
+                true => formatter.write_fmt(format_args!("{}", value.blue().bold()))?,
+                false => formatter.write_str(&value)?,
+            }
+        }
+        formatter.write_str("\n")?;
+
+        for (i, value) in rows.values.iter().enumerate() {
+            let value = format!("  {:>width$}", value, width = row_width);
+            match self.colors {
+                true => formatter.write_fmt(format_args!("{}", value.blue().bold()))?,
+                false => formatter.write_str(&value)?,
+            }
+
+            for (text, number) in cells[i * columns.values.len()..][..columns.values.len()].iter() {
+                let cell = format!("  {:>width$}", text, width = width);
+                match (self.colors, number) {
+                    (true, Some(number)) => {
+                        let heat = match max > min {
+                            true => (number - min) / (max - min),
+                            false => 0.0,
+                        };
+                        let color = HEAT[((heat * HEAT.len() as f64) as usize).min(HEAT.len() - 1)];
+
+                        formatter.write_fmt(format_args!("{}", cell.color(color)))?
+                    }
+                    _ => formatter.write_str(&cell)?,
+                }
+            }
+            formatter.write_str("\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_fmt(format_args!(
+            "{} {}\n",
+            self.heading("Expression:"),
+            self.sweep.expression
+        ))?;
+        if let Some(given) = &self.sweep.given {
+            formatter.write_fmt(format_args!("{} {}\n", self.heading("Given:"), given))?;
+        }
+        formatter.write_str("\n")?;
+
+        match self.sweep.variables.len() {
+            1 => self.table(formatter),
+            _ => {
+                for (i, measure) in self.measures().iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str("\n")?;
+                    }
+                    formatter.write_fmt(format_args!(
+                        "{}\n",
+                        self.heading(&format!("{}:", measure.label()))
+                    ))?;
+                    self.heatmap(formatter, measure)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
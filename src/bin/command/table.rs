@@ -0,0 +1,366 @@
+use super::{percent, Command, Generator, Rng};
+use ibig::IBig;
+use owo_colors::OwoColorize;
+use roll::expression::{Evaluand, Expression};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Bounds the number of nested tables, so tables that refer to each other cannot roll forever.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub struct Table {
+    path: PathBuf,
+    rng: Rng,
+}
+
+#[derive(Debug)]
+pub struct TablePmf {
+    path: PathBuf,
+}
+
+impl Table {
+    pub fn new(path: PathBuf, rng: Rng) -> Self {
+        Self { path, rng }
+    }
+
+    pub fn rng(&self) -> Rng {
+        self.rng
+    }
+}
+
+impl TablePmf {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+/// A random table, e.g. encounters or loot. Results may embed dice expressions, `{2d6}`, and
+/// rolls on other tables, `{@loot.json}`, relative to the table's file.
+#[derive(Debug, Deserialize)]
+pub struct TableFile {
+    pub name: Option<String>,
+    pub roll: String,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+    pub range: Range,
+    pub result: String,
+}
+
+/// The rolls an entry covers: either a single value, `7`, or an inclusive range, `"1..40"`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Range {
+    Value(i64),
+    Range(String),
+}
+
+#[derive(Debug)]
+pub struct TableRoll {
+    pub name: Option<String>,
+    pub expression: Expression,
+    pub evaluand: Evaluand,
+    pub result: String,
+}
+
+#[derive(Debug)]
+pub struct EntryProbability {
+    pub range: String,
+    pub result: String,
+    pub p: f64,
+}
+
+#[derive(Debug)]
+pub struct TableDistribution {
+    pub name: Option<String>,
+    pub expression: Expression,
+    pub entries: Vec<EntryProbability>,
+    pub unmatched: f64,
+}
+
+impl Command for Table {
+    type Output = TableRoll;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        roll_table(&self.path, &mut self.rng.generator(), 0)
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        output: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            roll: output,
+        })
+    }
+}
+
+impl Command for TablePmf {
+    type Output = TableDistribution;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        let table = TableFile::read(&self.path)?;
+        let expression = table.expression()?;
+        let pmf = expression.pmf()?;
+
+        let mut p = vec![0.0; table.entries.len()];
+        let mut unmatched = 0.0;
+        for outcome in pmf.iter() {
+            match table.entry(&outcome.value)? {
+                Some(i) => p[i] += outcome.p,
+                None => unmatched += outcome.p,
+            }
+        }
+
+        let entries = table
+            .entries
+            .iter()
+            .zip(p)
+            .map(|(entry, p)| EntryProbability {
+                range: entry.range.to_string(),
+                result: entry.result.clone(),
+                p,
+            })
+            .collect();
+
+        Ok(TableDistribution {
+            name: table.name,
+            expression,
+            entries,
+            unmatched,
+        })
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        output: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(PmfTextFormatter {
+            colors: args.use_colors(),
+            distribution: output,
+        })
+    }
+}
+
+impl TableFile {
+    pub fn read(path: &Path) -> Result<Self, anyhow::Error> {
+        let file = std::fs::File::open(path)
+            .map_err(|err| anyhow::anyhow!(err).context(format!("Could not open {:?}.", path)))?;
+
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(|err| {
+            anyhow::anyhow!(err).context(format!("{:?} is not a valid table.", path))
+        })
+    }
+
+    pub fn expression(&self) -> Result<Expression, anyhow::Error> {
+        parse_expression(&self.roll)
+    }
+
+    // The index of the first entry covering `value`.
+    fn entry(&self, value: &IBig) -> Result<Option<usize>, anyhow::Error> {
+        for (i, entry) in self.entries.iter().enumerate() {
+            let (min, max) = entry.range.bounds()?;
+
+            if value >= &min && value <= &max {
+                return Ok(Some(i));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Range {
+    fn bounds(&self) -> Result<(IBig, IBig), anyhow::Error> {
+        let invalid = || anyhow::anyhow!("The table range {:?} is not a number or range.", self);
+
+        match self {
+            Range::Value(value) => Ok((IBig::from(*value), IBig::from(*value))),
+            Range::Range(range) => match range.split_once("..") {
+                Some((min, max)) => Ok((
+                    min.trim().parse().map_err(|_| invalid())?,
+                    max.trim().parse().map_err(|_| invalid())?,
+                )),
+                None => {
+                    let value = range.trim().parse::<IBig>().map_err(|_| invalid())?;
+                    Ok((value.clone(), value))
+                }
+            },
+        }
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Range::Value(value) => formatter.write_fmt(format_args!("{}", value)),
+            Range::Range(range) => formatter.write_str(range.trim()),
+        }
+    }
+}
+
+fn roll_table(path: &Path, rng: &mut Generator, depth: usize) -> Result<TableRoll, anyhow::Error> {
+    if depth > MAX_DEPTH {
+        return Err(anyhow::anyhow!(
+            "Tables may not be nested more than {} deep.",
+            MAX_DEPTH
+        ));
+    }
+
+    let table = TableFile::read(path)?;
+    let expression = table.expression()?;
+    let evaluand = rng.eval(&expression)?;
+
+    let result = match table.entry(&evaluand.value)? {
+        Some(i) => resolve(&table.entries[i].result, path, rng, depth)?,
+        None => {
+            return Err(anyhow::anyhow!(
+                "{:?} has no entry for a roll of {}.",
+                path,
+                evaluand.value
+            ))
+        }
+    };
+
+    Ok(TableRoll {
+        name: table.name,
+        expression,
+        evaluand,
+        result,
+    })
+}
+
+// Replaces each `{expression}` in a result with its value and each `{@table}` with a roll on
+// that table.
+fn resolve(
+    result: &str,
+    path: &Path,
+    rng: &mut Generator,
+    depth: usize,
+) -> Result<String, anyhow::Error> {
+    let mut resolved = String::new();
+    let mut rest = result;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| {
+            anyhow::anyhow!("The table result {:?} has an unclosed '{{'.", result)
+        })? + start;
+        let embedded = rest[start + 1..end].trim();
+
+        resolved.push_str(&rest[..start]);
+        match embedded.strip_prefix('@') {
+            Some(table) => {
+                let table = path.parent().unwrap_or_else(|| Path::new("")).join(table);
+                resolved.push_str(&roll_table(&table, rng, depth + 1)?.result);
+            }
+            None => {
+                let evaluand = rng.eval(&parse_expression(embedded)?)?;
+                resolved.push_str(&evaluand.value.to_string());
+            }
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    resolved.push_str(rest);
+
+    Ok(resolved)
+}
+
+fn parse_expression(expression: &str) -> Result<Expression, anyhow::Error> {
+    Expression::from_str(expression).map_err(|err| {
+        anyhow::anyhow!(err.to_string())
+            .context(format!("{:?} is not a valid expression.", expression))
+    })
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    roll: TableRoll,
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        if !self.colors {
+            return formatter.write_str(&self.roll.result);
+        }
+
+        if let Some(name) = &self.roll.name {
+            formatter.write_fmt(format_args!("{} {}\n", "Table:".magenta().bold(), name))?;
+        }
+
+        formatter.write_fmt(format_args!(
+            "{} {}\n",
+            "Expression:".magenta().bold(),
+            self.roll.expression.to_string().blue()
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} {}\n\n",
+            "Roll:".magenta().bold(),
+            self.roll.evaluand.value.to_string().blue()
+        ))?;
+
+        formatter.write_str(&self.roll.result)
+    }
+}
+
+pub struct PmfTextFormatter {
+    colors: bool,
+    distribution: TableDistribution,
+}
+
+impl Display for PmfTextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        if self.colors {
+            if let Some(name) = &self.distribution.name {
+                formatter.write_fmt(format_args!("{} {}\n", "Table:".magenta().bold(), name))?;
+            }
+
+            formatter.write_fmt(format_args!(
+                "{} {}\n\n",
+                "Expression:".magenta().bold(),
+                self.distribution.expression.to_string().blue(),
+            ))?;
+        }
+
+        let max_range = self
+            .distribution
+            .entries
+            .iter()
+            .map(|entry| entry.range.len())
+            .max()
+            .unwrap_or(0);
+
+        for entry in self.distribution.entries.iter() {
+            formatter.write_fmt(format_args!(
+                "  {:>align$} {:>7}  {}\n",
+                entry
+                    .range
+                    .if_supports_color(owo_colors::Stream::Stdout, |text| text
+                        .style(owo_colors::Style::new().blue().bold())),
+                percent(&entry.p),
+                entry.result,
+                align = max_range
+            ))?;
+        }
+
+        if self.distribution.unmatched > 0.0 {
+            formatter.write_fmt(format_args!(
+                "  {:>align$} {:>7}\n",
+                "-",
+                percent(&self.distribution.unmatched),
+                align = max_range
+            ))?;
+        }
+
+        Ok(())
+    }
+}
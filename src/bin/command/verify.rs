@@ -0,0 +1,185 @@
+use super::Command;
+use ibig::IBig;
+use owo_colors::OwoColorize;
+use roll::{
+    expression::Expression,
+    verification::{Bucket, Verification},
+};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub struct Verify {
+    expression: Expression,
+    samples: u64,
+    seed: u64,
+    alpha: f64,
+}
+
+impl Verify {
+    pub fn new(expression: Expression, samples: u64, seed: u64, alpha: f64) -> Self {
+        Self {
+            expression,
+            samples,
+            seed,
+            alpha,
+        }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// The seed of the samples, so a failure can be reproduced.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Command for Verify {
+    type Output = Verification;
+
+    fn exec(&self) -> Result<Self::Output, anyhow::Error> {
+        if self.samples == 0 {
+            return Err(anyhow::anyhow!("A verification needs at least one sample."));
+        }
+
+        self.expression.verify(self.samples, self.seed, self.alpha)
+    }
+
+    fn formatter(
+        self,
+        args: crate::cli::Arguments,
+        verification: Self::Output,
+    ) -> Box<dyn std::fmt::Display> {
+        Box::from(TextFormatter {
+            colors: args.use_colors(),
+            expression: self.expression,
+            seed: self.seed,
+            verification,
+        })
+    }
+}
+
+pub struct TextFormatter {
+    colors: bool,
+    expression: Expression,
+    seed: u64,
+    verification: Verification,
+}
+
+impl TextFormatter {
+    fn heading(&self, heading: &str) -> String {
+        match self.colors {
+            true => heading.magenta().bold().to_string(),
+            false => heading.to_string(),
+        }
+    }
+}
+
+impl Display for TextFormatter {
+    fn fmt(&self, formatter: &mut Formatter) -> Result<(), std::fmt::Error> {
+        let verification = &self.verification;
+
+        formatter.write_fmt(format_args!(
+            "{} {}\n",
+            self.heading("Expression:"),
+            self.expression
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} {} (seed {})\n",
+            self.heading("Samples:"),
+            verification.samples,
+            self.seed
+        ))?;
+        formatter.write_fmt(format_args!(
+            "{} G = {:.2}, {} degrees of freedom, p = {:.4}\n",
+            self.heading("G-test:"),
+            verification.g,
+            verification.degrees_of_freedom,
+            verification.p_value
+        ))?;
+
+        let result = match (verification.passed(), self.colors) {
+            (true, true) => "passed".green().bold().to_string(),
+            (true, false) => String::from("passed"),
+            (false, true) => "FAILED".red().bold().to_string(),
+            (false, false) => String::from("FAILED"),
+        };
+        formatter.write_fmt(format_args!(
+            "{} {} at a significance level of {}\n",
+            self.heading("Result:"),
+            result,
+            verification.alpha
+        ))?;
+
+        formatter.write_fmt(format_args!("\n{}\n", self.heading("Buckets:")))?;
+        write_buckets(formatter, &verification.buckets, self.colors)?;
+
+        if !verification.impossible.is_empty() {
+            formatter.write_fmt(format_args!("\n{}\n", self.heading("Impossible outcomes:")))?;
+            write_impossible(formatter, &verification.impossible)?;
+        }
+
+        Ok(())
+    }
+}
+
+// A table of the observed and expected counts of each bucket.
+pub(super) fn write_buckets(
+    formatter: &mut Formatter,
+    buckets: &[Bucket],
+    colors: bool,
+) -> Result<(), std::fmt::Error> {
+    let ranges = buckets
+        .iter()
+        .map(|bucket| match bucket.min == bucket.max {
+            true => bucket.min.to_string(),
+            false => format!("{}..{}", bucket.min, bucket.max),
+        })
+        .collect::<Vec<_>>();
+    let range_width = ranges.iter().map(String::len).max().unwrap_or(0).max(8);
+
+    formatter.write_fmt(format_args!(
+        "  {:>range_width$}  {:>10}  {:>12}  {:>8}\n",
+        "outcomes",
+        "observed",
+        "expected",
+        "residual",
+        range_width = range_width
+    ))?;
+    for (bucket, range) in buckets.iter().zip(ranges.iter()) {
+        // Rounding error would otherwise show as -0.00.
+        let residual = match bucket.residual.abs() < 0.005 {
+            true => 0.0,
+            false => bucket.residual,
+        };
+        let row = format!(
+            "  {:>range_width$}  {:>10}  {:>12.2}  {:>+8.2}",
+            range,
+            bucket.observed,
+            bucket.expected,
+            residual,
+            range_width = range_width
+        );
+
+        // Significant deviations are starred, and red on terminals.
+        match (bucket.significant, colors) {
+            (true, true) => formatter.write_fmt(format_args!("{}\n", (row + "  *").red()))?,
+            (true, false) => formatter.write_fmt(format_args!("{}  *\n", row))?,
+            (false, _) => formatter.write_fmt(format_args!("{}\n", row))?,
+        }
+    }
+
+    Ok(())
+}
+
+pub(super) fn write_impossible(
+    formatter: &mut Formatter,
+    impossible: &[(IBig, u64)],
+) -> Result<(), std::fmt::Error> {
+    for (value, count) in impossible.iter() {
+        formatter.write_fmt(format_args!("  {} was rolled {} times\n", value, count))?;
+    }
+
+    Ok(())
+}
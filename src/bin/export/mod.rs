@@ -0,0 +1,186 @@
+use crate::command::Distribution;
+use clap::ArgEnum;
+use ibig::IBig;
+use roll::expression::Evaluand;
+use roll::pmf::Pmf;
+use roll::probability::Probability;
+
+/// A table format for spreadsheets and wikis.
+#[derive(ArgEnum, Clone, Copy, Debug)]
+pub enum Format {
+    Csv,
+    Tsv,
+    Markdown,
+}
+
+/// The header and rows of a table, exported as CSV, TSV or Markdown. Cells hold exact integers
+/// and fixed precision decimals.
+#[derive(Debug)]
+pub struct Export {
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Export {
+    /// Each die rolled and card drawn, then the total.
+    pub fn roll(evaluand: &Evaluand) -> Self {
+        let mut sides = evaluand.rolls.keys().collect::<Vec<_>>();
+        sides.sort();
+
+        let mut rows = vec![];
+        for sides in sides {
+            for roll in evaluand.rolls[sides].iter() {
+                rows.push(vec![format!("d{}", sides), roll.to_string()]);
+            }
+        }
+        for cards in evaluand.draws.iter() {
+            for card in cards.iter() {
+                rows.push(vec![String::from("draw"), card.to_string()]);
+            }
+        }
+        rows.push(vec![String::from("total"), evaluand.value.to_string()]);
+
+        Self {
+            header: vec![String::from("die"), String::from("result")],
+            rows,
+        }
+    }
+
+    /// The probability of each outcome, of at most it and of more than it, with `precision`
+    /// digits after the decimal point. Exact probabilities are summed before they are rounded.
+    pub fn pmf(distribution: &Distribution, precision: usize) -> Self {
+        let rows = match &distribution.exact {
+            Some(exact) => rows(exact, precision),
+            None => rows(&distribution.pmf, precision),
+        };
+
+        Self {
+            header: ["value", "probability", "cumulative", "survival"]
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            rows,
+        }
+    }
+
+    /// The table in `format`, one line per row after the header.
+    pub fn render(&self, format: Format) -> String {
+        let lines = match format {
+            Format::Csv | Format::Tsv => {
+                let (separator, escape): (&str, fn(&str) -> String) = match format {
+                    Format::Csv => (",", escape_csv),
+                    _ => ("\t", escape_tsv),
+                };
+
+                std::iter::once(&self.header)
+                    .chain(self.rows.iter())
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| escape(cell))
+                            .collect::<Vec<_>>()
+                            .join(separator)
+                    })
+                    .collect::<Vec<_>>()
+            }
+            Format::Markdown => {
+                let row = |cells: &[String]| {
+                    let cells = cells
+                        .iter()
+                        .map(|cell| cell.replace('|', "\\|").replace(['\n', '\r'], " "))
+                        .collect::<Vec<_>>();
+
+                    format!("| {} |", cells.join(" | "))
+                };
+                // The first column is left aligned, and the numbers after it right aligned.
+                let alignment = (0..self.header.len())
+                    .map(|i| match i {
+                        0 => "---",
+                        _ => "---:",
+                    })
+                    .collect::<Vec<_>>();
+
+                std::iter::once(row(&self.header))
+                    .chain(std::iter::once(format!("| {} |", alignment.join(" | "))))
+                    .chain(self.rows.iter().map(|cells| row(cells)))
+                    .collect()
+            }
+        };
+
+        lines.join("\n")
+    }
+}
+
+fn rows<P: Probability>(pmf: &Pmf<IBig, P>, precision: usize) -> Vec<Vec<String>> {
+    let cdf = pmf.cdf();
+    // The chance of more than each outcome is the chance of at least the next one.
+    let survival = pmf
+        .at_least()
+        .into_iter()
+        .skip(1)
+        .chain(std::iter::once(P::zero()));
+
+    pmf.iter()
+        .zip(cdf.iter())
+        .zip(survival)
+        .map(|((outcome, cdf), survival)| {
+            vec![
+                outcome.value.to_string(),
+                format!("{:.*}", precision, outcome.p.to_f64()),
+                format!("{:.*}", precision, cdf.to_f64()),
+                format!("{:.*}", precision, survival.to_f64()),
+            ]
+        })
+        .collect()
+}
+
+// Quotes a cell containing a separator, quote or line break, doubling its quotes.
+fn escape_csv(cell: &str) -> String {
+    match cell.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string(),
+    }
+}
+
+// Replaces tabs and line breaks with spaces, since TSV has no quoting.
+fn escape_tsv(cell: &str) -> String {
+    cell.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn export() -> Export {
+        Export {
+            header: vec![String::from("name"), String::from("value")],
+            rows: vec![
+                vec![String::from("a, \"b\""), String::from("1")],
+                vec![String::from("c\td\r\ne|f"), String::from("2")],
+            ],
+        }
+    }
+
+    #[test]
+    fn render_csv() {
+        assert_eq!(
+            export().render(Format::Csv),
+            "name,value\n\"a, \"\"b\"\"\",1\n\"c\td\r\ne|f\",2"
+        );
+    }
+
+    #[test]
+    fn render_tsv() {
+        assert_eq!(
+            export().render(Format::Tsv),
+            "name\tvalue\na, \"b\"\t1\nc d  e|f\t2"
+        );
+    }
+
+    #[test]
+    fn render_markdown() {
+        assert_eq!(
+            export().render(Format::Markdown),
+            "| name | value |\n| --- | ---: |\n| a, \"b\" | 1 |\n| c\td  e\\|f | 2 |"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use super::pmf::serialize_pmf;
+use super::statistics::Statistics;
+use crate::command::{self, Comparison, Distribution, PmfColumns};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Compare {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    given: Option<String>,
+    expressions: Vec<Series>,
+    pairs: Vec<Pair>,
+}
+
+#[derive(Serialize)]
+struct Series {
+    expression: String,
+    #[serde(serialize_with = "serialize_pmf")]
+    pmf: (Distribution, PmfColumns),
+    statistics: Statistics,
+}
+
+#[derive(Serialize)]
+struct Pair {
+    left: usize,
+    right: usize,
+    p_greater: f64,
+    total_variation: f64,
+    // JSON has no infinity, so an infinite divergence is null.
+    kl_divergence: f64,
+}
+
+impl Compare {
+    pub fn new(comparison: Comparison, command: &command::Compare) -> Self {
+        let statistics = command.statistics();
+
+        Self {
+            given: comparison.given.map(|given| given.to_string()),
+            expressions: comparison
+                .expressions
+                .iter()
+                .zip(comparison.pmfs)
+                .map(|(expression, pmf)| Series {
+                    expression: expression.to_string(),
+                    statistics: Statistics::new(&pmf, &statistics),
+                    pmf: (
+                        Distribution {
+                            pmf,
+                            exact: None,
+                            simulation: None,
+                            tree: None,
+                        },
+                        PmfColumns::default(),
+                    ),
+                })
+                .collect(),
+            pairs: comparison
+                .pairs
+                .into_iter()
+                .map(|pair| Pair {
+                    left: pair.left,
+                    right: pair.right,
+                    p_greater: pair.p_greater,
+                    total_variation: pair.total_variation,
+                    kl_divergence: pair.kl_divergence,
+                })
+                .collect(),
+        }
+    }
+}
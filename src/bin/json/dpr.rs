@@ -0,0 +1,75 @@
+use super::ibig::serialize_ibig;
+use crate::command::{self, Target};
+use ibig::IBig;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Dpr {
+    #[serde(serialize_with = "serialize_ibig")]
+    bonus: IBig,
+    damage: String,
+    critical_damage: String,
+    critical: u8,
+    attacks: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rider: Option<Rider>,
+    targets: Vec<ArmorClass>,
+}
+
+/// Damage dealt once per turn, on the first hit.
+#[derive(Serialize)]
+struct Rider {
+    damage: String,
+    critical_damage: String,
+}
+
+#[derive(Serialize)]
+struct ArmorClass {
+    #[serde(serialize_with = "serialize_ibig")]
+    ac: IBig,
+    /// The chance each attack hits, including critical hits.
+    hit: f64,
+    critical: f64,
+    dpr: f64,
+    pmf: Vec<Outcome>,
+}
+
+#[derive(Serialize)]
+struct Outcome {
+    #[serde(serialize_with = "serialize_ibig")]
+    value: IBig,
+    p: f64,
+}
+
+impl Dpr {
+    pub fn new(targets: Vec<Target>, command: &command::Dpr) -> Self {
+        Self {
+            bonus: command.bonus().clone(),
+            damage: command.damage().to_string(),
+            critical_damage: command.damage().double_dice().to_string(),
+            critical: command.critical(),
+            attacks: command.attacks(),
+            rider: command.rider().map(|rider| Rider {
+                damage: rider.to_string(),
+                critical_damage: rider.double_dice().to_string(),
+            }),
+            targets: targets
+                .into_iter()
+                .map(|target| ArmorClass {
+                    dpr: target.pmf.expected_value(),
+                    pmf: target
+                        .pmf
+                        .iter()
+                        .map(|outcome| Outcome {
+                            value: outcome.value.clone(),
+                            p: outcome.p,
+                        })
+                        .collect(),
+                    ac: target.ac,
+                    hit: target.odds.hit + target.odds.critical,
+                    critical: target.odds.critical,
+                })
+                .collect(),
+        }
+    }
+}
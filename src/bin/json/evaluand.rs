@@ -1,25 +1,91 @@
 use super::ibig::{serialize_ibig, IBigSerializer};
+use crate::command::Rng;
 use ibig::{IBig, UBig};
 use roll::expression;
 use serde::{Serialize, Serializer};
 use std::collections::HashMap;
 
 #[derive(Serialize)]
-pub struct Evaluand(#[serde(with = "EvaluandSerializer")] pub expression::Evaluand);
+pub struct Evaluand {
+    #[serde(flatten, with = "EvaluandSerializer")]
+    evaluand: expression::Evaluand,
+    rng: RngSource,
+}
+
+/// Where the rolls came from: "seeded", with the seed that rolls the same again, "os" or
+/// "replay".
+#[derive(Serialize)]
+struct RngSource {
+    backend: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
 
 impl Evaluand {
-    pub fn new(expression: expression::Evaluand) -> Self {
-        Self(expression)
+    /// A roll made with `rng`, or with replayed faces if none.
+    pub fn new(evaluand: expression::Evaluand, rng: Option<Rng>) -> Self {
+        Self {
+            evaluand,
+            rng: RngSource {
+                backend: rng.map_or("replay", |rng| rng.backend()),
+                seed: rng.and_then(|rng| rng.seed()),
+            },
+        }
     }
 }
 
 #[derive(Serialize)]
 #[serde(remote = "expression::Evaluand")]
 struct EvaluandSerializer {
+    #[serde(
+        serialize_with = "serialize_draws",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    draws: Vec<Vec<IBig>>,
     #[serde(serialize_with = "serialize_rolls")]
     rolls: HashMap<UBig, Vec<UBig>>,
     #[serde(serialize_with = "serialize_ibig")]
     value: IBig,
+    #[serde(serialize_with = "serialize_trace")]
+    trace: expression::Trace,
+}
+
+/// How a subexpression evaluated, with the dice it rolled and the cards it drew in order.
+#[derive(Serialize)]
+struct Trace {
+    expression: String,
+    #[serde(serialize_with = "serialize_ibig")]
+    value: IBig,
+    #[serde(
+        serialize_with = "serialize_ibigs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    rolls: Vec<IBig>,
+    #[serde(
+        serialize_with = "serialize_ibigs",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    draws: Vec<IBig>,
+    operands: Vec<Trace>,
+}
+
+impl Trace {
+    fn new(trace: &expression::Trace) -> Self {
+        Self {
+            expression: trace.expression.to_string(),
+            value: trace.value.clone(),
+            rolls: trace.rolls.iter().map(IBig::from).collect(),
+            draws: trace.draws.clone(),
+            operands: trace.operands.iter().map(Trace::new).collect(),
+        }
+    }
+}
+
+fn serialize_trace<S>(trace: &expression::Trace, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Trace::new(trace).serialize(serializer)
 }
 
 fn serialize_rolls<S>(rolls: &HashMap<UBig, Vec<UBig>>, serializer: S) -> Result<S::Ok, S::Error>
@@ -28,13 +94,37 @@ where
 {
     use serde::ser::SerializeMap;
 
+    // Sorted by sides, since the order of a `HashMap` changes from run to run.
+    let mut sides = rolls.keys().collect::<Vec<_>>();
+    sides.sort();
+
     let mut map = serializer.serialize_map(Some(rolls.len()))?;
-    for (sides, rolls) in rolls {
-        map.serialize_entry(&format!("d{}", sides), &RollsSerializer(rolls))?;
+    for sides in sides {
+        map.serialize_entry(&format!("d{}", sides), &RollsSerializer(&rolls[sides]))?;
     }
     map.end()
 }
 
+fn serialize_ibigs<S>(ns: &[IBig], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(ns.iter().map(IBigSerializer::new))
+}
+
+fn serialize_draws<S>(draws: &[Vec<IBig>], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    use serde::ser::SerializeSeq;
+
+    let mut seq = serializer.serialize_seq(Some(draws.len()))?;
+    for cards in draws {
+        seq.serialize_element(&cards.iter().map(IBigSerializer::new).collect::<Vec<_>>())?;
+    }
+    seq.end()
+}
+
 struct RollsSerializer<'a>(&'a Vec<UBig>);
 
 impl<'a> serde::Serialize for RollsSerializer<'a> {
@@ -0,0 +1,41 @@
+use super::verify::{Bucket, Impossible};
+use crate::command;
+use roll::fairness;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Fairness {
+    expression: String,
+    results: u64,
+    alpha: f64,
+    chi_squared: f64,
+    degrees_of_freedom: usize,
+    chi_squared_p_value: f64,
+    kolmogorov_smirnov: f64,
+    kolmogorov_smirnov_p_value: f64,
+    fair: bool,
+    buckets: Vec<Bucket>,
+    impossible: Vec<Impossible>,
+}
+
+impl Fairness {
+    pub fn new(fairness: fairness::Fairness, command: &command::Fairness) -> Self {
+        Self {
+            expression: command.expression().to_string(),
+            results: fairness.results,
+            alpha: fairness.alpha,
+            chi_squared: fairness.chi_squared,
+            degrees_of_freedom: fairness.degrees_of_freedom,
+            chi_squared_p_value: fairness.chi_squared_p_value,
+            kolmogorov_smirnov: fairness.kolmogorov_smirnov,
+            kolmogorov_smirnov_p_value: fairness.kolmogorov_smirnov_p_value,
+            fair: fairness.fair(),
+            buckets: fairness.buckets.into_iter().map(Bucket::from).collect(),
+            impossible: fairness
+                .impossible
+                .into_iter()
+                .map(Impossible::from)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::command::Fitted;
+use roll::fit::Target;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Fit {
+    target: Statistics,
+    /// The number of results the target was estimated from, with `--observed`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<u64>,
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Serialize)]
+struct Statistics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mean: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stddev: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Candidate {
+    expression: String,
+    mean: f64,
+    stddev: f64,
+    distance: f64,
+}
+
+impl Fit {
+    pub fn new(fitted: Fitted) -> Self {
+        let target = match &fitted.target {
+            Target::Statistics {
+                mean,
+                standard_deviation,
+            } => Statistics {
+                mean: *mean,
+                stddev: *standard_deviation,
+            },
+            Target::Pmf(pmf) => Statistics {
+                mean: Some(pmf.expected_value()),
+                stddev: Some(pmf.standard_deviation()),
+            },
+        };
+
+        Self {
+            target,
+            results: fitted.results,
+            candidates: fitted
+                .candidates
+                .into_iter()
+                .map(|candidate| Candidate {
+                    expression: candidate.expression.to_string(),
+                    mean: candidate.pmf.expected_value(),
+                    stddev: candidate.pmf.standard_deviation(),
+                    distance: candidate.distance,
+                })
+                .collect(),
+        }
+    }
+}
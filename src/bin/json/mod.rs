@@ -1,6 +1,25 @@
+pub use compare::Compare;
+pub use dpr::Dpr;
 pub use evaluand::Evaluand;
+pub use fairness::Fairness;
+pub use fit::Fit;
 pub use pmf::Pmf;
+pub use presets::Presets;
+pub use query::Query;
+pub use sweep::Sweep;
+pub use table::{Table, TablePmf};
+pub use verify::Verify;
 
+mod compare;
+mod dpr;
 mod evaluand;
+mod fairness;
+mod fit;
 mod ibig;
 mod pmf;
+mod presets;
+mod query;
+mod statistics;
+mod sweep;
+mod table;
+mod verify;
@@ -1,35 +1,119 @@
-use super::ibig::{serialize_ibig, serialize_opt_ibig};
+use super::ibig::serialize_ibig;
+use super::statistics::Statistics;
+use crate::command::{self, Distribution, PmfColumns, Statistic};
 use ibig::IBig;
-use roll::pmf;
+use roll::expression::PmfTree;
+use roll::pmf::{Bin as PmfBin, Pmf as ExpressionPmf, Tail};
+use roll::probability::Probability;
 use serde::{Serialize, Serializer};
 
 #[derive(Serialize)]
 pub struct Pmf {
-    #[serde(serialize_with = "serialize_pmf")]
-    pmf: pmf::Pmf<IBig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    given: Option<String>,
+    #[serde(flatten)]
+    outcomes: Outcomes,
     statistics: Statistics,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simulation: Option<Simulation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tree: Option<Tree>,
 }
 
-impl Pmf {
-    pub fn new(pmf: pmf::Pmf<IBig>) -> Self {
-        let min = pmf.iter().next().map(|outcome| outcome.value.clone());
-        let mean = pmf.expected_value();
-        let max = pmf.iter().last().map(|outcome| outcome.value.clone());
+/// The probability of each outcome, or of each range of outcomes with `--bins` or `--bin-width`.
+#[derive(Serialize)]
+enum Outcomes {
+    #[serde(rename = "pmf", serialize_with = "serialize_pmf")]
+    Pmf((Distribution, PmfColumns)),
+    #[serde(rename = "bins")]
+    Bins(Vec<Bin>),
+}
 
+/// The distribution of a subexpression and of each of its operands, with `--tree`.
+#[derive(Serialize)]
+struct Tree {
+    expression: String,
+    statistics: Statistics,
+    pmf: Vec<Value>,
+    operands: Vec<Tree>,
+}
+
+#[derive(Serialize)]
+struct Value {
+    #[serde(serialize_with = "serialize_ibig")]
+    value: IBig,
+    p: f64,
+}
+
+impl Tree {
+    fn new(tree: &PmfTree, statistics: &[Statistic]) -> Self {
         Self {
-            pmf,
-            statistics: Statistics { min, mean, max },
+            expression: tree.expression.to_string(),
+            statistics: Statistics::new(&tree.pmf, statistics),
+            pmf: tree
+                .pmf
+                .iter()
+                .map(|outcome| Value {
+                    value: outcome.value.clone(),
+                    p: outcome.p,
+                })
+                .collect(),
+            operands: tree
+                .operands
+                .iter()
+                .map(|operand| Tree::new(operand, statistics))
+                .collect(),
         }
     }
 }
 
+/// How closely a simulated distribution is likely to match the true one.
 #[derive(Serialize)]
-struct Statistics {
-    #[serde(serialize_with = "serialize_opt_ibig")]
-    min: Option<IBig>,
-    mean: f64,
-    #[serde(serialize_with = "serialize_opt_ibig")]
-    max: Option<IBig>,
+struct Simulation {
+    samples: u64,
+    standard_error: f64,
+    max_error: f64,
+}
+
+impl Pmf {
+    pub fn new(mut distribution: Distribution, command: &command::Pmf) -> Self {
+        let chosen = match command.statistics() {
+            Some(statistics) => statistics.0.clone(),
+            None => vec![Statistic::Min, Statistic::Mean, Statistic::Max],
+        };
+        let tree = distribution
+            .tree
+            .take()
+            .map(|tree| Tree::new(&tree, &chosen));
+        let pmf = &distribution.pmf;
+        let statistics = Statistics::new(pmf, &chosen);
+
+        let simulation = distribution
+            .simulation
+            .as_ref()
+            .map(|simulation| Simulation {
+                samples: simulation.samples(),
+                standard_error: simulation.standard_error(),
+                max_error: simulation.max_error(),
+            });
+
+        // Only the binning asked for applies, so programs get every outcome by default.
+        let outcomes = match command.bins().binning(pmf.len(), false) {
+            Some(binning) => {
+                let (bins, exact) = distribution.bin(&binning, command.bins().tail);
+                Outcomes::Bins(Bin::all(&bins, exact.as_ref(), command.columns()))
+            }
+            None => Outcomes::Pmf((distribution, command.columns())),
+        };
+
+        Self {
+            given: command.given().map(|given| given.to_string()),
+            simulation,
+            outcomes,
+            statistics,
+            tree,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -37,21 +121,126 @@ struct Outcome<'a> {
     #[serde(serialize_with = "serialize_ibig")]
     value: &'a IBig,
     p: f64,
+    /// The exact probability as a fraction, e.g. "1/36", with `--exact`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact: Option<String>,
+    /// The 95% confidence interval of `p`, with `--simulate`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<(f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cdf: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_least: Option<f64>,
 }
 
-fn serialize_pmf<S>(pmf: &pmf::Pmf<IBig>, serializer: S) -> Result<S::Ok, S::Error>
+#[derive(Serialize)]
+struct Bin {
+    #[serde(serialize_with = "serialize_ibig")]
+    min: IBig,
+    #[serde(serialize_with = "serialize_ibig")]
+    max: IBig,
+    /// "below" or "above" when the bin collapses a negligible tail of the distribution.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tail: Option<&'static str>,
+    p: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cdf: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    at_least: Option<f64>,
+}
+
+impl Bin {
+    fn all(
+        bins: &ExpressionPmf<PmfBin>,
+        exact: Option<&ExpressionPmf<PmfBin, roll::probability::Ratio>>,
+        columns: PmfColumns,
+    ) -> Vec<Self> {
+        let to_f64 = |probabilities: Vec<_>| {
+            probabilities
+                .iter()
+                .map(Probability::to_f64)
+                .collect::<Vec<_>>()
+        };
+        let (cdf, at_least) = match exact {
+            Some(exact) => (
+                columns.cdf.then(|| to_f64(exact.cdf())),
+                columns.at_least.then(|| to_f64(exact.at_least())),
+            ),
+            None => (
+                columns.cdf.then(|| bins.cdf()),
+                columns.at_least.then(|| bins.at_least()),
+            ),
+        };
+        let fractions = exact.map(|exact| {
+            exact
+                .iter()
+                .map(|outcome| outcome.p.to_string())
+                .collect::<Vec<_>>()
+        });
+
+        bins.iter()
+            .enumerate()
+            .map(|(i, outcome)| Bin {
+                min: outcome.value.min.clone(),
+                max: outcome.value.max.clone(),
+                tail: outcome.value.tail.map(|tail| match tail {
+                    Tail::Below => "below",
+                    Tail::Above => "above",
+                }),
+                p: outcome.p,
+                exact: fractions.as_ref().map(|fractions| fractions[i].clone()),
+                cdf: cdf.as_ref().map(|cdf| cdf[i]),
+                at_least: at_least.as_ref().map(|at_least| at_least[i]),
+            })
+            .collect()
+    }
+}
+
+pub(super) fn serialize_pmf<S>(
+    (distribution, columns): &(Distribution, PmfColumns),
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     use serde::ser::SerializeSeq;
 
-    let iter = pmf.iter();
+    let to_f64 = |probabilities: Vec<_>| probabilities.iter().map(Probability::to_f64).collect();
+    let pmf = &distribution.pmf;
+    let (cdf, at_least): (Option<Vec<f64>>, Option<Vec<f64>>) = match &distribution.exact {
+        Some(exact) => (
+            columns.cdf.then(|| to_f64(exact.cdf())),
+            columns.at_least.then(|| to_f64(exact.at_least())),
+        ),
+        None => (
+            columns.cdf.then(|| pmf.cdf()),
+            columns.at_least.then(|| pmf.at_least()),
+        ),
+    };
+    let fractions = distribution.exact.as_ref().map(|exact| {
+        exact
+            .iter()
+            .map(|outcome| outcome.p.to_string())
+            .collect::<Vec<_>>()
+    });
 
+    let intervals = distribution
+        .simulation
+        .as_ref()
+        .map(|simulation| simulation.confidence_intervals());
+
+    let iter = pmf.iter();
     let mut seq = serializer.serialize_seq(iter.size_hint().1)?;
-    for outcome in iter {
+    for (i, outcome) in iter.enumerate() {
         seq.serialize_element(&Outcome {
             value: &outcome.value,
             p: outcome.p,
+            exact: fractions.as_ref().map(|fractions| fractions[i].clone()),
+            interval: intervals.as_ref().map(|intervals| intervals[i]),
+            cdf: cdf.as_ref().map(|cdf| cdf[i]),
+            at_least: at_least.as_ref().map(|at_least| at_least[i]),
         })?;
     }
     seq.end()
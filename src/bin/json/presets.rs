@@ -0,0 +1,31 @@
+use crate::command::PresetListing;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Presets(Vec<Preset>);
+
+#[derive(Serialize)]
+struct Preset {
+    name: &'static str,
+    usage: &'static str,
+    description: &'static str,
+    example: &'static str,
+    expansion: String,
+}
+
+impl Presets {
+    pub fn new(presets: Vec<PresetListing>) -> Self {
+        Self(
+            presets
+                .into_iter()
+                .map(|listing| Preset {
+                    name: listing.preset.name,
+                    usage: listing.preset.usage,
+                    description: listing.preset.description,
+                    example: listing.preset.example,
+                    expansion: listing.expansion,
+                })
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,23 @@
+use crate::command::Answer;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Query {
+    query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    given: Option<String>,
+    p: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exact: Option<String>,
+}
+
+impl Query {
+    pub fn new(answer: Answer) -> Self {
+        Self {
+            query: answer.query.to_string(),
+            given: answer.given.map(|given| given.to_string()),
+            p: answer.p,
+            exact: answer.exact.map(|exact| exact.to_string()),
+        }
+    }
+}
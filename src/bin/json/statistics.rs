@@ -0,0 +1,51 @@
+use super::ibig::{serialize_opt_ibig, IBigSerializer};
+use crate::command::{Statistic, StatisticValue};
+use ibig::IBig;
+use roll::pmf::Pmf;
+use serde::{Serialize, Serializer};
+
+/// Statistics keyed by their command line names, in the order they were chosen.
+pub struct Statistics(Vec<(String, StatisticValue)>);
+
+impl Statistics {
+    pub fn new(pmf: &Pmf<IBig>, statistics: &[Statistic]) -> Self {
+        Self(
+            statistics
+                .iter()
+                .map(|statistic| (statistic.name(), statistic.value(pmf)))
+                .collect(),
+        )
+    }
+}
+
+impl Serialize for Statistics {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in self.0.iter() {
+            map.serialize_entry(name, &Value(value))?;
+        }
+        map.end()
+    }
+}
+
+struct Value<'a>(&'a StatisticValue);
+
+impl<'a> Serialize for Value<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            StatisticValue::Integer(n) => serialize_opt_ibig(n, serializer),
+            StatisticValue::Integers(ns) => {
+                serializer.collect_seq(ns.iter().map(IBigSerializer::new))
+            }
+            StatisticValue::Real(x) => x.serialize(serializer),
+        }
+    }
+}
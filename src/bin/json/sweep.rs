@@ -0,0 +1,90 @@
+use super::ibig::IBigSerializer;
+use super::statistics::Statistics;
+use crate::command::{self, SweepResult};
+use ibig::IBig;
+use serde::{Serialize, Serializer};
+
+#[derive(Serialize)]
+pub struct Sweep {
+    expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    given: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    variables: Vec<Variable>,
+    rows: Vec<Row>,
+}
+
+#[derive(Serialize)]
+struct Variable {
+    name: String,
+    #[serde(serialize_with = "serialize_values")]
+    values: Vec<IBig>,
+}
+
+#[derive(Serialize)]
+struct Row {
+    variables: Values,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<f64>,
+    statistics: Statistics,
+}
+
+/// The value of each variable, keyed by its name.
+struct Values(Vec<(String, IBig)>);
+
+impl Sweep {
+    pub fn new(sweep: SweepResult, command: &command::Sweep) -> Self {
+        let statistics = command.statistics();
+        let names = sweep
+            .variables
+            .iter()
+            .map(|variable| variable.name.clone())
+            .collect::<Vec<_>>();
+
+        Self {
+            expression: sweep.expression.to_string(),
+            given: sweep.given.map(|given| given.to_string()),
+            query: sweep.query.map(|query| query.to_string()),
+            variables: sweep
+                .variables
+                .into_iter()
+                .map(|variable| Variable {
+                    name: variable.name,
+                    values: variable.values,
+                })
+                .collect(),
+            rows: sweep
+                .points
+                .into_iter()
+                .map(|point| Row {
+                    variables: Values(names.iter().cloned().zip(point.values).collect()),
+                    p: point.p,
+                    statistics: Statistics::new(&point.pmf, &statistics),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Serialize for Values {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, value) in self.0.iter() {
+            map.serialize_entry(name, &IBigSerializer::new(value))?;
+        }
+        map.end()
+    }
+}
+
+fn serialize_values<S>(values: &[IBig], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(values.iter().map(IBigSerializer::new))
+}
@@ -0,0 +1,54 @@
+use super::Evaluand;
+use crate::command::{Rng, TableDistribution, TableRoll};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Table {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    roll: Evaluand,
+    result: String,
+}
+
+impl Table {
+    pub fn new(roll: TableRoll, rng: Rng) -> Self {
+        Self {
+            name: roll.name,
+            roll: Evaluand::new(roll.evaluand, Some(rng)),
+            result: roll.result,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TablePmf {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    entries: Vec<Entry>,
+    unmatched: f64,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    range: String,
+    result: String,
+    p: f64,
+}
+
+impl TablePmf {
+    pub fn new(distribution: TableDistribution) -> Self {
+        Self {
+            name: distribution.name,
+            entries: distribution
+                .entries
+                .into_iter()
+                .map(|entry| Entry {
+                    range: entry.range,
+                    result: entry.result,
+                    p: entry.p,
+                })
+                .collect(),
+            unmatched: distribution.unmatched,
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::ibig::serialize_ibig;
+use crate::command;
+use ibig::IBig;
+use roll::verification;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Verify {
+    expression: String,
+    samples: u64,
+    seed: u64,
+    alpha: f64,
+    g: f64,
+    degrees_of_freedom: usize,
+    p_value: f64,
+    passed: bool,
+    buckets: Vec<Bucket>,
+    impossible: Vec<Impossible>,
+}
+
+#[derive(Serialize)]
+pub(super) struct Bucket {
+    #[serde(serialize_with = "serialize_ibig")]
+    min: IBig,
+    #[serde(serialize_with = "serialize_ibig")]
+    max: IBig,
+    observed: u64,
+    expected: f64,
+    residual: f64,
+    significant: bool,
+}
+
+#[derive(Serialize)]
+pub(super) struct Impossible {
+    #[serde(serialize_with = "serialize_ibig")]
+    value: IBig,
+    count: u64,
+}
+
+impl Verify {
+    pub fn new(verification: verification::Verification, command: &command::Verify) -> Self {
+        Self {
+            expression: command.expression().to_string(),
+            samples: verification.samples,
+            seed: command.seed(),
+            alpha: verification.alpha,
+            g: verification.g,
+            degrees_of_freedom: verification.degrees_of_freedom,
+            p_value: verification.p_value,
+            passed: verification.passed(),
+            buckets: verification.buckets.into_iter().map(Bucket::from).collect(),
+            impossible: verification
+                .impossible
+                .into_iter()
+                .map(Impossible::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<verification::Bucket> for Bucket {
+    fn from(bucket: verification::Bucket) -> Self {
+        Self {
+            min: bucket.min,
+            max: bucket.max,
+            observed: bucket.observed,
+            expected: bucket.expected,
+            residual: bucket.residual,
+            significant: bucket.significant,
+        }
+    }
+}
+
+impl From<(IBig, u64)> for Impossible {
+    fn from((value, count): (IBig, u64)) -> Self {
+        Self { value, count }
+    }
+}
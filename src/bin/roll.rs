@@ -4,8 +4,12 @@ use log::trace;
 
 mod cli;
 mod command;
+mod export;
 mod json;
+mod svg;
 
+// Newer toolchains deprecate the `PanicInfo` alias `setup_panic!` expands to.
+#[allow(deprecated)]
 fn main() -> Result<()> {
     setup_panic!();
     env_logger::init();
@@ -17,8 +21,16 @@ fn main() -> Result<()> {
     let output = cmd.exec()?;
     trace!("Evaluated: {:?}", output);
 
+    output.write_svg(&args)?;
+    output.write_rng(&args);
+
+    let passed = output.passed();
     let formatter = output.formatter(args);
     println!("{}", formatter);
 
+    if !passed {
+        std::process::exit(1);
+    }
+
     Ok(())
 }
@@ -0,0 +1,391 @@
+use crate::command::{PmfColumns, Statistic};
+use ibig::ops::UnsignedAbs;
+use ibig::{IBig, UBig};
+use roll::pmf::Pmf;
+use std::fmt::Write;
+
+const WIDTH: f64 = 720.0;
+const HEIGHT: f64 = 420.0;
+const LEFT: f64 = 64.0;
+const TOP: f64 = 56.0;
+const BOTTOM: f64 = 52.0;
+
+// The terminal colours of `roll compare`, in the same order.
+const PALETTE: [&str; 6] = [
+    "#1f77b4", "#2ca02c", "#e0a800", "#9467bd", "#17becf", "#d62728",
+];
+
+/// A bar chart of one or more distributions, overlaid on the same axes, with a dashed line at
+/// each mean and dotted lines at the chosen quantiles.
+pub struct Chart<'a> {
+    series: Vec<(String, &'a Pmf<IBig>)>,
+    columns: PmfColumns,
+    quantiles: Vec<f64>,
+}
+
+impl<'a> Chart<'a> {
+    /// Marks the quantiles among `statistics`, or the median when there are none.
+    pub fn new(
+        series: Vec<(String, &'a Pmf<IBig>)>,
+        columns: PmfColumns,
+        statistics: &[Statistic],
+    ) -> Self {
+        let mut quantiles = statistics
+            .iter()
+            .filter_map(|statistic| match statistic {
+                Statistic::Median => Some(0.5),
+                Statistic::Quantile(percent) => Some(percent / 100.0),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if quantiles.is_empty() {
+            quantiles.push(0.5);
+        }
+
+        Self {
+            series,
+            columns,
+            quantiles,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut svg = String::new();
+        // Writing to a `String` never fails.
+        self.write(&mut svg).unwrap();
+
+        svg
+    }
+
+    fn write(&self, svg: &mut String) -> std::fmt::Result {
+        let cumulative = self.columns.cdf || self.columns.at_least;
+        let right = if cumulative { 64.0 } else { 24.0 };
+        let (plot_width, plot_height) = (WIDTH - LEFT - right, HEIGHT - TOP - BOTTOM);
+
+        let pmfs = self.series.iter().map(|(_, pmf)| pmf);
+        let min = pmfs
+            .clone()
+            .filter_map(|pmf| pmf.iter().next())
+            .map(|outcome| &outcome.value)
+            .min();
+        let max = pmfs
+            .clone()
+            .filter_map(|pmf| pmf.iter().last())
+            .map(|outcome| &outcome.value)
+            .max();
+        let (min, max) = match (min, max) {
+            (Some(min), Some(max)) => (min.clone(), max.clone()),
+            _ => (IBig::from(0u8), IBig::from(0u8)),
+        };
+        let max_p = pmfs
+            .flat_map(|pmf| pmf.iter().map(|outcome| outcome.p))
+            .fold(0.0, f64::max);
+
+        // Each value is the centre of a band, so the bars at the ends are whole.
+        let span = (&max - &min).to_f64() + 1.0;
+        let x = |value: f64| LEFT + (value + 0.5) / span * plot_width;
+        let x_of = |value: &IBig| x((value - &min).to_f64());
+        let y_step = nice_step(max_p / 5.0);
+        let y_max = ((max_p / y_step).ceil() * y_step).max(y_step);
+        let y = |p: f64| TOP + plot_height * (1.0 - p / y_max);
+        let y_cumulative = |p: f64| TOP + plot_height * (1.0 - p);
+
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#,
+            WIDTH, HEIGHT, WIDTH, HEIGHT
+        )?;
+        writeln!(
+            svg,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            WIDTH, HEIGHT
+        )?;
+
+        let title = self
+            .series
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect::<Vec<_>>()
+            .join(" vs ");
+        writeln!(
+            svg,
+            r#"<text x="{}" y="22" text-anchor="middle" font-size="16" font-weight="bold">{}</text>"#,
+            WIDTH / 2.0,
+            escape(&title)
+        )?;
+
+        // The probability axis, with a gridline at each tick.
+        let decimals = (-(y_step * 100.0).log10()).ceil().max(0.0) as usize;
+        let mut tick = 0.0;
+        while tick <= y_max + y_step / 2.0 {
+            writeln!(
+                svg,
+                r##"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="#e0e0e0"/>"##,
+                LEFT,
+                y(tick),
+                LEFT + plot_width,
+                y(tick)
+            )?;
+            writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="end" dominant-baseline="middle">{:.*}%</text>"#,
+                LEFT - 6.0,
+                y(tick),
+                decimals,
+                tick * 100.0
+            )?;
+            tick += y_step;
+        }
+
+        // The outcome axis, with ticks at round numbers far enough apart for their labels.
+        let label_width = 7.0 * min.to_string().len().max(max.to_string().len()) as f64 + 12.0;
+        let ticks = (plot_width / label_width).clamp(1.0, 10.0);
+        let x_step = IBig::from(nice_step(span / ticks).max(1.0) as u64);
+        let offset = ((&min % &x_step) + &x_step) % &x_step;
+        let mut tick = match offset == IBig::from(0u8) {
+            true => min.clone(),
+            false => &min - &offset + &x_step,
+        };
+        while tick <= max {
+            writeln!(
+                svg,
+                r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black"/>"#,
+                x_of(&tick),
+                TOP + plot_height,
+                x_of(&tick),
+                TOP + plot_height + 5.0
+            )?;
+            writeln!(
+                svg,
+                r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                x_of(&tick),
+                TOP + plot_height + 18.0,
+                tick
+            )?;
+            tick += &x_step;
+        }
+
+        // Overlaid bars are translucent, so each distribution shows through the others.
+        let opacity = match self.series.len() {
+            1 => 0.85,
+            _ => 0.5,
+        };
+        let bar_width = (plot_width / span * 0.9).max(0.5);
+        for (i, (_, pmf)) in self.series.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            for outcome in pmf.iter() {
+                writeln!(
+                    svg,
+                    r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}" fill-opacity="{}"/>"#,
+                    x_of(&outcome.value) - bar_width / 2.0,
+                    y(outcome.p),
+                    bar_width,
+                    TOP + plot_height - y(outcome.p),
+                    color,
+                    opacity
+                )?;
+            }
+        }
+
+        for (i, (_, pmf)) in self.series.iter().enumerate() {
+            let color = PALETTE[i % PALETTE.len()];
+            let points = |probabilities: Vec<f64>| {
+                pmf.iter()
+                    .zip(probabilities)
+                    .map(|(outcome, p)| {
+                        format!("{:.2},{:.2}", x_of(&outcome.value), y_cumulative(p))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+
+            if self.columns.cdf {
+                writeln!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2"/>"#,
+                    points(pmf.cdf()),
+                    color
+                )?;
+            }
+            if self.columns.at_least {
+                writeln!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="2" stroke-dasharray="6 3"/>"#,
+                    points(pmf.at_least()),
+                    color
+                )?;
+            }
+
+            // Labels are stacked, so the markers of overlaid distributions stay readable.
+            // The mean is measured from the smallest outcome, which is exact for huge outcomes.
+            let mean = pmf
+                .iter()
+                .map(|outcome| outcome.p * (&outcome.value - &min).to_f64())
+                .sum::<f64>();
+            let mut markers = vec![(format!("mean {}", offset_label(&min, mean)), x(mean), "6 4")];
+            for quantile in self.quantiles.iter() {
+                if let Some(value) = pmf.quantile(*quantile) {
+                    markers.push((
+                        format!("p{} {}", quantile * 100.0, value),
+                        x_of(value),
+                        "2 3",
+                    ));
+                }
+            }
+            for (j, (label, position, dashes)) in markers.iter().enumerate() {
+                let row = (i * markers.len() + j) as f64;
+                writeln!(
+                    svg,
+                    r#"<line x1="{:.2}" y1="{:.1}" x2="{:.2}" y2="{:.1}" stroke="{}" stroke-width="1.5" stroke-dasharray="{}"/>"#,
+                    position,
+                    TOP,
+                    position,
+                    TOP + plot_height,
+                    color,
+                    dashes
+                )?;
+                writeln!(
+                    svg,
+                    r#"<text x="{:.2}" y="{:.1}" fill="{}" font-size="11">{}</text>"#,
+                    position + 3.0,
+                    TOP + 12.0 + 13.0 * row,
+                    color,
+                    escape(label)
+                )?;
+            }
+        }
+
+        if cumulative {
+            for tick in [0.0, 0.25, 0.5, 0.75, 1.0] {
+                writeln!(
+                    svg,
+                    r#"<text x="{:.1}" y="{:.1}" dominant-baseline="middle">{}%</text>"#,
+                    LEFT + plot_width + 6.0,
+                    y_cumulative(tick),
+                    tick * 100.0
+                )?;
+            }
+            writeln!(
+                svg,
+                r#"<text transform="translate({:.1},{:.1}) rotate(90)" text-anchor="middle">cumulative</text>"#,
+                WIDTH - 12.0,
+                TOP + plot_height / 2.0
+            )?;
+        }
+
+        // The legend names the colour of each overlaid distribution.
+        if self.series.len() > 1 {
+            let mut position = LEFT;
+            for (i, (label, _)) in self.series.iter().enumerate() {
+                writeln!(
+                    svg,
+                    r#"<rect x="{:.1}" y="34" width="12" height="12" fill="{}" fill-opacity="{}"/>"#,
+                    position,
+                    PALETTE[i % PALETTE.len()],
+                    opacity
+                )?;
+                writeln!(
+                    svg,
+                    r#"<text x="{:.1}" y="44">{}</text>"#,
+                    position + 16.0,
+                    escape(label)
+                )?;
+                position += 32.0 + 7.0 * label.chars().count() as f64;
+            }
+        }
+
+        writeln!(
+            svg,
+            r#"<line x1="{}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black"/>"#,
+            LEFT,
+            TOP + plot_height,
+            LEFT + plot_width,
+            TOP + plot_height
+        )?;
+        writeln!(
+            svg,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{:.1}" stroke="black"/>"#,
+            LEFT,
+            TOP,
+            LEFT,
+            TOP + plot_height
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">outcome</text>"#,
+            LEFT + plot_width / 2.0,
+            HEIGHT - 10.0
+        )?;
+        writeln!(
+            svg,
+            r#"<text transform="translate(14,{:.1}) rotate(-90)" text-anchor="middle">probability</text>"#,
+            TOP + plot_height / 2.0
+        )?;
+        write!(svg, "</svg>")
+    }
+}
+
+// `min + offset` to two decimal places, without rounding `min` through a float.
+fn offset_label(min: &IBig, offset: f64) -> String {
+    let hundred = IBig::from(100u8);
+    let value = IBig::from((offset * 100.0).round() as i64) + min * &hundred;
+    let sign = match value < IBig::from(0u8) {
+        true => "-",
+        false => "",
+    };
+    let magnitude = value.unsigned_abs();
+    let hundred = UBig::from(100u8);
+
+    format!(
+        "{}{}.{:0>2}",
+        sign,
+        &magnitude / &hundred,
+        &magnitude % &hundred
+    )
+}
+
+// The smallest of 1, 2 or 5 times a power of ten that is at least `x`.
+fn nice_step(x: f64) -> f64 {
+    if x <= 0.0 || !x.is_finite() {
+        return 1.0;
+    }
+
+    let power = 10f64.powf(x.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .iter()
+        .map(|multiple| multiple * power)
+        .find(|step| *step >= x)
+        .unwrap_or(10.0 * power)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn offset_labels() {
+        assert_eq!(offset_label(&IBig::from(3), 7.5), "10.50");
+        assert_eq!(offset_label(&IBig::from(-3), 0.25), "-2.75");
+        assert_eq!(offset_label(&IBig::from(-1), 0.5), "-0.50");
+        assert_eq!(
+            offset_label(&(IBig::from(2u8).pow(70) + 3), 7.5),
+            "1180591620717411303434.50"
+        );
+    }
+
+    #[test]
+    fn nice_steps() {
+        assert_eq!(nice_step(0.03), 0.05);
+        assert_eq!(nice_step(0.05), 0.05);
+        assert_eq!(nice_step(7.0), 10.0);
+        assert_eq!(nice_step(12.0), 20.0);
+        assert_eq!(nice_step(0.0), 1.0);
+    }
+}
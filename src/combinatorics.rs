@@ -1,3 +1,5 @@
+use crate::iter_func::IterFunc;
+use crate::probability::ratio_to_f64;
 use ibig::UBig;
 use num_traits::{One, Zero};
 use std::collections::HashMap;
@@ -5,6 +7,7 @@ use std::collections::HashMap;
 #[derive(Default)]
 pub struct Combinations {
     pascal: HashMap<usize, UBig>,
+    keep_highest: HashMap<(usize, usize, usize, usize), UBig>,
 }
 
 impl Combinations {
@@ -14,9 +17,10 @@ impl Combinations {
         //  * https://github.com/carlosvega/DiceProbabilities
         //  * https://www.omnicalculator.com/statistics/dice
         //  * https://marvelvietnam.com/top2/bai-viet/dice-from-wolfram-mathworld/2478703015
-        self.dice_roll_sum_count(sum, n_dice as usize, sides)
-            .to_f64()
-            / (UBig::from(sides)).pow(n_dice).to_f64()
+        ratio_to_f64(
+            &self.dice_roll_sum_count(sum, n_dice, sides),
+            &UBig::from(sides).pow(n_dice),
+        )
     }
 
     pub fn dice_roll_sum_count(&mut self, sum: usize, n_dice: usize, sides: usize) -> UBig {
@@ -39,6 +43,176 @@ impl Combinations {
         count
     }
 
+    pub fn probability_hypergeometric(&mut self, hand: &[usize], deck: &[usize]) -> f64 {
+        // Reference: https://en.wikipedia.org/wiki/Hypergeometric_distribution#Multivariate_hypergeometric_distribution
+        let total = self.choose(deck.iter().sum(), hand.iter().sum());
+
+        ratio_to_f64(&self.hypergeometric_count(hand, deck), &total)
+    }
+
+    // The number of ways to draw `hand[i]` of the `deck[i]` cards of each kind.
+    pub fn hypergeometric_count(&mut self, hand: &[usize], deck: &[usize]) -> UBig {
+        hand.iter()
+            .zip(deck.iter())
+            .fold(UBig::one(), |count, (drawn, cards)| {
+                count * self.choose(*cards, *drawn)
+            })
+    }
+
+    pub fn probability_order_statistic(
+        &mut self,
+        k: usize,
+        value: usize,
+        n_dice: usize,
+        sides: usize,
+    ) -> f64 {
+        ratio_to_f64(
+            &self.order_statistic_count(k, value, n_dice, sides),
+            &UBig::from(sides).pow(n_dice),
+        )
+    }
+
+    // The number of rolls of `n_dice` where the `k`th highest die, counting from 1, shows `value`.
+    pub fn order_statistic_count(
+        &mut self,
+        k: usize,
+        value: usize,
+        n_dice: usize,
+        sides: usize,
+    ) -> UBig {
+        // Reference: https://en.wikipedia.org/wiki/Order_statistic#Dealing_with_discrete_variables
+        if k == 0 || k > n_dice || value == 0 || value > sides {
+            return UBig::zero();
+        }
+
+        self.order_statistic_at_most(k, value, n_dice, sides)
+            - self.order_statistic_at_most(k, value - 1, n_dice, sides)
+    }
+
+    // The number of rolls of `n_dice` where the highest dice show `top`, in non-increasing order.
+    pub fn top_k_count(&mut self, top: &[usize], n_dice: usize, sides: usize) -> UBig {
+        let lowest = match top.last() {
+            Some(lowest) => *lowest,
+            None => return UBig::from(sides).pow(n_dice),
+        };
+
+        let ordered = top.windows(2).all(|pair| pair[0] >= pair[1]);
+        if !ordered || top.len() > n_dice || lowest == 0 || top[0] > sides {
+            return UBig::zero();
+        }
+
+        // The dice above the lowest kept value are arranged among themselves, then the remaining
+        // dice show at most the lowest value, at least as many times as it was kept.
+        let ties = top.iter().filter(|value| **value == lowest).count();
+        let above = top.len() - ties;
+        let mut count = self.choose(n_dice, above);
+        let mut placed = 0;
+        for (_, copies) in top[..above]
+            .iter()
+            .group_by(|value| *value, |value, copies| (value, copies.len() + 1))
+        {
+            placed += copies;
+            count *= self.choose(placed, copies);
+        }
+
+        count * self.at_least_ties(ties, lowest, n_dice - above)
+    }
+
+    pub fn probability_keep_highest_sum(
+        &mut self,
+        sum: usize,
+        keep: usize,
+        n_dice: usize,
+        sides: usize,
+    ) -> f64 {
+        ratio_to_f64(
+            &self.keep_highest_sum_count(sum, keep, n_dice, sides),
+            &UBig::from(sides).pow(n_dice),
+        )
+    }
+
+    // The number of rolls of `n_dice` where the highest `keep` dice add up to `sum`.
+    pub fn keep_highest_sum_count(
+        &mut self,
+        sum: usize,
+        keep: usize,
+        n_dice: usize,
+        sides: usize,
+    ) -> UBig {
+        if keep == 0 {
+            return match sum {
+                0 => UBig::from(sides).pow(n_dice),
+                _ => UBig::zero(),
+            };
+        }
+
+        if keep > n_dice || sides == 0 {
+            return UBig::zero();
+        }
+
+        if let Some(count) = self.keep_highest.get(&(sum, keep, n_dice, sides)) {
+            return count.clone();
+        }
+
+        // Every roll is counted once, by the value of its lowest kept die and the number of dice
+        // above it. The dice above it are all kept, and the rest show at most the lowest value.
+        let mut count = UBig::zero();
+        for lowest in 1..=sides {
+            for above in 0..keep {
+                let rest = match sum.checked_sub(keep * lowest) {
+                    Some(rest) => rest,
+                    None => continue,
+                };
+
+                let count_above = if above == 0 {
+                    if rest == 0 {
+                        UBig::one()
+                    } else {
+                        continue;
+                    }
+                } else if rest < above || rest > above * (sides - lowest) {
+                    continue;
+                } else {
+                    self.dice_roll_sum_count(rest, above, sides - lowest)
+                };
+
+                count += self.choose(n_dice, above)
+                    * count_above
+                    * self.at_least_ties(keep - above, lowest, n_dice - above);
+            }
+        }
+
+        self.keep_highest
+            .insert((sum, keep, n_dice, sides), count.clone());
+
+        count
+    }
+
+    // The number of rolls where the `k`th highest of `n_dice` is at most `value`, i.e. fewer than
+    // `k` dice are higher than `value`.
+    fn order_statistic_at_most(
+        &mut self,
+        k: usize,
+        value: usize,
+        n_dice: usize,
+        sides: usize,
+    ) -> UBig {
+        (0..k).fold(UBig::zero(), |count, higher| {
+            count
+                + self.choose(n_dice, higher)
+                    * UBig::from(sides - value).pow(higher)
+                    * UBig::from(value).pow(n_dice - higher)
+        })
+    }
+
+    // The number of rolls of `n_dice` that all show at most `value` and show `value` at least
+    // `ties` times.
+    fn at_least_ties(&mut self, ties: usize, value: usize, n_dice: usize) -> UBig {
+        (ties..=n_dice).fold(UBig::zero(), |count, equal| {
+            count + self.choose(n_dice, equal) * UBig::from(value - 1).pow(n_dice - equal)
+        })
+    }
+
     pub fn choose(&mut self, n: usize, r: usize) -> UBig {
         if r > n {
             return Zero::zero();
@@ -200,6 +374,178 @@ mod test {
         TestResult::from_bool(total_rolls == UBig::from(sides).pow(Into::<usize>::into(n_dice)))
     }
 
+    #[quickcheck]
+    fn test_hypergeometric_vandermonde_identity(deck: Vec<u8>, n: u8) -> TestResult {
+        let deck = deck
+            .into_iter()
+            .take(4)
+            .map(|cards| usize::from(cards % 16))
+            .collect::<Vec<_>>();
+        let n = usize::from(n);
+
+        if n > deck.iter().sum() {
+            return TestResult::discard();
+        }
+
+        let mut comb = Combinations::default();
+        let total = hands(&deck, n).iter().fold(UBig::zero(), |total, hand| {
+            total + comb.hypergeometric_count(hand, &deck)
+        });
+
+        TestResult::from_bool(total == comb.choose(deck.iter().sum(), n))
+    }
+
+    #[quickcheck]
+    fn test_order_statistic_total(k: u8, n_dice: u8, sides: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 16);
+        let sides = usize::from(sides % 32);
+        let k = usize::from(k) % (n_dice + 1);
+
+        if k == 0 {
+            return TestResult::discard();
+        }
+
+        let mut comb = Combinations::default();
+        let total = (1..=sides).fold(UBig::zero(), |total, value| {
+            total + comb.order_statistic_count(k, value, n_dice, sides)
+        });
+
+        TestResult::from_bool(total == UBig::from(sides).pow(n_dice))
+    }
+
+    #[quickcheck]
+    fn test_order_statistic_symmetry(k: u8, value: u8, n_dice: u8, sides: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 16);
+        let sides = usize::from(sides % 32);
+        let k = usize::from(k) % (n_dice + 1);
+        let value = usize::from(value);
+
+        if k == 0 || value == 0 || value > sides {
+            return TestResult::discard();
+        }
+
+        // The kth highest die showing a value mirrors the kth lowest die showing its opposite.
+        let mut comb = Combinations::default();
+        TestResult::from_bool(
+            comb.order_statistic_count(k, value, n_dice, sides)
+                == comb.order_statistic_count(n_dice - k + 1, sides + 1 - value, n_dice, sides),
+        )
+    }
+
+    #[quickcheck]
+    fn test_top_k_marginal(k: u8, value: u8, n_dice: u8, sides: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 5);
+        let sides = usize::from(sides % 7);
+        let k = usize::from(k) % (n_dice + 1);
+        let value = usize::from(value);
+
+        if k == 0 || value == 0 || value > sides {
+            return TestResult::discard();
+        }
+
+        // Summing the joint distribution of the top k dice over every kth value but one leaves
+        // the order statistic.
+        let mut comb = Combinations::default();
+        let total = tops(k, sides)
+            .iter()
+            .filter(|top| top[k - 1] == value)
+            .fold(UBig::zero(), |total, top| {
+                total + comb.top_k_count(top, n_dice, sides)
+            });
+
+        TestResult::from_bool(total == comb.order_statistic_count(k, value, n_dice, sides))
+    }
+
+    #[quickcheck]
+    fn test_top_k_brute_force(n_dice: u8, sides: u8, k: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 5);
+        let sides = usize::from(sides % 6);
+        let k = usize::from(k) % (n_dice + 1);
+
+        let mut counts = HashMap::<Vec<usize>, UBig>::new();
+        for roll in rolls(n_dice, sides) {
+            let mut roll = roll;
+            roll.sort_unstable_by(|a, b| b.cmp(a));
+            roll.truncate(k);
+
+            *counts.entry(roll).or_insert_with(UBig::zero) += UBig::one();
+        }
+
+        let mut comb = Combinations::default();
+        TestResult::from_bool(
+            counts
+                .iter()
+                .all(|(top, count)| comb.top_k_count(top, n_dice, sides) == *count),
+        )
+    }
+
+    #[quickcheck]
+    fn test_keep_highest_sum_total(keep: u8, n_dice: u8, sides: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 12);
+        let sides = usize::from(sides % 20);
+        let keep = usize::from(keep) % (n_dice + 1);
+
+        let mut comb = Combinations::default();
+        let total = (0..=keep * sides).fold(UBig::zero(), |total, sum| {
+            total + comb.keep_highest_sum_count(sum, keep, n_dice, sides)
+        });
+
+        TestResult::from_bool(total == UBig::from(sides).pow(n_dice))
+    }
+
+    #[quickcheck]
+    fn test_keep_highest_sum_identities(sum: u8, n_dice: u8, sides: u8) -> TestResult {
+        let n_dice = usize::from(n_dice % 12);
+        let sides = usize::from(sides % 20);
+        let sum = usize::from(sum);
+
+        if n_dice == 0 || sides == 0 || sum < n_dice || sum > n_dice * sides {
+            return TestResult::discard();
+        }
+
+        // Keeping every die is an ordinary sum, and keeping one die is the highest die.
+        let mut comb = Combinations::default();
+        TestResult::from_bool(
+            comb.keep_highest_sum_count(sum, n_dice, n_dice, sides)
+                == comb.dice_roll_sum_count(sum, n_dice, sides)
+                && comb.keep_highest_sum_count(sum.min(sides), 1, n_dice, sides)
+                    == comb.order_statistic_count(1, sum.min(sides), n_dice, sides),
+        )
+    }
+
+    #[test]
+    fn test_keep_highest_three_of_four_dee_six() {
+        let mut comb = Combinations::default();
+
+        assert_eq!(comb.keep_highest_sum_count(3, 3, 4, 6), UBig::from(1_u8));
+        assert_eq!(comb.keep_highest_sum_count(10, 3, 4, 6), UBig::from(122_u8));
+        assert_eq!(comb.keep_highest_sum_count(18, 3, 4, 6), UBig::from(21_u8));
+    }
+
+    #[test]
+    fn test_order_statistic_median_of_three() {
+        let mut comb = Combinations::default();
+
+        // The median of 3d20 is 10 when one die shows 10 and the others fall on either side, or
+        // two or three dice show 10.
+        float_eq::assert_float_eq!(
+            comb.probability_order_statistic(2, 10, 3, 20),
+            (6.0 * 9.0 * 10.0 + 3.0 * 19.0 + 1.0) / 8000.0,
+            abs <= 0.000001
+        );
+    }
+
+    #[test]
+    fn test_hypergeometric_aces() {
+        let mut comb = Combinations::default();
+
+        float_eq::assert_float_eq!(
+            comb.probability_hypergeometric(&[1, 4], &[4, 48]),
+            4.0 * 194580.0 / 2598960.0,
+            abs <= 0.000001
+        );
+    }
+
     #[test]
     fn test_dee_twenty() {
         let mut comb = Combinations::default();
@@ -245,11 +591,75 @@ mod test {
         assert_eq!(comb.probability_dice_roll_sum(18, 3, 6), 1.0 / 216.0);
     }
 
+    #[test]
+    fn probability_dice_roll_sum_beyond_f64() {
+        let mut comb = Combinations::default();
+
+        // 6^400 and the number of ways to roll the sum both exceed the largest f64.
+        let p = comb.probability_dice_roll_sum(1400, 400, 6);
+
+        assert!(p.is_finite() && p > 0.0 && p < 1.0);
+        float_eq::assert_float_eq!(
+            comb.probability_dice_roll_sum(400, 400, 6),
+            (-400.0 * 6_f64.ln()).exp(),
+            rmax <= 1e-6
+        );
+    }
+
     fn choose(n: usize, r: usize) -> UBig {
         let mut comb = Combinations::default();
         comb.choose(n, r)
     }
 
+    fn hands(deck: &[usize], n: usize) -> Vec<Vec<usize>> {
+        match deck {
+            [] if n == 0 => vec![vec![]],
+            [] => vec![],
+            [cards, rest @ ..] => (0..=n.min(*cards))
+                .flat_map(|drawn| {
+                    hands(rest, n - drawn).into_iter().map(move |mut hand| {
+                        hand.insert(0, drawn);
+                        hand
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    // Every non-increasing sequence of `k` faces.
+    fn tops(k: usize, sides: usize) -> Vec<Vec<usize>> {
+        match k {
+            0 => vec![vec![]],
+            _ => tops(k - 1, sides)
+                .into_iter()
+                .flat_map(|top| {
+                    let highest = top.last().copied().unwrap_or(sides);
+
+                    (1..=highest).map(move |value| {
+                        let mut top = top.clone();
+                        top.push(value);
+                        top
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn rolls(n_dice: usize, sides: usize) -> Vec<Vec<usize>> {
+        (0..n_dice).fold(vec![vec![]], |rolls, _| {
+            rolls
+                .into_iter()
+                .flat_map(|roll| {
+                    (1..=sides).map(move |face| {
+                        let mut roll = roll.clone();
+                        roll.push(face);
+                        roll
+                    })
+                })
+                .collect()
+        })
+    }
+
     fn factorial(n: usize) -> UBig {
         let product = One::one();
         if n == 0 {
@@ -0,0 +1,470 @@
+use crate::combinatorics::Combinations;
+use crate::pmf::Pmf;
+use crate::probability::{Probability, Ratio};
+use ibig::{rand::UniformIBig, IBig, UBig};
+use num_traits::{One, Zero};
+use rand::distributions::uniform::UniformSampler;
+use rand::Rng;
+
+/// Distributions with infinite support, e.g. geometric, are truncated once the remaining tail
+/// has less than this probability.
+pub const TAIL: f64 = 1e-12;
+
+/// The most outcomes a truncated distribution may keep. Wider distributions can only be sampled.
+pub const MAX_OUTCOMES: usize = 1_000_000;
+
+/// The number of successes in `n` independent trials that each succeed with probability `p`.
+pub fn binomial(n: usize, p: f64, combinations: &mut Combinations) -> Pmf<IBig> {
+    if p <= 0.0 || p >= 1.0 {
+        return Pmf::constant(if p <= 0.0 {
+            IBig::zero()
+        } else {
+            IBig::from(n)
+        });
+    }
+
+    // Computed in log space; the coefficients and powers overflow f64 for large `n`.
+    (0..=n)
+        .map(|k| {
+            let ln_p = ln(&combinations.choose(n, k))
+                + k as f64 * p.ln()
+                + (n - k) as f64 * (1.0 - p).ln();
+
+            (ln_p.exp(), IBig::from(k))
+        })
+        .collect()
+}
+
+/// The number of trials, up to and including the first success, of trials that each succeed
+/// with probability `p`. `None` if the truncated distribution has more than `MAX_OUTCOMES`.
+pub fn geometric(p: f64) -> Option<Pmf<IBig>> {
+    if p >= 1.0 {
+        return Some(Pmf::constant(IBig::one()));
+    }
+
+    let trials = geometric_trials(p);
+    if trials > MAX_OUTCOMES as f64 {
+        return None;
+    }
+
+    let ln_q = (-p).ln_1p();
+    Some(
+        (1..=trials as usize)
+            .map(|k| (((k - 1) as f64 * ln_q).exp() * p, IBig::from(k)))
+            .collect(),
+    )
+}
+
+/// The number of events in an interval when events occur independently at an average rate of
+/// `lambda` per interval. `None` if the truncated distribution has more than `MAX_OUTCOMES`.
+pub fn poisson(lambda: f64) -> Option<Pmf<IBig>> {
+    if lambda <= 0.0 {
+        return Some(Pmf::constant(IBig::zero()));
+    }
+
+    // Walks out from the mode, where the probabilities are largest, with each probability
+    // relative to the mode's; e^-lambda underflows for large rates. Each side stops once its
+    // remaining tail, which shrinks faster than a geometric series, is negligible.
+    let mode = lambda.floor();
+    let mut outcomes = vec![(1.0, mode)];
+    let mut total = 1.0;
+
+    let (mut k, mut p) = (mode, 1.0);
+    loop {
+        k += 1.0;
+        p *= lambda / k;
+        outcomes.push((p, k));
+        total += p;
+
+        if p / (1.0 - lambda / (k + 1.0)) < TAIL / 2.0 * total {
+            break;
+        }
+        if outcomes.len() > MAX_OUTCOMES {
+            return None;
+        }
+    }
+
+    let (mut k, mut p) = (mode, 1.0);
+    while k > 0.0 {
+        p *= k / lambda;
+        k -= 1.0;
+        outcomes.push((p, k));
+        total += p;
+
+        if p / (1.0 - k / lambda) < TAIL / 2.0 * total {
+            break;
+        }
+        if outcomes.len() > MAX_OUTCOMES {
+            return None;
+        }
+    }
+
+    Some(
+        outcomes
+            .into_iter()
+            .filter(|(p, _)| *p > 0.0)
+            .map(|(p, k)| (p, IBig::from(k as u64)))
+            .collect(),
+    )
+}
+
+/// `binomial` with exact probabilities. `p` is at most one.
+pub fn exact_binomial(n: usize, p: &Ratio, combinations: &mut Combinations) -> Pmf<IBig, Ratio> {
+    let (successes, trials) = (p.numerator(), p.denominator());
+    let failures = trials - successes;
+    let total = trials.pow(n);
+
+    (0..=n)
+        .map(|k| {
+            let ways = combinations.choose(n, k) * successes.pow(k) * failures.pow(n - k);
+
+            (Ratio::ratio(&ways, &total), IBig::from(k))
+        })
+        .filter(|(p, _)| !p.is_zero())
+        .collect()
+}
+
+/// Every integer from `min` to `max`, inclusive, with equal probability.
+pub fn uniform<P: Probability>(min: &IBig, max: &IBig) -> Pmf<IBig, P> {
+    let mut outcomes = vec![];
+    let mut value = min.clone();
+
+    while &value <= max {
+        outcomes.push((P::one(), value.clone()));
+        value += 1;
+    }
+
+    outcomes.into_iter().collect()
+}
+
+pub fn sample_binomial<R: Rng>(rng: &mut R, n: usize, p: f64) -> IBig {
+    let p = p.clamp(0.0, 1.0);
+
+    IBig::from((0..n).filter(|_| rng.gen_bool(p)).count())
+}
+
+pub fn sample_geometric<R: Rng>(rng: &mut R, p: f64) -> IBig {
+    if p >= 1.0 {
+        return IBig::one();
+    }
+
+    // Inverse transform sampling, rejecting the truncated tail.
+    let trials = geometric_trials(p);
+    loop {
+        let u = 1.0 - rng.gen::<f64>();
+        let k = (u.ln() / (-p).ln_1p()).ceil().max(1.0);
+
+        if k <= trials {
+            return IBig::from(k as u64);
+        }
+    }
+}
+
+pub fn sample_poisson<R: Rng>(rng: &mut R, lambda: f64) -> IBig {
+    if lambda <= 0.0 {
+        return IBig::zero();
+    }
+
+    // Inverse transform sampling is quick while the rate is small, but takes time proportional
+    // to the rate, and e^-lambda underflows for large rates.
+    if lambda < 10.0 {
+        let u = rng.gen::<f64>();
+        let (mut k, mut p) = (0_u64, (-lambda).exp());
+        let mut cumulative = p;
+
+        while u >= cumulative && p > 0.0 {
+            k += 1;
+            p *= lambda / k as f64;
+            cumulative += p;
+        }
+
+        return IBig::from(k);
+    }
+
+    // Hörmann's transformed rejection with squeeze, PTRS, for rates of at least 10.
+    let (sqrt_lambda, ln_lambda) = (lambda.sqrt(), lambda.ln());
+    let b = 0.931 + 2.53 * sqrt_lambda;
+    let a = -0.059 + 0.02483 * b;
+    let inverse_alpha = 1.1239 + 1.1328 / (b - 3.4);
+    let v_r = 0.9277 - 3.6224 / (b - 2.0);
+
+    loop {
+        let u = rng.gen::<f64>() - 0.5;
+        let v = rng.gen::<f64>();
+        let us = 0.5 - u.abs();
+        let k = ((2.0 * a / us + b) * u + lambda + 0.43).floor();
+
+        if us >= 0.07 && v <= v_r {
+            return IBig::from(k as u64);
+        }
+        if k < 0.0 || (us < 0.013 && v > us) {
+            continue;
+        }
+        if v.ln() + inverse_alpha.ln() - (a / (us * us) + b).ln()
+            <= -lambda + k * ln_lambda - ln_gamma(k + 1.0)
+        {
+            return IBig::from(k as u64);
+        }
+    }
+}
+
+pub fn sample_uniform<R: Rng>(rng: &mut R, min: &IBig, max: &IBig) -> IBig {
+    UniformIBig::new_inclusive(min, max).sample(rng)
+}
+
+/// P(X >= x) for a chi-squared distribution with `degrees_of_freedom`, the p-value of a
+/// chi-squared or G-test statistic `x`.
+pub fn chi_squared_survival(x: f64, degrees_of_freedom: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    upper_regularized_gamma(degrees_of_freedom / 2.0, x / 2.0)
+}
+
+/// P(K > lambda) for the Kolmogorov distribution, the asymptotic p-value of a
+/// Kolmogorov–Smirnov statistic scaled by the square root of the number of samples.
+pub fn kolmogorov_survival(lambda: f64) -> f64 {
+    // The series converges too slowly for small lambda, where the p-value is 1 anyway.
+    if lambda < 0.2 {
+        return 1.0;
+    }
+
+    let mut sum = 0.0;
+    for k in 1..=100 {
+        let k = k as f64;
+        let term = (-2.0 * k * k * lambda * lambda).exp();
+        sum += if k % 2.0 == 1.0 { term } else { -term };
+
+        if term < 1e-16 {
+            break;
+        }
+    }
+
+    (2.0 * sum).clamp(0.0, 1.0)
+}
+
+// The number of trials after which the geometric distribution's tail is negligible.
+fn geometric_trials(p: f64) -> f64 {
+    (TAIL.ln() / (-p).ln_1p()).ceil().max(1.0)
+}
+
+// Q(a, x), by its series when x is small and its continued fraction otherwise, as in Numerical
+// Recipes. Both converge quickly on their side of a + 1.
+fn upper_regularized_gamma(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const ITERATIONS: usize = 1000;
+
+    let ln_prefactor = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        return (1.0 - sum * ln_prefactor.exp()).clamp(0.0, 1.0);
+    }
+
+    // Lentz's method.
+    let tiny = f64::MIN_POSITIVE / EPSILON;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / tiny;
+    let mut d = 1.0 / b;
+    let mut fraction = d;
+    for n in 1..ITERATIONS {
+        let an = -(n as f64) * (n as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < tiny {
+            d = tiny;
+        }
+        c = b + an / c;
+        if c.abs() < tiny {
+            c = tiny;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        fraction *= delta;
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    (fraction * ln_prefactor.exp()).clamp(0.0, 1.0)
+}
+
+// The Lanczos approximation of ln Γ(x) for x > 0.
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.18009172947146,
+        -86.50532032941677,
+        24.01409824083091,
+        -1.231739572450155,
+        0.1208650973866179e-2,
+        -0.5395239384953e-5,
+    ];
+
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000000000190015, |sum, (i, coefficient)| {
+            sum + coefficient / (x + 1.0 + i as f64)
+        });
+
+    -tmp + (2.5066282746310005 * series / x).ln()
+}
+
+// The natural logarithm of integers too large for f64.
+fn ln(n: &UBig) -> f64 {
+    let bits = n.bit_len();
+
+    if bits <= 1000 {
+        return n.to_f64().ln();
+    }
+
+    let shift = bits - 64;
+    (n >> shift).to_f64().ln() + shift as f64 * std::f64::consts::LN_2
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+    use rand::SeedableRng;
+
+    #[test]
+    fn binomial_coin_flips() {
+        let mut combinations = Combinations::default();
+        let pmf = binomial(4, 0.5, &mut combinations);
+        let p = pmf.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+        float_eq::assert_float_eq!(p[0], 1.0 / 16.0, abs <= 0.000001);
+        float_eq::assert_float_eq!(p[2], 6.0 / 16.0, abs <= 0.000001);
+        float_eq::assert_float_eq!(p[4], 1.0 / 16.0, abs <= 0.000001);
+    }
+
+    #[quickcheck]
+    fn binomial_mean(n: u16, p: u8) -> TestResult {
+        let n = usize::from(n % 200);
+        let p = f64::from(p) / 255.0;
+        let mut combinations = Combinations::default();
+
+        TestResult::from_bool(float_eq::float_eq!(
+            binomial(n, p, &mut combinations).expected_value(),
+            n as f64 * p,
+            abs <= 0.000001 * n as f64
+        ))
+    }
+
+    #[quickcheck]
+    fn geometric_mean(p: u8) -> TestResult {
+        if p == 0 {
+            return TestResult::discard();
+        }
+
+        let p = f64::from(p) / 255.0;
+
+        TestResult::from_bool(float_eq::float_eq!(
+            geometric(p).unwrap().expected_value(),
+            1.0 / p,
+            abs <= 0.00001
+        ))
+    }
+
+    #[quickcheck]
+    fn poisson_mean(lambda: u16) -> bool {
+        let lambda = f64::from(lambda % 1000) / 10.0;
+
+        float_eq::float_eq!(
+            poisson(lambda).unwrap().expected_value(),
+            lambda,
+            abs <= 0.00001
+        )
+    }
+
+    #[test]
+    fn poisson_large_rate() {
+        let pmf = poisson(1000.0).unwrap();
+
+        float_eq::assert_float_eq!(pmf.expected_value(), 1000.0, abs <= 0.00001);
+
+        let pmf = poisson(1_000_000.0).unwrap();
+
+        float_eq::assert_float_eq!(pmf.expected_value(), 1_000_000.0, abs <= 0.001);
+        assert!(pmf.len() < 20_000);
+    }
+
+    #[test]
+    fn too_many_outcomes() {
+        assert!(geometric(0.0000000001).is_none());
+        assert!(poisson(10_000_000_000.0).is_none());
+    }
+
+    #[test]
+    fn sample_wide_distributions() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mean = |samples: Vec<IBig>| {
+            samples.iter().map(IBig::to_f64).sum::<f64>() / samples.len() as f64
+        };
+
+        let poisson = (0..1000)
+            .map(|_| sample_poisson(&mut rng, 10_000_000_000.0))
+            .collect();
+        float_eq::assert_float_eq!(mean(poisson), 10_000_000_000.0, rmax <= 0.0001);
+
+        let poisson = (0..10000).map(|_| sample_poisson(&mut rng, 25.0)).collect();
+        float_eq::assert_float_eq!(mean(poisson), 25.0, abs <= 0.2);
+
+        let geometric = (0..1000)
+            .map(|_| sample_geometric(&mut rng, 0.0000000001))
+            .collect();
+        float_eq::assert_float_eq!(mean(geometric), 10_000_000_000.0, rmax <= 0.2);
+    }
+
+    #[test]
+    fn chi_squared_critical_values() {
+        // Critical values at 5% and 0.1% from standard tables.
+        float_eq::assert_float_eq!(chi_squared_survival(3.841, 1.0), 0.05, abs <= 1e-4);
+        float_eq::assert_float_eq!(chi_squared_survival(18.307, 10.0), 0.05, abs <= 1e-4);
+        float_eq::assert_float_eq!(chi_squared_survival(29.588, 10.0), 0.001, abs <= 1e-5);
+        float_eq::assert_float_eq!(
+            chi_squared_survival(2.0, 2.0),
+            (-1.0_f64).exp(),
+            abs <= 1e-9
+        );
+        assert_eq!(chi_squared_survival(0.0, 4.0), 1.0);
+    }
+
+    #[test]
+    fn kolmogorov_critical_values() {
+        // Critical values at 5% and 1% from standard tables.
+        float_eq::assert_float_eq!(kolmogorov_survival(1.3581), 0.05, abs <= 1e-4);
+        float_eq::assert_float_eq!(kolmogorov_survival(1.6276), 0.01, abs <= 1e-4);
+        assert_eq!(kolmogorov_survival(0.0), 1.0);
+    }
+
+    #[quickcheck]
+    fn samples_in_support(seed: u64) -> bool {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let binomial = sample_binomial(&mut rng, 8, 0.35);
+        let geometric = sample_geometric(&mut rng, 0.25);
+        let poisson = sample_poisson(&mut rng, 2.5);
+        let uniform = sample_uniform(&mut rng, &IBig::from(-3), &IBig::from(3));
+
+        binomial >= IBig::zero()
+            && binomial <= IBig::from(8)
+            && geometric >= IBig::one()
+            && poisson >= IBig::zero()
+            && uniform >= IBig::from(-3)
+            && uniform <= IBig::from(3)
+    }
+}
@@ -0,0 +1,96 @@
+use ibig::IBig;
+use std::cmp::Ordering;
+
+/// A multiset of cards drawn from without replacement. Decks are identified by their cards, so
+/// every literal of the same deck in an expression refers to the same physical deck.
+#[derive(Clone, Debug)]
+pub struct Deck {
+    items: Vec<(IBig, IBig)>,
+    cards: Vec<IBig>,
+}
+
+/// The most cards a deck may hold, since every card is kept in memory.
+const MAX_CARDS: usize = 1_000_000;
+
+impl Deck {
+    pub fn new(items: Vec<(IBig, IBig)>) -> Result<Self, &'static str> {
+        let mut size = IBig::from(0u8);
+        for (first, last) in items.iter() {
+            if first > last {
+                return Err(
+                    "A deck range is empty. Ranges go from the lowest card to the highest, e.g. \
+                     [1..10].",
+                );
+            }
+
+            size += last - first + IBig::from(1u8);
+        }
+        if size > IBig::from(MAX_CARDS) {
+            return Err("A deck may hold at most 1000000 cards.");
+        }
+
+        let mut cards = vec![];
+        for (first, last) in items.iter() {
+            let mut card = first.clone();
+            while &card <= last {
+                cards.push(card.clone());
+                card += 1;
+            }
+        }
+
+        cards.sort_unstable();
+
+        Ok(Self { items, cards })
+    }
+
+    /// The cards in the deck in ascending order.
+    pub fn cards(&self) -> &[IBig] {
+        &self.cards
+    }
+}
+
+impl std::fmt::Display for Deck {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str("[")?;
+
+        for (i, (first, last)) in self.items.iter().enumerate() {
+            if i > 0 {
+                formatter.write_str(", ")?;
+            }
+
+            if first == last {
+                formatter.write_fmt(format_args!("{}", first))?;
+            } else {
+                formatter.write_fmt(format_args!("{}..{}", first, last))?;
+            }
+        }
+
+        formatter.write_str("]")
+    }
+}
+
+impl PartialEq for Deck {
+    fn eq(&self, other: &Self) -> bool {
+        self.cards == other.cards
+    }
+}
+
+impl Eq for Deck {}
+
+impl PartialOrd for Deck {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Deck {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cards.cmp(&other.cards)
+    }
+}
+
+impl std::hash::Hash for Deck {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.cards.hash(state);
+    }
+}
@@ -0,0 +1,155 @@
+use super::Expression;
+use crate::probability::{Probability, Ratio};
+use ibig::{IBig, UBig};
+
+/// The number of times a single exploding die may explode. Bounding explosions keeps every
+/// distribution finite, and the interpreter honors the same bound so rolls match the PMF.
+pub const MAX_EXPLOSIONS: usize = 16;
+
+/// Each function's name and the error shown when it is called incorrectly.
+const FUNCTIONS: [(&str, &str); 7] = [
+    (
+        "binomial",
+        "binomial expects a number of trials and a probability, e.g. binomial(8, 35%).",
+    ),
+    ("explode", "explode expects dice, e.g. explode(3d6)."),
+    (
+        "geometric",
+        "geometric expects a probability greater than 0 and at most 1, e.g. geometric(0.25).",
+    ),
+    (
+        "max",
+        "max expects one or more arguments, e.g. max(1d20, 1d20).",
+    ),
+    (
+        "min",
+        "min expects one or more arguments, e.g. min(1d20, 1d20).",
+    ),
+    (
+        "poisson",
+        "poisson expects a non-negative rate, e.g. poisson(2.5).",
+    ),
+    (
+        "uniform",
+        "uniform expects a minimum and a maximum, e.g. uniform(-3, 3).",
+    ),
+];
+
+/// Functions whose real parameters, e.g. a probability, are constants stored with the function.
+/// Every other argument is an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Function {
+    Binomial(Real),
+    Explode,
+    Geometric(Real),
+    Max,
+    Min,
+    Poisson(Real),
+    Uniform,
+}
+
+/// A constant real number, e.g. `0.35` or `35%`, that remembers how it was written.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Real {
+    pub value: f64,
+    text: String,
+}
+
+/// A parsed function argument. Only some functions accept real numbers.
+pub enum Argument {
+    Expression(Expression),
+    Real(Real),
+}
+
+impl Function {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Binomial(..) => "binomial",
+            Function::Explode => "explode",
+            Function::Geometric(..) => "geometric",
+            Function::Max => "max",
+            Function::Min => "min",
+            Function::Poisson(..) => "poisson",
+            Function::Uniform => "uniform",
+        }
+    }
+
+    /// The real parameter, which always follows the expression arguments.
+    pub fn parameter(&self) -> Option<&Real> {
+        match self {
+            Function::Binomial(real) | Function::Geometric(real) | Function::Poisson(real) => {
+                Some(real)
+            }
+            Function::Explode | Function::Max | Function::Min | Function::Uniform => None,
+        }
+    }
+}
+
+pub fn usage(name: &str) -> Option<&'static str> {
+    FUNCTIONS
+        .iter()
+        .find(|(function, _)| *function == name)
+        .map(|(_, usage)| *usage)
+}
+
+impl Real {
+    pub fn new(text: &str) -> Option<Self> {
+        let value = match text.strip_suffix('%') {
+            Some(percent) => percent.parse::<f64>().ok()? / 100.0,
+            None => text.parse::<f64>().ok()?,
+        };
+
+        Some(Self {
+            value,
+            text: String::from(text),
+        })
+    }
+
+    /// The exact value, when it is not negative.
+    pub fn ratio(&self) -> Option<Ratio> {
+        let (text, scale) = match self.text.strip_suffix('%') {
+            Some(percent) => (percent, UBig::from(100_u8)),
+            None => (self.text.as_str(), UBig::from(1_u8)),
+        };
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        let digits = format!("{}{}", whole, fraction).parse::<UBig>().ok()?;
+
+        Some(Ratio::ratio(
+            &digits,
+            &(scale * UBig::from(10_u8).pow(fraction.len())),
+        ))
+    }
+}
+
+impl From<&IBig> for Real {
+    fn from(n: &IBig) -> Self {
+        Self {
+            value: n.to_f64(),
+            text: n.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Real {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str(&self.text)
+    }
+}
+
+impl Argument {
+    /// Integer literals are accepted wherever a real number is.
+    pub fn real(self) -> Option<Real> {
+        match self {
+            Argument::Real(real) => Some(real),
+            Argument::Expression(Expression::Literal(n)) => Some(Real::from(&n)),
+            Argument::Expression(..) => None,
+        }
+    }
+
+    pub fn expression(self) -> Option<Expression> {
+        match self {
+            Argument::Expression(expression) => Some(expression),
+            Argument::Real(..) => None,
+        }
+    }
+}
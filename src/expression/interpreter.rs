@@ -1,11 +1,258 @@
-use super::Expression;
-use ibig::{rand::UniformUBig, IBig, UBig};
+use super::functions::{Function, MAX_EXPLOSIONS};
+use super::operators::{Binary, Operator};
+use super::{Deck, Expression};
+use crate::distributions;
+use ibig::{ops::UnsignedAbs, rand::UniformUBig, IBig, UBig};
 use num_traits::{One, Zero};
 use rand::distributions::uniform::UniformSampler;
+use rand::RngCore;
+use std::collections::HashMap;
 
-pub fn evaluate(
-    rng: &mut rand::rngs::StdRng,
-    rolls: &mut std::collections::HashMap<UBig, Vec<UBig>>,
+#[derive(Default)]
+pub struct History {
+    pub draws: Vec<Vec<IBig>>,
+    pub rolls: HashMap<UBig, Vec<UBig>>,
+    decks: HashMap<Deck, Vec<IBig>>,
+    // The traces of the subexpressions evaluated but not yet taken by the expression they are
+    // operands of, when tracing, and the dice rolled since the last trace.
+    traces: Option<Vec<Trace>>,
+    dice: Vec<UBig>,
+    replay: Option<Replay>,
+}
+
+// Faces supplied in place of the rng, in the order they are rolled, and how many were used.
+struct Replay {
+    faces: std::vec::IntoIter<IBig>,
+    used: usize,
+}
+
+/// How an expression evaluated: its value, the dice it rolled or cards it drew itself, in order,
+/// and how each operand evaluated, in the order of `Expression::operands`.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub expression: Expression,
+    pub value: IBig,
+    pub rolls: Vec<UBig>,
+    pub draws: Vec<IBig>,
+    pub operands: Vec<Trace>,
+}
+
+impl History {
+    /// A history that also traces the evaluation. Rolling many times is faster without it.
+    pub fn traced() -> Self {
+        Self {
+            traces: Some(vec![]),
+            ..Default::default()
+        }
+    }
+
+    /// A traced history that takes each roll from `faces` instead of the rng.
+    pub fn replaying(faces: Vec<IBig>) -> Self {
+        Self {
+            replay: Some(Replay {
+                faces: faces.into_iter(),
+                used: 0,
+            }),
+            ..Self::traced()
+        }
+    }
+
+    /// The faces left to replay.
+    pub fn unused_faces(&self) -> usize {
+        self.replay.as_ref().map_or(0, |replay| replay.faces.len())
+    }
+
+    /// The trace of the last expression evaluated, if tracing.
+    pub fn trace(&mut self) -> Option<Trace> {
+        self.traces.as_mut().and_then(Vec::pop)
+    }
+
+    // The next face, if replaying. It is an error if there are none left or `e` cannot roll it.
+    fn face(
+        &mut self,
+        e: &Expression,
+        possible: impl Fn(&IBig) -> bool,
+    ) -> Result<Option<IBig>, anyhow::Error> {
+        let replay = match &mut self.replay {
+            Some(replay) => replay,
+            None => return Ok(None),
+        };
+
+        let face = replay.faces.next().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Ran out of faces to replay after {}: {} needs another.",
+                replay.used,
+                e
+            )
+        })?;
+        replay.used += 1;
+
+        match possible(&face) {
+            true => Ok(Some(face)),
+            false => Err(anyhow::anyhow!(
+                "Face {}, {}, is not a possible roll of {}.",
+                replay.used,
+                face,
+                e
+            )),
+        }
+    }
+
+    fn roll(&mut self, sides: &UBig, roll: UBig) {
+        if self.traces.is_some() {
+            self.dice.push(roll.clone());
+        }
+
+        let dice_rolls = self.rolls.entry(sides.clone()).or_insert(vec![]);
+        dice_rolls.push(roll);
+    }
+}
+
+impl Trace {
+    /// The expression with what each subexpression rolled beside it, e.g. `1d20 (14) + 3`.
+    /// `roll` writes a die's roll given its sides.
+    pub fn annotate(&self, roll: &dyn Fn(&UBig, &UBig) -> String) -> String {
+        let operands = self.expression.operands();
+        // Rolls beside an operand of `d` would run into it, e.g. `1d4 (3)d6`.
+        let joined = matches!(
+            self.expression.operator(),
+            Some(Operator::Binary(Binary { space: false, .. }))
+        );
+        let text = self.expression.display_with(&|child, delimited| {
+            match operands
+                .iter()
+                .position(|operand| std::ptr::eq(*operand, child))
+            {
+                Some(i) => {
+                    let annotated = self.operands[i].annotate(roll);
+                    match joined && !delimited && annotated != child.to_string() {
+                        true => format!("({})", annotated),
+                        false => annotated,
+                    }
+                }
+                // The dice of `explode` are its argument, but their operands are its operands.
+                None => child.to_string(),
+            }
+        });
+
+        let results = match &self.expression {
+            Expression::Dice { .. }
+            | Expression::Function {
+                function: Function::Explode,
+                ..
+            } => {
+                let sides = (&self.operands[1].value).unsigned_abs();
+                self.rolls.iter().map(|r| roll(r, &sides)).collect()
+            }
+            Expression::Draw { .. } => self.draws.iter().map(IBig::to_string).collect(),
+            Expression::Function {
+                function:
+                    Function::Binomial(..)
+                    | Function::Geometric(..)
+                    | Function::Poisson(..)
+                    | Function::Uniform,
+                ..
+            } => vec![self.value.to_string()],
+            _ => vec![],
+        };
+
+        match results.is_empty() {
+            true => text,
+            false => format!("{} ({})", text, results.join(", ")),
+        }
+    }
+}
+
+// Expressions are equal when they are written the same way.
+impl PartialEq for Trace {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression.to_string() == other.expression.to_string()
+            && self.value == other.value
+            && self.rolls == other.rolls
+            && self.draws == other.draws
+            && self.operands == other.operands
+    }
+}
+
+impl Eq for Trace {}
+
+impl Trace {
+    /// The faces rolled, cards drawn and values sampled, in the order they were, which
+    /// `Expression::replay` takes to evaluate the same way again.
+    pub fn faces(&self) -> Vec<IBig> {
+        let mut faces = self
+            .operands
+            .iter()
+            .flat_map(Trace::faces)
+            .collect::<Vec<_>>();
+
+        match &self.expression {
+            Expression::Dice { .. }
+            | Expression::Function {
+                function: Function::Explode,
+                ..
+            } => faces.extend(self.rolls.iter().map(IBig::from)),
+            Expression::Draw { .. } => faces.extend(self.draws.iter().cloned()),
+            Expression::Function {
+                function:
+                    Function::Binomial(..)
+                    | Function::Geometric(..)
+                    | Function::Poisson(..)
+                    | Function::Uniform,
+                ..
+            } => faces.push(self.value.clone()),
+            _ => {}
+        }
+
+        faces
+    }
+}
+
+impl std::fmt::Display for Trace {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_fmt(format_args!(
+            "{} = {}",
+            self.annotate(&|roll, _| roll.to_string()),
+            self.value
+        ))
+    }
+}
+
+pub fn evaluate<R: RngCore>(
+    rng: &mut R,
+    history: &mut History,
+    e: &Expression,
+) -> Result<IBig, anyhow::Error> {
+    let start = match &history.traces {
+        Some(traces) => traces.len(),
+        None => return step(rng, history, e),
+    };
+
+    let value = step(rng, history, e)?;
+    let rolls = std::mem::take(&mut history.dice);
+    let draws = match e {
+        Expression::Draw { .. } => history.draws.last().cloned().unwrap_or_default(),
+        _ => vec![],
+    };
+
+    // Unwrap is safe because tracing was checked above.
+    let traces = history.traces.as_mut().unwrap();
+    let operands = traces.split_off(start);
+    traces.push(Trace {
+        expression: e.clone(),
+        value: value.clone(),
+        rolls,
+        draws,
+        operands,
+    });
+
+    Ok(value)
+}
+
+// Evaluates one expression, tracing its operands if tracing.
+fn step<R: RngCore>(
+    rng: &mut R,
+    history: &mut History,
     e: &Expression,
 ) -> Result<IBig, anyhow::Error> {
     match e {
@@ -14,55 +261,188 @@ pub fn evaluate(
             right: right_e,
             ..
         } => {
-            let left = evaluate(rng, rolls, left_e)?;
-            let right = evaluate(rng, rolls, right_e)?;
+            let left = evaluate(rng, history, left_e)?;
+            let right = evaluate(rng, history, right_e)?;
             let right = UBig::from(super::parse::die(&right, right_e)?);
 
             let mut sum = IBig::zero();
             for _ in 0..super::parse::dice(&left, left_e)? {
-                let roll = int(rng, &right);
+                let roll = die(rng, history, e, &right)?;
                 sum += IBig::from(&roll);
-
-                let dice_rolls = rolls.entry(right.clone()).or_insert(vec![]);
-                dice_rolls.push(roll);
+                history.roll(&right, roll);
             }
 
             Ok(sum)
         }
         Expression::Difference { left, right, .. } => {
-            Ok(evaluate(rng, rolls, left)? - evaluate(rng, rolls, right)?)
+            Ok(evaluate(rng, history, left)? - evaluate(rng, history, right)?)
+        }
+        Expression::Draw {
+            count: count_e,
+            deck,
+        } => {
+            let count = evaluate(rng, history, count_e)?;
+            let mut cards = history
+                .decks
+                .remove(deck)
+                .unwrap_or_else(|| deck.cards().to_vec());
+            let count = super::parse::cards(&count, count_e, cards.len())?;
+
+            let mut hand = vec![];
+            for _ in 0..count {
+                // Unwraps are safe because a replayed card is in the deck, and the index is
+                // always less than the number of cards.
+                let i = match history.face(e, |face| cards.contains(face))? {
+                    Some(face) => cards.iter().position(|card| card == &face).unwrap(),
+                    None => {
+                        let i = int(rng, &UBig::from(cards.len())) - UBig::one();
+                        usize::try_from(&i).unwrap()
+                    }
+                };
+
+                hand.push(cards.swap_remove(i));
+            }
+            history.decks.insert(deck.clone(), cards);
+
+            let sum = hand.iter().fold(IBig::zero(), |sum, card| sum + card);
+            history.draws.push(hand);
+
+            Ok(sum)
         }
         Expression::Exponentiation {
             left,
             right: right_e,
             ..
         } => {
-            let left = evaluate(rng, rolls, left)?;
-            let right = evaluate(rng, rolls, right_e)?;
+            let left = evaluate(rng, history, left)?;
+            let right = evaluate(rng, history, right_e)?;
             let right = super::parse::exponent(&right, right_e)?;
             Ok(left.pow(right))
         }
+        Expression::Function {
+            function: Function::Explode,
+            arguments,
+        } => {
+            let (left_e, right_e) = match arguments.as_slice() {
+                [Expression::Dice { left, right, .. }] => (left, right),
+                _ => unreachable!("Only dice explode."),
+            };
+
+            let left = evaluate(rng, history, left_e)?;
+            let right = evaluate(rng, history, right_e)?;
+            let right = UBig::from(super::parse::die(&right, right_e)?);
+
+            let mut sum = IBig::zero();
+            for _ in 0..super::parse::dice(&left, left_e)? {
+                for explosions in 0..=MAX_EXPLOSIONS {
+                    let roll = die(rng, history, e, &right)?;
+                    let exploded = roll == right && explosions < MAX_EXPLOSIONS;
+                    sum += IBig::from(&roll);
+                    history.roll(&right, roll);
+
+                    if !exploded {
+                        break;
+                    }
+                }
+            }
+
+            Ok(sum)
+        }
+        Expression::Function {
+            function: Function::Binomial(p),
+            arguments,
+        } => {
+            let trials_e = &arguments[0];
+            let trials = evaluate(rng, history, trials_e)?;
+            let trials = super::parse::trials(&trials, trials_e)?;
+
+            match history.face(e, |face| {
+                face >= &IBig::zero() && face <= &IBig::from(trials)
+            })? {
+                Some(face) => Ok(face),
+                None => Ok(distributions::sample_binomial(rng, trials, p.value)),
+            }
+        }
+        Expression::Function {
+            function: Function::Geometric(p),
+            ..
+        } => match history.face(e, |face| face >= &IBig::one())? {
+            Some(face) => Ok(face),
+            None => Ok(distributions::sample_geometric(rng, p.value)),
+        },
+        Expression::Function {
+            function: Function::Poisson(rate),
+            ..
+        } => match history.face(e, |face| face >= &IBig::zero())? {
+            Some(face) => Ok(face),
+            None => Ok(distributions::sample_poisson(rng, rate.value)),
+        },
+        Expression::Function {
+            function: Function::Uniform,
+            arguments,
+        } => {
+            let min = evaluate(rng, history, &arguments[0])?;
+            let max = evaluate(rng, history, &arguments[1])?;
+            let (min, max) = super::parse::range(&min, &max, e)?;
+
+            match history.face(e, |face| face >= min && face <= max)? {
+                Some(face) => Ok(face),
+                None => Ok(distributions::sample_uniform(rng, min, max)),
+            }
+        }
+        Expression::Function {
+            function: Function::Max,
+            arguments,
+        } => arguments
+            .iter()
+            .map(|argument| evaluate(rng, history, argument))
+            .reduce(|max, value| Ok(max?.max(value?)))
+            .unwrap_or_else(|| unreachable!("max requires an argument.")),
+        Expression::Function {
+            function: Function::Min,
+            arguments,
+        } => arguments
+            .iter()
+            .map(|argument| evaluate(rng, history, argument))
+            .reduce(|min, value| Ok(min?.min(value?)))
+            .unwrap_or_else(|| unreachable!("min requires an argument.")),
         Expression::IQuotient {
             left,
             right: right_e,
             ..
         } => {
-            let right = evaluate(rng, rolls, right_e)?;
+            let right = evaluate(rng, history, right_e)?;
             let right = super::parse::nonzero(&right, right_e)?;
-            Ok(evaluate(rng, rolls, left)? / right)
+            Ok(evaluate(rng, history, left)? / right)
         }
         Expression::Product { left, right, .. } => {
-            Ok(evaluate(rng, rolls, left)? * evaluate(rng, rolls, right)?)
+            Ok(evaluate(rng, history, left)? * evaluate(rng, history, right)?)
         }
         Expression::Sum { left, right, .. } => {
-            Ok(evaluate(rng, rolls, left)? + evaluate(rng, rolls, right)?)
+            Ok(evaluate(rng, history, left)? + evaluate(rng, history, right)?)
         }
-        Expression::Minus { operand, .. } => Ok(-evaluate(rng, rolls, operand)?),
-        Expression::Plus { operand, .. } => Ok(evaluate(rng, rolls, operand)?),
+        Expression::Minus { operand, .. } => Ok(-evaluate(rng, history, operand)?),
+        Expression::Plus { operand, .. } => Ok(evaluate(rng, history, operand)?),
         Expression::Literal(literal) => Ok(literal.clone()),
+        Expression::Variable(name) => Err(super::parse::unbound(name)),
+    }
+}
+
+// A roll of a die with `sides` sides, or the next face if replaying.
+fn die<R: RngCore>(
+    rng: &mut R,
+    history: &mut History,
+    e: &Expression,
+    sides: &UBig,
+) -> Result<UBig, anyhow::Error> {
+    let max = IBig::from(sides);
+
+    match history.face(e, |face| face >= &IBig::one() && face <= &max)? {
+        Some(face) => Ok(face.unsigned_abs()),
+        None => Ok(int(rng, sides)),
     }
 }
 
-fn int(rng: &mut rand::rngs::StdRng, sides: &UBig) -> UBig {
+fn int<R: RngCore>(rng: &mut R, sides: &UBig) -> UBig {
     UniformUBig::new_inclusive(UBig::one(), sides).sample(rng)
 }
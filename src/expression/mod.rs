@@ -1,21 +1,33 @@
 use crate::combinatorics::Combinations;
+use crate::probability::Ratio;
+use crate::simulation::Simulation;
+use crate::verification::Verification;
 use ibig::{IBig, UBig};
 use owo_colors::OwoColorize;
+use rand::RngCore;
 use std::collections::HashMap;
 
+pub use deck::Deck;
+pub use interpreter::Trace;
+pub use pmf::PmfTree;
+pub use presets::{Preset, PRESETS};
+
+mod deck;
+mod functions;
 mod interpreter;
 mod operators;
 mod parse;
 mod parser;
 mod pmf;
+mod presets;
 
-#[allow(clippy::all)]
+#[allow(clippy::all, dead_code)]
 mod precedence;
 
 #[cfg(test)]
 mod test;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Expression {
     Dice {
         left: Box<Expression>,
@@ -27,11 +39,19 @@ pub enum Expression {
         right: Box<Expression>,
         operator: operators::Binary,
     },
+    Draw {
+        count: Box<Expression>,
+        deck: Deck,
+    },
     Exponentiation {
         left: Box<Expression>,
         right: Box<Expression>,
         operator: operators::Binary,
     },
+    Function {
+        function: functions::Function,
+        arguments: Vec<Expression>,
+    },
     IQuotient {
         left: Box<Expression>,
         right: Box<Expression>,
@@ -56,21 +76,95 @@ pub enum Expression {
         operator: operators::Unary,
     },
     Literal(IBig),
+    /// A placeholder, e.g. `$m`, for a value substituted before evaluation.
+    Variable(String),
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Evaluand {
+    pub draws: Vec<Vec<IBig>>,
     pub rolls: HashMap<UBig, Vec<UBig>>,
     pub value: IBig,
+    /// How each subexpression evaluated, with its dice in the order they were rolled.
+    pub trace: Trace,
+}
+
+impl Evaluand {
+    fn new(mut history: interpreter::History, value: IBig) -> Self {
+        // Unwrap is safe because evaluands are always traced.
+        let trace = history.trace().unwrap();
+
+        Self {
+            draws: history.draws,
+            rolls: history.rolls,
+            value,
+            trace,
+        }
+    }
 }
 
 impl Expression {
+    /// Rolls the expression with a `StdRng` seeded with `seed`, so the same seed rolls the same.
     pub fn eval(&self, seed: u64) -> Result<Evaluand, anyhow::Error> {
         let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed);
-        let mut rolls = HashMap::new();
-        let value = interpreter::evaluate(&mut rng, &mut rolls, self)?;
 
-        Ok(Evaluand { rolls, value })
+        self.eval_with_rng(&mut rng)
+    }
+
+    /// Rolls the expression with any generator, e.g. one shared by many rolls or `OsRng` for
+    /// the operating system's entropy on every die.
+    pub fn eval_with_rng(&self, rng: &mut impl RngCore) -> Result<Evaluand, anyhow::Error> {
+        let mut history = interpreter::History::traced();
+        let value = interpreter::evaluate(rng, &mut history, self)?;
+
+        Ok(Evaluand::new(history, value))
+    }
+
+    /// Evaluates the expression with `faces` in place of random rolls, e.g. dice rolled at the
+    /// table or the faces of a saved `Trace`. Each die rolled, card drawn and value sampled takes
+    /// the next face, and it is an error if there are too few faces or any left over.
+    pub fn replay(&self, faces: Vec<IBig>) -> Result<Evaluand, anyhow::Error> {
+        // Every roll takes a face, so the rng is never used.
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(0);
+        let mut history = interpreter::History::replaying(faces);
+        let value = interpreter::evaluate(&mut rng, &mut history, self)?;
+
+        match history.unused_faces() {
+            0 => Ok(Evaluand::new(history, value)),
+            1 => Err(anyhow::anyhow!(
+                "1 face was left over after replaying {}.",
+                self
+            )),
+            unused => Err(anyhow::anyhow!(
+                "{} faces were left over after replaying {}.",
+                unused,
+                self
+            )),
+        }
+    }
+
+    /// Estimates the distribution from `samples` rolls, for expressions too large to compute
+    /// exactly.
+    pub fn simulate(&self, samples: u64, seed: u64) -> Result<Simulation, anyhow::Error> {
+        let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed);
+        let rolls = (0..samples)
+            .map(|_| interpreter::evaluate(&mut rng, &mut interpreter::History::default(), self))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Simulation::from_samples(rolls))
+    }
+
+    /// Checks the evaluator against the distribution by testing whether `samples` rolls fit it,
+    /// at the significance level `alpha`. Either may be wrong when the check fails.
+    pub fn verify(
+        &self,
+        samples: u64,
+        seed: u64,
+        alpha: f64,
+    ) -> Result<Verification, anyhow::Error> {
+        let simulation = self.simulate(samples, seed)?;
+
+        Ok(Verification::new(&simulation, &self.pmf()?, alpha))
     }
 
     pub fn pmf(&self) -> Result<crate::pmf::Pmf<IBig>, anyhow::Error> {
@@ -79,6 +173,29 @@ impl Expression {
         pmf::pmf(self, &mut combinations)
     }
 
+    /// The distribution with exact probabilities. It is an error if the expression has
+    /// infinitely many outcomes, e.g. `geometric(0.5)`.
+    pub fn exact_pmf(&self) -> Result<crate::pmf::Pmf<IBig, Ratio>, anyhow::Error> {
+        let mut combinations = Combinations::default();
+
+        pmf::pmf(self, &mut combinations)
+    }
+
+    /// The distribution, with the distribution of every subexpression, e.g. to find which part of
+    /// an expression gives a surprising result.
+    pub fn pmf_tree(&self) -> Result<PmfTree, anyhow::Error> {
+        let mut combinations = Combinations::default();
+
+        pmf::tree(self, &mut combinations)
+    }
+
+    /// The distribution tree with exact probabilities. See `pmf_tree` and `exact_pmf`.
+    pub fn exact_pmf_tree(&self) -> Result<PmfTree<Ratio>, anyhow::Error> {
+        let mut combinations = Combinations::default();
+
+        pmf::tree(self, &mut combinations)
+    }
+
     fn operator(&self) -> Option<operators::Operator> {
         match self {
             Expression::Dice { .. } => Some(operators::Operator::Binary(operators::DICE)),
@@ -93,7 +210,227 @@ impl Expression {
             Expression::Sum { .. } => Some(operators::Operator::Binary(operators::SUM)),
             Expression::Minus { .. } => Some(operators::Operator::Unary(operators::MINUS)),
             Expression::Plus { .. } => Some(operators::Operator::Unary(operators::PLUS)),
-            Expression::Literal(..) => None,
+            Expression::Draw { .. }
+            | Expression::Function { .. }
+            | Expression::Literal(..)
+            | Expression::Variable(..) => None,
+        }
+    }
+
+    // The subexpressions an expression evaluates, in the order the interpreter evaluates them.
+    fn operands(&self) -> Vec<&Expression> {
+        match self {
+            Expression::IQuotient { left, right, .. } => vec![right, left],
+            Expression::Dice { left, right, .. }
+            | Expression::Difference { left, right, .. }
+            | Expression::Exponentiation { left, right, .. }
+            | Expression::Product { left, right, .. }
+            | Expression::Sum { left, right, .. } => vec![left, right],
+            Expression::Draw { count, .. } => vec![count],
+            Expression::Function {
+                function: functions::Function::Explode,
+                arguments,
+            } => arguments.iter().flat_map(|dice| dice.operands()).collect(),
+            Expression::Function { arguments, .. } => arguments.iter().collect(),
+            Expression::Minus { operand, .. } | Expression::Plus { operand, .. } => vec![operand],
+            Expression::Literal(..) | Expression::Variable(..) => vec![],
+        }
+    }
+
+    /// The names of the variables in the expression, sorted and without duplicates.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = match self {
+            Expression::Variable(name) => vec![name.as_str()],
+            Expression::Function { arguments, .. } => arguments
+                .iter()
+                .flat_map(|argument| argument.variables())
+                .collect(),
+            _ => self
+                .operands()
+                .into_iter()
+                .flat_map(|operand| operand.variables())
+                .collect(),
+        };
+        variables.sort_unstable();
+        variables.dedup();
+
+        variables
+    }
+
+    /// The expression with every `$name` replaced by `value`.
+    pub fn substitute(&self, name: &str, value: &IBig) -> Expression {
+        self.rewrite(&|expression| match expression {
+            Expression::Variable(variable) if variable == name => {
+                Some(Expression::Literal(value.clone()))
+            }
+            _ => None,
+        })
+    }
+
+    /// The expression with twice as many of every die, e.g. `2d6 + 3` becomes `4d6 + 3`, as on a
+    /// critical hit. A rolled number of dice is doubled, not rolled with doubled dice, e.g.
+    /// `(1d4)d6` becomes `(2 * 1d4)d6`.
+    pub fn double_dice(&self) -> Expression {
+        self.rewrite(&|expression| match expression {
+            Expression::Dice {
+                left,
+                right,
+                operator,
+            } => Some(Expression::Dice {
+                left: Box::from(match &**left {
+                    Expression::Literal(count) => Expression::Literal(count * IBig::from(2u8)),
+                    left => Expression::Product {
+                        left: Box::from(Expression::Literal(IBig::from(2u8))),
+                        right: Box::from(left.clone()),
+                        operator: operators::PRODUCT,
+                    },
+                }),
+                right: right.clone(),
+                operator: operator.clone(),
+            }),
+            _ => None,
+        })
+    }
+
+    /// `n` dice of `sides` sides, e.g. `3d6`.
+    pub(crate) fn dice(n: u32, sides: u32) -> Expression {
+        Expression::Dice {
+            left: Box::from(Expression::Literal(IBig::from(n))),
+            right: Box::from(Expression::Literal(IBig::from(sides))),
+            operator: operators::DICE,
+        }
+    }
+
+    /// The highest of `arguments`, e.g. `max(2d6, 2d6)`.
+    pub(crate) fn max(arguments: Vec<Expression>) -> Expression {
+        Expression::Function {
+            function: functions::Function::Max,
+            arguments,
+        }
+    }
+
+    /// The lowest of `arguments`, e.g. `min(2d6, 2d6)`.
+    pub(crate) fn min(arguments: Vec<Expression>) -> Expression {
+        Expression::Function {
+            function: functions::Function::Min,
+            arguments,
+        }
+    }
+
+    /// The sum of two expressions, e.g. `1d6 + 1d8`.
+    pub(crate) fn sum(left: Expression, right: Expression) -> Expression {
+        Expression::Sum {
+            left: Box::from(left),
+            right: Box::from(right),
+            operator: operators::SUM,
+        }
+    }
+
+    /// The expression plus `constant`, e.g. `1d6 + 2`, or `1d6 - 2` when it is negative. A zero
+    /// constant leaves the expression as it is.
+    pub(crate) fn offset(self, constant: &IBig) -> Expression {
+        match constant.cmp(&IBig::from(0u8)) {
+            std::cmp::Ordering::Equal => self,
+            std::cmp::Ordering::Greater => {
+                Expression::sum(self, Expression::Literal(constant.clone()))
+            }
+            std::cmp::Ordering::Less => Expression::Difference {
+                left: Box::from(self),
+                right: Box::from(Expression::Literal(-constant)),
+                operator: operators::DIFFERENCE,
+            },
+        }
+    }
+
+    // Replaces each subexpression `f` returns a replacement for, and rebuilds the rest.
+    fn rewrite<F>(&self, f: &F) -> Expression
+    where
+        F: Fn(&Expression) -> Option<Expression>,
+    {
+        if let Some(expression) = f(self) {
+            return expression;
+        }
+
+        let rewrite = |e: &Expression| Box::from(e.rewrite(f));
+
+        match self {
+            Expression::Dice {
+                left,
+                right,
+                operator,
+            } => Expression::Dice {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::Difference {
+                left,
+                right,
+                operator,
+            } => Expression::Difference {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::Exponentiation {
+                left,
+                right,
+                operator,
+            } => Expression::Exponentiation {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::IQuotient {
+                left,
+                right,
+                operator,
+            } => Expression::IQuotient {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::Product {
+                left,
+                right,
+                operator,
+            } => Expression::Product {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::Sum {
+                left,
+                right,
+                operator,
+            } => Expression::Sum {
+                left: rewrite(left),
+                right: rewrite(right),
+                operator: operator.clone(),
+            },
+            Expression::Draw { count, deck } => Expression::Draw {
+                count: rewrite(count),
+                deck: deck.clone(),
+            },
+            Expression::Function {
+                function,
+                arguments,
+            } => Expression::Function {
+                function: function.clone(),
+                arguments: arguments
+                    .iter()
+                    .map(|argument| argument.rewrite(f))
+                    .collect(),
+            },
+            Expression::Minus { operand, operator } => Expression::Minus {
+                operand: rewrite(operand),
+                operator: operator.clone(),
+            },
+            Expression::Plus { operand, operator } => Expression::Plus {
+                operand: rewrite(operand),
+                operator: operator.clone(),
+            },
+            Expression::Literal(..) | Expression::Variable(..) => self.clone(),
         }
     }
 }
@@ -105,7 +442,23 @@ pub struct ParseError {
     token: Option<(usize, usize)>,
 }
 
-fn convert_error(input: &str, nom_error: nom::error::VerboseError<&str>) -> ParseError {
+pub(crate) fn convert_error(input: &str, nom_error: nom::error::VerboseError<&str>) -> ParseError {
+    let context = nom_error
+        .errors
+        .iter()
+        .find_map(|(substring, kind)| match kind {
+            nom::error::VerboseErrorKind::Context(message) => Some((substring, message)),
+            _ => None,
+        });
+
+    if let Some((substring, message)) = context {
+        return ParseError {
+            err: anyhow::anyhow!(*message),
+            expression: String::from(input),
+            token: Some((nom::Offset::offset(input, substring), substring.len())),
+        };
+    }
+
     let (position, length) = match nom_error.errors.as_slice() {
         [(substring, _), ..] => (nom::Offset::offset(input, substring), substring.len()),
         _ => (0, input.len()),
@@ -137,8 +490,16 @@ impl std::str::FromStr for Expression {
     }
 }
 
-impl ToString for Expression {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for Expression {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        formatter.write_str(&self.display_with(&|child, _| child.to_string()))
+    }
+}
+
+impl Expression {
+    // Writes the expression with `child` writing each direct subexpression, e.g. to annotate them.
+    // `child` is told whether the subexpression is set off by parentheses or commas.
+    fn display_with(&self, child: &dyn Fn(&Expression, bool) -> String) -> String {
         match self {
             Expression::Dice {
                 left,
@@ -175,13 +536,17 @@ impl ToString for Expression {
                 let left_precedence = left.operator().map(|op| op.precedence()).unwrap_or(0);
                 let right_precedence = right.operator().map(|op| op.precedence()).unwrap_or(0);
 
+                // A variable's name would run into an operator without spaces, e.g. `($n)d6`.
+                let left_variable = matches!(**left, Expression::Variable(..)) && !operator.space;
+
                 if operator.assoc == precedence::Assoc::Left && left_precedence > self_precedence
                     || operator.assoc == precedence::Assoc::Right
                         && left_precedence >= self_precedence
+                    || left_variable
                 {
-                    str.push_str(&format!("({})", left.to_string()));
+                    str.push_str(&format!("({})", child(left, true)));
                 } else {
-                    str.push_str(&left.to_string());
+                    str.push_str(&child(left, false));
                 }
 
                 if operator.space {
@@ -194,17 +559,31 @@ impl ToString for Expression {
                     || operator.assoc == precedence::Assoc::Right
                         && self_precedence < right_precedence
                 {
-                    str.push_str(&format!("({})", right.to_string()));
+                    str.push_str(&format!("({})", child(right, true)));
                 } else {
-                    str.push_str(&right.to_string());
+                    str.push_str(&child(right, false));
                 }
 
                 str
             }
             Expression::Minus { operand, operator } | Expression::Plus { operand, operator } => {
-                format!("{}{}", operator.symbol, operand.to_string())
+                format!("{}{}", operator.symbol, child(operand, false))
+            }
+            Expression::Draw { count, deck } => format!("draw({}, {})", child(count, true), deck),
+            Expression::Function {
+                function,
+                arguments,
+            } => {
+                let mut arguments = arguments
+                    .iter()
+                    .map(|argument| child(argument, true))
+                    .collect::<Vec<_>>();
+                arguments.extend(function.parameter().map(|real| real.to_string()));
+
+                format!("{}({})", function.name(), arguments.join(", "))
             }
             Expression::Literal(literal) => literal.to_string(),
+            Expression::Variable(name) => format!("${}", name),
         }
     }
 }
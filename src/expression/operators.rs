@@ -15,7 +15,7 @@ impl Operator {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Binary {
     pub assoc: precedence::Assoc,
     pub precedence: u64,
@@ -23,7 +23,7 @@ pub struct Binary {
     pub space: bool,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Unary {
     pub precedence: u64,
     pub symbol: &'static str,
@@ -3,6 +3,31 @@ use ibig::IBig;
 use num_traits::Zero;
 use std::cmp::Ordering;
 
+pub fn cards(n: &IBig, expression: &Expression, remaining: usize) -> Result<usize, anyhow::Error> {
+    match to_usize(n, expression) {
+        Ok(usize) if usize > remaining => Err(anyhow::anyhow!(format!(
+            "The expression {} evaluated to {}, but only {} cards remain in the deck.",
+            expression, n, remaining
+        ))
+        .context("Cannot draw more cards than remain in the deck.")),
+        Ok(usize) => Ok(usize),
+        Err((Ordering::Less, err)) => {
+            Err(err.context("Drawing a negative number of cards is not supported."))
+        }
+        Err((Ordering::Greater, err)) => {
+            Err(err.context(format!("Cannot draw more than {} cards.", usize::MAX)))
+        }
+        Err((Ordering::Equal, err)) => {
+            Err(err.context(format!("Could not parse {} into usize cards.", n)))
+        }
+    }
+}
+
+/// Variables must be substituted before an expression is evaluated.
+pub fn unbound(name: &str) -> anyhow::Error {
+    anyhow::anyhow!("The variable ${} has no value.", name)
+}
+
 pub fn die(sides: &IBig, expression: &Expression) -> Result<usize, anyhow::Error> {
     match to_usize(sides, expression) {
         Ok(usize) => Ok(usize),
@@ -44,6 +69,37 @@ pub fn exponent(x: &IBig, expression: &Expression) -> Result<usize, anyhow::Erro
     }
 }
 
+pub fn trials(n: &IBig, expression: &Expression) -> Result<usize, anyhow::Error> {
+    match to_usize(n, expression) {
+        Ok(usize) => Ok(usize),
+        Err((Ordering::Less, err)) => {
+            Err(err.context("A negative number of trials is not supported."))
+        }
+        Err((Ordering::Greater, err)) => {
+            Err(err.context(format!("Cannot perform more than {} trials.", usize::MAX)))
+        }
+        Err((Ordering::Equal, err)) => {
+            Err(err.context(format!("Could not parse {} into usize trials.", n)))
+        }
+    }
+}
+
+pub fn range<'a>(
+    min: &'a IBig,
+    max: &'a IBig,
+    expression: &Expression,
+) -> Result<(&'a IBig, &'a IBig), anyhow::Error> {
+    if min > max {
+        return Err(anyhow::anyhow!(format!(
+            "The expression {} has a minimum of {}, which is greater than its maximum of {}.",
+            expression, min, max
+        ))
+        .context("A range's minimum must not exceed its maximum."));
+    }
+
+    Ok((min, max))
+}
+
 pub fn nonzero<'a, A>(n: &'a A, expression: &Expression) -> Result<&'a A, anyhow::Error>
 where
     A: PartialEq + Zero,
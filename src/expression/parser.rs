@@ -1,13 +1,14 @@
-use super::{operators, precedence, Expression};
+use super::functions::{self, Argument, Function, Real};
+use super::{operators, precedence, Deck, Expression, Preset};
 use ibig::IBig;
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_while},
-    character::complete::one_of,
-    combinator::{all_consuming, fail, map, map_res, opt, recognize},
-    error::VerboseError,
-    multi::many1,
-    sequence::{delimited, terminated},
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{alpha1, one_of},
+    combinator::{all_consuming, fail, map, map_opt, map_res, not, opt, peek, recognize, verify},
+    error::{VerboseError, VerboseErrorKind},
+    multi::{many1, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 use num_traits::One;
@@ -21,7 +22,7 @@ fn expression(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
         alt((
             prefix_op(operators::MINUS),
             prefix_op(operators::PLUS),
-            prefix_op(operators::D),
+            preceded(not(function_name), prefix_op(operators::D)),
         )),
         fail,
         alt((
@@ -32,7 +33,13 @@ fn expression(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
             binary_op(operators::DIFFERENCE),
             binary_op(operators::SUM),
         )),
-        alt((literal, delimited(tag("("), expression, tag(")")))),
+        alt((
+            literal,
+            variable,
+            draw,
+            call,
+            delimited(tag("("), expression, tag(")")),
+        )),
         |op: precedence::Operation<&str, &str, &str, Expression>| match op {
             // Binary Expressions
             precedence::Operation::Binary(left, operator, right) => {
@@ -134,10 +141,197 @@ fn space_delimited(s: &str) -> impl FnMut(&str) -> IResult<&str, &str, VerboseEr
     move |i: &str| delimited(space, tag(s), space)(i)
 }
 
+fn draw(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
+    map(
+        delimited(
+            tag("draw("),
+            separated_pair(expression, comma, deck),
+            tag(")"),
+        ),
+        |(count, deck)| Expression::Draw {
+            count: Box::from(count),
+            deck,
+        },
+    )(i)
+}
+
+fn deck(i: &str) -> IResult<&str, Deck, VerboseError<&str>> {
+    let (rest, items) = delimited(
+        tag("["),
+        separated_list1(
+            comma,
+            map(
+                pair(integer, opt(preceded(tag(".."), integer))),
+                |(first, last)| (first.clone(), last.unwrap_or(first)),
+            ),
+        ),
+        tag("]"),
+    )(i)?;
+
+    match Deck::new(items) {
+        Ok(deck) => Ok((rest, deck)),
+        Err(message) => Err(nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(message))],
+        })),
+    }
+}
+
+fn call(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
+    let (rest, (name, arguments)) = pair(
+        terminated(function_name, tag("(")),
+        terminated(
+            separated_list1(comma, alt((real, map(expression, Argument::Expression)))),
+            tag(")"),
+        ),
+    )(i)?;
+
+    match function(name, arguments) {
+        Ok(expression) => Ok((rest, expression)),
+        Err(message) => Err(nom::Err::Failure(VerboseError {
+            errors: vec![(i, VerboseErrorKind::Context(message))],
+        })),
+    }
+}
+
+fn function(name: &str, arguments: Vec<Argument>) -> Result<Expression, &'static str> {
+    if let Some(preset) = Preset::find(name) {
+        return match <[Argument; 1]>::try_from(arguments) {
+            Ok([Argument::Expression(argument)]) => preset.expand(argument),
+            _ => Err("Presets take a single argument."),
+        };
+    }
+
+    let usage = match functions::usage(name) {
+        Some(usage) => usage,
+        None => return Err("draw expects a count and a deck, e.g. draw(2, [1..10])."),
+    };
+
+    let probability = |argument: Argument| {
+        argument
+            .real()
+            .filter(|p| (0.0..=1.0).contains(&p.value))
+            .ok_or(usage)
+    };
+
+    let (function, arguments) = match name {
+        "binomial" => match <[Argument; 2]>::try_from(arguments) {
+            Ok([Argument::Expression(trials), p]) => {
+                (Function::Binomial(probability(p)?), vec![trials])
+            }
+            _ => return Err(usage),
+        },
+        "geometric" => match <[Argument; 1]>::try_from(arguments) {
+            Ok([p]) => {
+                let p = probability(p)?;
+                if p.value <= 0.0 {
+                    return Err(usage);
+                }
+
+                (Function::Geometric(p), vec![])
+            }
+            _ => return Err(usage),
+        },
+        "poisson" => match <[Argument; 1]>::try_from(arguments) {
+            Ok([rate]) => {
+                let rate = rate
+                    .real()
+                    .filter(|rate| rate.value.is_finite() && rate.value >= 0.0)
+                    .ok_or(usage)?;
+
+                (Function::Poisson(rate), vec![])
+            }
+            _ => return Err(usage),
+        },
+        _ => {
+            let function = match name {
+                "explode" => Function::Explode,
+                "max" => Function::Max,
+                "min" => Function::Min,
+                "uniform" => Function::Uniform,
+                _ => return Err(usage),
+            };
+            let arguments = arguments
+                .into_iter()
+                .map(Argument::expression)
+                .collect::<Option<Vec<_>>>()
+                .ok_or(usage)?;
+
+            let valid = match function {
+                Function::Explode => matches!(arguments.as_slice(), [Expression::Dice { .. }]),
+                Function::Uniform => arguments.len() == 2,
+                _ => !arguments.is_empty(),
+            };
+            if !valid {
+                return Err(usage);
+            }
+
+            (function, arguments)
+        }
+    };
+
+    Ok(Expression::Function {
+        function,
+        arguments,
+    })
+}
+
+// Real numbers that are not integers, e.g. `0.35` or `35%`. They are only valid as a whole
+// function argument.
+fn real(i: &str) -> IResult<&str, Argument, VerboseError<&str>> {
+    let digits = || take_while1(|c: char| c.is_ascii_digit());
+
+    map_opt(
+        terminated(
+            recognize(alt((
+                recognize(tuple((digits(), tag("."), digits(), opt(tag("%"))))),
+                recognize(pair(digits(), tag("%"))),
+            ))),
+            peek(alt((comma, tag(")")))),
+        ),
+        |text| Real::new(text).map(Argument::Real),
+    )(i)
+}
+
+// Names that are followed by an argument list, e.g. `draw(`. Checking for these keeps the `d`
+// prefix operator from consuming the first letter of a function.
+fn function_name(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    terminated(
+        verify(alpha1, |name: &str| {
+            name == "draw" || functions::usage(name).is_some() || Preset::find(name).is_some()
+        }),
+        peek(tag("(")),
+    )(i)
+}
+
+fn comma(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    space_delimited(",")(i)
+}
+
 fn literal(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
     map(decimal, Expression::Literal)(i)
 }
 
+// A placeholder, e.g. `$m` or `$bonus_2`.
+fn variable(i: &str) -> IResult<&str, Expression, VerboseError<&str>> {
+    map(
+        preceded(
+            tag("$"),
+            recognize(pair(
+                take_while1(|c: char| c.is_ascii_alphabetic() || c == '_'),
+                take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+            )),
+        ),
+        |name: &str| Expression::Variable(name.to_string()),
+    )(i)
+}
+
+fn integer(i: &str) -> IResult<&str, IBig, VerboseError<&str>> {
+    map(pair(opt(tag("-")), decimal), |(sign, n)| match sign {
+        Some(_) => -n,
+        None => n,
+    })(i)
+}
+
 fn decimal(i: &str) -> IResult<&str, IBig, VerboseError<&str>> {
     map_res(recognize(many1(one_of("0123456789"))), |out: &str| {
         str::parse(out).map_err(|_| ())
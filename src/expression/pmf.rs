@@ -1,81 +1,737 @@
-use super::Expression;
+use super::functions::{Function, Real, MAX_EXPLOSIONS};
+use super::{Deck, Expression};
 use crate::combinatorics::Combinations;
+use crate::distributions;
 use crate::iter_func::IterFunc;
 use crate::pmf::Pmf;
-use ibig::IBig;
+use crate::probability::{Probability, Ratio};
+use ibig::{IBig, UBig};
+use num_traits::{One, Zero};
+use std::collections::{BTreeMap, BTreeSet};
+
+// The cards remaining in each deck drawn from so far.
+type Decks = BTreeMap<Deck, Vec<IBig>>;
+
+// The joint probability of each deck state and the values of the operands that led to it.
+type Paths<P> = BTreeMap<Decks, BTreeMap<Vec<IBig>, P>>;
+
+// The most deck states, or operand values given a deck state, tracked when computing the
+// distribution of an expression that draws from the same deck several times.
+const MAX_STATES: usize = 10_000;
+
+/// A probability the distribution of an expression can be computed in. Only distributions with
+/// finitely many outcomes have exact probabilities.
+pub trait ExpressionProbability: Probability {
+    fn binomial(
+        n: usize,
+        p: &Real,
+        combinations: &mut Combinations,
+    ) -> Result<Pmf<IBig, Self>, anyhow::Error>;
+
+    fn geometric(p: &Real) -> Result<Pmf<IBig, Self>, anyhow::Error>;
+
+    fn poisson(rate: &Real) -> Result<Pmf<IBig, Self>, anyhow::Error>;
+}
+
+impl ExpressionProbability for f64 {
+    fn binomial(
+        n: usize,
+        p: &Real,
+        combinations: &mut Combinations,
+    ) -> Result<Pmf<IBig>, anyhow::Error> {
+        Ok(distributions::binomial(n, p.value, combinations))
+    }
+
+    fn geometric(p: &Real) -> Result<Pmf<IBig>, anyhow::Error> {
+        distributions::geometric(p.value).ok_or_else(|| too_wide(&format!("geometric({})", p)))
+    }
+
+    fn poisson(rate: &Real) -> Result<Pmf<IBig>, anyhow::Error> {
+        distributions::poisson(rate.value).ok_or_else(|| too_wide(&format!("poisson({})", rate)))
+    }
+}
+
+impl ExpressionProbability for Ratio {
+    fn binomial(
+        n: usize,
+        p: &Real,
+        combinations: &mut Combinations,
+    ) -> Result<Pmf<IBig, Ratio>, anyhow::Error> {
+        let p = p
+            .ratio()
+            .ok_or_else(|| anyhow::anyhow!("{} is not a probability.", p))?;
+
+        Ok(distributions::exact_binomial(n, &p, combinations))
+    }
+
+    fn geometric(p: &Real) -> Result<Pmf<IBig, Ratio>, anyhow::Error> {
+        Err(inexact(&format!("geometric({})", p)))
+    }
+
+    fn poisson(rate: &Real) -> Result<Pmf<IBig, Ratio>, anyhow::Error> {
+        Err(inexact(&format!("poisson({})", rate)))
+    }
+}
+
+fn too_wide(distribution: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} has more than {} likely outcomes, too many to compute its distribution. Try \
+         --simulate instead.",
+        distribution,
+        distributions::MAX_OUTCOMES
+    )
+}
+
+fn inexact(distribution: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} has infinitely many outcomes, so its probabilities cannot be exact.",
+        distribution
+    )
+}
+
+/// The distribution of an expression, and the distributions of its operands in the order of
+/// `Expression::operands`. Each operand's distribution is of the operand on its own, so operands
+/// that draw from the same deck are not conditioned on one another.
+#[derive(Clone, Debug)]
+pub struct PmfTree<P = f64> {
+    pub expression: Expression,
+    pub pmf: Pmf<IBig, P>,
+    pub operands: Vec<PmfTree<P>>,
+}
+
+impl<P: Probability> PmfTree<P> {
+    /// The same tree with the nearest `f64` to each probability.
+    pub fn to_f64(&self) -> PmfTree {
+        PmfTree {
+            expression: self.expression.clone(),
+            pmf: self.pmf.to_f64(),
+            operands: self.operands.iter().map(PmfTree::to_f64).collect(),
+        }
+    }
+}
+
+pub fn pmf<P>(
+    e: &Expression,
+    combinations: &mut Combinations,
+) -> Result<Pmf<IBig, P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    let operands = e.operands();
+
+    // Subexpressions drawing from the same deck are not independent.
+    if shares_deck(e, &operands) {
+        return match pool_draws::<P>(e, combinations)? {
+            Some(pooled) => pmf(&pooled, combinations),
+            None => Ok(marginal(&stateful(e, combinations, &Decks::new())?)),
+        };
+    }
 
-pub fn pmf(e: &Expression, combinations: &mut Combinations) -> Result<Pmf<IBig>, anyhow::Error> {
     match e {
-        Expression::Dice { left, right, .. } => {
-            let left = pmf(left, combinations)?
+        Expression::Draw { count, deck } => {
+            let counts = pmf::<P>(count, combinations)?;
+
+            draw(&counts, count, deck, combinations)
+        }
+        Expression::Literal(literal) => Ok(Pmf::constant(literal.clone())),
+        Expression::Variable(name) => Err(super::parse::unbound(name)),
+        _ => {
+            let operands = operands
+                .into_iter()
+                .map(|operand| pmf(operand, combinations))
+                .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+            apply(e, &operands, combinations)
+        }
+    }
+}
+
+/// Like `pmf`, but keeps the distribution of every subexpression.
+pub fn tree<P>(e: &Expression, combinations: &mut Combinations) -> Result<PmfTree<P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    let operands = e
+        .operands()
+        .into_iter()
+        .map(|operand| tree(operand, combinations))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+
+    let pmf = match e {
+        _ if shares_deck(e, &e.operands()) => pmf(e, combinations)?,
+        Expression::Draw { count, deck } => draw(&operands[0].pmf, count, deck, combinations)?,
+        Expression::Literal(literal) => Pmf::constant(literal.clone()),
+        Expression::Variable(name) => return Err(super::parse::unbound(name)),
+        _ => {
+            let pmfs = operands
+                .iter()
+                .map(|operand| operand.pmf.clone())
+                .collect::<Vec<_>>();
+
+            apply(e, &pmfs, combinations)?
+        }
+    };
+
+    Ok(PmfTree {
+        expression: e.clone(),
+        pmf,
+        operands,
+    })
+}
+
+// The distribution of the sum of a hand drawn from `deck`, with `counts` cards.
+fn draw<P>(
+    counts: &Pmf<IBig, P>,
+    count: &Expression,
+    deck: &Deck,
+    combinations: &mut Combinations,
+) -> Result<Pmf<IBig, P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    let mut outcomes = vec![];
+    for outcome in counts.iter() {
+        let n = super::parse::cards(&outcome.value, count, deck.cards().len())?;
+
+        outcomes.extend(
+            hand_sums(deck.cards(), n, combinations)
+                .into_iter()
+                .map(|(p, sum)| (outcome.p.clone() * p, sum)),
+        );
+    }
+
+    Ok(outcomes.into_iter().collect())
+}
+
+// Applies an operator to the distributions of its independent operands, given in the order of
+// `Expression::operands`.
+fn apply<P>(
+    e: &Expression,
+    operands: &[Pmf<IBig, P>],
+    combinations: &mut Combinations,
+) -> Result<Pmf<IBig, P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    match (e, operands) {
+        (Expression::Dice { left, right, .. }, [left_pmf, right_pmf]) => {
+            let left = left_pmf
                 .iter()
-                .map(|outcome| Ok((outcome.p, super::parse::dice(&outcome.value, left)?)))
-                .collect::<Result<Pmf<_>, anyhow::Error>>()?;
+                .map(|outcome| Ok((outcome.p.clone(), super::parse::dice(&outcome.value, left)?)))
+                .collect::<Result<Pmf<_, P>, anyhow::Error>>()?;
 
-            let right = pmf(right, combinations)?
+            let right = right_pmf
                 .iter()
-                .map(|outcome| Ok((outcome.p, super::parse::die(&outcome.value, right)?)))
-                .collect::<Result<Pmf<_>, anyhow::Error>>()?;
+                .map(|outcome| Ok((outcome.p.clone(), super::parse::die(&outcome.value, right)?)))
+                .collect::<Result<Pmf<_, P>, anyhow::Error>>()?;
 
             Ok(left
                 .iter()
                 .cartesian_product_by(right.iter(), |n_dice, die| {
-                    (n_dice.p * die.p, n_dice.value, die.value)
+                    (n_dice.p.clone() * die.p.clone(), n_dice.value, die.value)
                 })
                 .flat_map(|(p, n_dice, die)| {
                     let max_roll = n_dice * die;
 
-                    (n_dice..=max_roll)
-                        .into_iter()
-                        .map(move |sum| (p, sum, n_dice, die))
+                    (n_dice..=max_roll).map(move |sum| (p.clone(), sum, n_dice, die))
                 })
                 .scan(combinations, |combinations, (p, sum, n_dice, die)| {
+                    let count = combinations.dice_roll_sum_count(sum, n_dice, die);
+
                     Some((
-                        p * combinations.probability_dice_roll_sum(sum, n_dice, die),
+                        p * P::ratio(&count, &UBig::from(die).pow(n_dice)),
                         IBig::from(sum),
                     ))
                 })
-                .collect::<Pmf<IBig>>())
+                .collect::<Pmf<IBig, P>>())
         }
-        Expression::Difference { left, right, .. } => {
-            let left = pmf(left, combinations)?;
-            let right = pmf(right, combinations)?;
-
-            Ok(left.cartesian_product(&right, |l, r| l - r))
+        (Expression::Difference { .. }, [left, right]) => {
+            Ok(left.cartesian_product(right, |l, r| l - r))
         }
-        Expression::Exponentiation { left, right, .. } => {
-            let left = pmf(left, combinations)?;
-            let right = pmf(right, combinations)?
+        (Expression::Exponentiation { right, .. }, [left_pmf, right_pmf]) => {
+            let right = right_pmf
                 .iter()
-                .map(|outcome| Ok((outcome.p, super::parse::exponent(&outcome.value, right)?)))
-                .collect::<Result<Pmf<_>, anyhow::Error>>()?;
+                .map(|outcome| {
+                    Ok((
+                        outcome.p.clone(),
+                        super::parse::exponent(&outcome.value, right)?,
+                    ))
+                })
+                .collect::<Result<Pmf<_, P>, anyhow::Error>>()?;
+
+            Ok(left_pmf.cartesian_product(&right, |b, x| b.pow(*x)))
+        }
+        (
+            Expression::Function {
+                function: Function::Explode,
+                arguments,
+            },
+            [left_pmf, right_pmf],
+        ) => {
+            let (left, right) = match arguments.as_slice() {
+                [Expression::Dice { left, right, .. }] => (left, right),
+                _ => unreachable!("Only dice explode."),
+            };
+
+            let mut components = vec![];
+            for n_dice in left_pmf.iter() {
+                let n = super::parse::dice(&n_dice.value, left)?;
+
+                for die in right_pmf.iter() {
+                    let sides = super::parse::die(&die.value, right)?;
+
+                    components.push((
+                        n_dice.p.clone() * die.p.clone(),
+                        exploding_die(sides).convolve_n(n),
+                    ));
+                }
+            }
+
+            Ok(Pmf::mixture(&components))
+        }
+        (
+            Expression::Function {
+                function: Function::Binomial(p),
+                arguments,
+            },
+            [trials_pmf],
+        ) => {
+            let mut components = vec![];
+            for trials in trials_pmf.iter() {
+                let n = super::parse::trials(&trials.value, &arguments[0])?;
+
+                components.push((trials.p.clone(), P::binomial(n, p, combinations)?));
+            }
+
+            Ok(Pmf::mixture(&components))
+        }
+        (
+            Expression::Function {
+                function: Function::Geometric(p),
+                ..
+            },
+            [],
+        ) => P::geometric(p),
+        (
+            Expression::Function {
+                function: Function::Poisson(rate),
+                ..
+            },
+            [],
+        ) => P::poisson(rate),
+        (
+            Expression::Function {
+                function: Function::Uniform,
+                ..
+            },
+            [min_pmf, max_pmf],
+        ) => {
+            let mut components = vec![];
+            for min in min_pmf.iter() {
+                for max in max_pmf.iter() {
+                    let (min_value, max_value) = super::parse::range(&min.value, &max.value, e)?;
+
+                    components.push((
+                        min.p.clone() * max.p.clone(),
+                        distributions::uniform(min_value, max_value),
+                    ));
+                }
+            }
 
-            Ok(left.cartesian_product(&right, |b, x| b.pow(*x)))
+            Ok(Pmf::mixture(&components))
         }
-        Expression::IQuotient { left, right, .. } => {
-            let left = pmf(left, combinations)?;
-            let right_pmf = pmf(right, combinations)?;
+        (
+            Expression::Function {
+                function: Function::Max,
+                ..
+            },
+            [first, rest @ ..],
+        ) => Ok(rest.iter().fold(first.clone(), |max, pmf| {
+            max.cartesian_product(pmf, |l, r| l.max(r).clone())
+        })),
+        (
+            Expression::Function {
+                function: Function::Min,
+                ..
+            },
+            [first, rest @ ..],
+        ) => Ok(rest.iter().fold(first.clone(), |min, pmf| {
+            min.cartesian_product(pmf, |l, r| l.min(r).clone())
+        })),
+        (Expression::IQuotient { right, .. }, [right_pmf, left_pmf]) => {
             let right = right_pmf
                 .iter()
-                .map(|outcome| Ok((outcome.p, super::parse::nonzero(&outcome.value, right)?)))
-                .collect::<Result<Pmf<_>, anyhow::Error>>()?;
+                .map(|outcome| {
+                    Ok((
+                        outcome.p.clone(),
+                        super::parse::nonzero(&outcome.value, right)?,
+                    ))
+                })
+                .collect::<Result<Pmf<_, P>, anyhow::Error>>()?;
 
-            Ok(left.cartesian_product(&right, |l, r| l / *r))
+            Ok(left_pmf.cartesian_product(&right, |l, r| l / *r))
+        }
+        (Expression::Product { .. }, [left, right]) => {
+            Ok(left.cartesian_product(right, |l, r| l * r))
         }
-        Expression::Product { left, right, .. } => {
-            let left = pmf(left, combinations)?;
-            let right = pmf(right, combinations)?;
+        (Expression::Sum { .. }, [left, right]) => Ok(left.cartesian_product(right, |l, r| l + r)),
+        (Expression::Minus { .. }, [operand]) => Ok(operand.clone().map(|value| -value)),
+        (Expression::Plus { .. }, [operand]) => Ok(operand.clone()),
+        _ => unreachable!("{} has operands that do not match its operator.", e),
+    }
+}
+
+// The distribution of a single die that is rolled again, and added, each time it shows its
+// highest face.
+fn exploding_die<P: Probability>(sides: usize) -> Pmf<IBig, P> {
+    let mut outcomes = vec![];
+
+    // A die without sides has no outcomes.
+    if sides == 0 {
+        return outcomes.into_iter().collect();
+    }
 
-            Ok(left.cartesian_product(&right, |l, r| l * r))
+    for explosions in 0..=MAX_EXPLOSIONS {
+        let p = P::ratio(&UBig::one(), &UBig::from(sides).pow(explosions + 1));
+        let faces = if explosions == MAX_EXPLOSIONS {
+            sides
+        } else {
+            sides.saturating_sub(1)
+        };
+
+        for face in 1..=faces {
+            outcomes.push((p.clone(), IBig::from(explosions * sides + face)));
+        }
+    }
+
+    outcomes.into_iter().collect()
+}
+
+// Computes the joint distribution of an expression's value and the cards left in each deck. This
+// is only necessary when several parts of the expression draw from the same deck.
+fn stateful<P>(
+    e: &Expression,
+    combinations: &mut Combinations,
+    state: &Decks,
+) -> Result<Pmf<(IBig, Decks), P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    if decks(e).is_empty() {
+        return Ok(pmf(e, combinations)?.map(|value| (value.clone(), state.clone())));
+    }
+
+    let mut outcomes = BTreeMap::<(IBig, Decks), P>::new();
+    for (state, paths) in stateful_operands::<P>(e, combinations, state)? {
+        for (values, p_values) in paths {
+            match e {
+                Expression::Draw { count, deck } => {
+                    let cards = state
+                        .get(deck)
+                        .map(|cards| cards.as_slice())
+                        .unwrap_or_else(|| deck.cards());
+                    let n = super::parse::cards(&values[0], count, cards.len())?;
+
+                    for (p, sum, remaining) in hands(e, cards, n, combinations)? {
+                        let mut state = state.clone();
+                        state.insert(deck.clone(), remaining);
+
+                        add(&mut outcomes, (sum, state), p_values.clone() * p);
+                    }
+                }
+                _ => {
+                    let operands = values
+                        .into_iter()
+                        .map(Pmf::<IBig, P>::constant)
+                        .collect::<Vec<_>>();
+
+                    for outcome in apply(e, &operands, combinations)?.iter() {
+                        add(
+                            &mut outcomes,
+                            (outcome.value.clone(), state.clone()),
+                            p_values.clone() * outcome.p.clone(),
+                        );
+                    }
+                }
+            }
+
+            if outcomes.len() > MAX_STATES {
+                return Err(too_many_states(e));
+            }
+        }
+    }
+
+    Ok(outcomes.into_iter().map(|(value, p)| (p, value)).collect())
+}
+
+// The joint probability of the values of an expression's operands, evaluated in order, and of the
+// deck state they leave. Later operands only depend on earlier ones through the deck state, so
+// the paths that leave the same cards in each deck are merged after each operand.
+fn stateful_operands<P>(
+    e: &Expression,
+    combinations: &mut Combinations,
+    state: &Decks,
+) -> Result<Paths<P>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    let mut paths = Paths::from([(state.clone(), BTreeMap::from([(vec![], P::one())]))]);
+
+    for operand in e.operands() {
+        let mut next = Paths::new();
+        let mut size = 0;
+
+        for (state, values) in paths {
+            let operand_pmf = stateful::<P>(operand, combinations, &state)?;
+
+            for (values, p) in values.iter() {
+                for outcome in operand_pmf.iter() {
+                    let (value, state) = &outcome.value;
+                    let mut values = values.clone();
+                    values.push(value.clone());
+
+                    let paths = next.entry(state.clone()).or_insert_with(BTreeMap::new);
+                    if !paths.contains_key(&values) {
+                        size += 1;
+                    }
+                    add(paths, values, p.clone() * outcome.p.clone());
+                }
+            }
+
+            if size > MAX_STATES {
+                return Err(too_many_states(e));
+            }
+        }
+
+        paths = next;
+    }
+
+    Ok(paths)
+}
+
+fn add<A: Ord, P: Probability>(outcomes: &mut BTreeMap<A, P>, value: A, p: P) {
+    let total = outcomes.entry(value).or_insert_with(P::zero);
+    *total = total.clone() + p;
+}
+
+fn too_many_states(e: &Expression) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} can leave its decks in more than {} ways, too many to track exactly. Try --simulate \
+         instead.",
+        e,
+        MAX_STATES
+    )
+}
+
+// A sum of hands drawn from the same deck is one hand of all their cards, so a sum with
+// `draw(a, D) + draw(b, D)`, where `a` and `b` are constants, is the same sum with `draw(a + b, D)`.
+// The pooled sum, if any draws could be pooled.
+fn pool_draws<P>(
+    e: &Expression,
+    combinations: &mut Combinations,
+) -> Result<Option<Expression>, anyhow::Error>
+where
+    P: ExpressionProbability,
+{
+    let mut terms = vec![];
+    summands(e, &mut terms);
+
+    let mut constants = BTreeMap::<&Deck, Vec<IBig>>::new();
+    for term in terms.iter() {
+        if let Expression::Draw { count, deck } = term {
+            if decks(count).is_empty() {
+                let counts_pmf = pmf::<P>(count, combinations)?;
+
+                if let [outcome] = counts_pmf.iter().collect::<Vec<_>>().as_slice() {
+                    super::parse::cards(&outcome.value, count, deck.cards().len())?;
+                    constants
+                        .entry(deck)
+                        .or_default()
+                        .push(outcome.value.clone());
+                }
+            }
         }
+    }
+
+    // Only pool a deck when every term drawing from it is a constant draw.
+    constants.retain(|deck, counts| {
+        counts.len() > 1
+            && terms
+                .iter()
+                .filter(|term| decks(term).contains(deck))
+                .count()
+                == counts.len()
+    });
+    if constants.is_empty() {
+        return Ok(None);
+    }
+
+    let mut pooled = terms
+        .into_iter()
+        .filter(
+            |term| !matches!(term, Expression::Draw { deck, .. } if constants.contains_key(deck)),
+        )
+        .cloned()
+        .collect::<Vec<_>>();
+    for (deck, counts) in constants {
+        pooled.push(Expression::Draw {
+            count: Box::new(Expression::Literal(
+                counts.into_iter().fold(IBig::zero(), |sum, n| sum + n),
+            )),
+            deck: deck.clone(),
+        });
+    }
+
+    Ok(pooled.into_iter().reduce(|left, right| Expression::Sum {
+        left: Box::new(left),
+        right: Box::new(right),
+        operator: super::operators::SUM,
+    }))
+}
+
+// The terms of a sum, e.g. `a`, `b` and `c` in `a + b + c`.
+fn summands<'a>(e: &'a Expression, terms: &mut Vec<&'a Expression>) {
+    match e {
         Expression::Sum { left, right, .. } => {
-            let left = pmf(left, combinations)?;
-            let right = pmf(right, combinations)?;
+            summands(left, terms);
+            summands(right, terms);
+        }
+        _ => terms.push(e),
+    }
+}
+
+fn marginal<P: Probability>(pmf: &Pmf<(IBig, Decks), P>) -> Pmf<IBig, P> {
+    pmf.iter()
+        .map(|outcome| (outcome.p.clone(), outcome.value.0.clone()))
+        .collect()
+}
 
-            Ok(left.cartesian_product(&right, |l, r| l + r))
+// The distribution of the sum of `n` cards drawn from `cards`, computed one kind of card at a time.
+fn hand_sums<P: Probability>(
+    cards: &[IBig],
+    n: usize,
+    combinations: &mut Combinations,
+) -> Vec<(P, IBig)> {
+    let mut sums = BTreeMap::from([((0, IBig::zero()), UBig::from(1_u8))]);
+
+    for (card, count) in kinds(cards) {
+        let mut next = BTreeMap::new();
+
+        for ((drawn, sum), ways) in sums.into_iter() {
+            for i in 0..=count.min(n - drawn) {
+                *next
+                    .entry((drawn + i, &sum + card * IBig::from(i)))
+                    .or_insert_with(UBig::zero) += &ways * combinations.choose(count, i);
+            }
+        }
+
+        sums = next;
+    }
+
+    let total = combinations.choose(cards.len(), n);
+    sums.into_iter()
+        .filter(|((drawn, _), _)| *drawn == n)
+        .map(|((_, sum), ways)| (P::ratio(&ways, &total), sum))
+        .collect()
+}
+
+// Every distinct hand of `n` cards drawn from `cards`, with its probability, sum and the cards
+// remaining in the deck.
+fn hands<P: Probability>(
+    e: &Expression,
+    cards: &[IBig],
+    n: usize,
+    combinations: &mut Combinations,
+) -> Result<Vec<(P, IBig, Vec<IBig>)>, anyhow::Error> {
+    let kinds = kinds(cards);
+    let deck = kinds.iter().map(|(_, count)| *count).collect::<Vec<_>>();
+
+    // Count the hands before listing them, since there can be far too many to list.
+    let mut ways = vec![0_usize; n + 1];
+    ways[0] = 1;
+    for count in deck.iter() {
+        for drawn in (0..=n).rev() {
+            ways[drawn] = (1..=(*count).min(drawn)).fold(ways[drawn], |total, i| {
+                total.saturating_add(ways[drawn - i])
+            });
         }
-        Expression::Minus { operand, .. } => Ok(pmf(operand, combinations)?.map(|value| -value)),
-        Expression::Plus { operand, .. } => pmf(operand, combinations),
-        Expression::Literal(literal) => Ok(Pmf::constant(literal.clone())),
     }
+    if ways[n] > MAX_STATES {
+        return Err(too_many_states(e));
+    }
+
+    // Only keep the partial hands that the later kinds of card can still complete.
+    let mut later = cards.len();
+    let mut hands = vec![vec![]];
+    for count in deck.iter() {
+        later -= count;
+        hands = hands
+            .into_iter()
+            .flat_map(|hand: Vec<usize>| {
+                let needed = n - hand.iter().sum::<usize>();
+
+                (needed.saturating_sub(later)..=(*count).min(needed)).map(move |i| {
+                    let mut hand = hand.clone();
+                    hand.push(i);
+                    hand
+                })
+            })
+            .collect();
+    }
+
+    Ok(hands
+        .into_iter()
+        .map(|hand| {
+            let p = P::ratio(
+                &combinations.hypergeometric_count(&hand, &deck),
+                &combinations.choose(cards.len(), n),
+            );
+            let mut sum = IBig::zero();
+            let mut remaining = vec![];
+
+            for ((card, count), drawn) in kinds.iter().zip(hand.iter()) {
+                sum += *card * IBig::from(*drawn);
+                for _ in *drawn..*count {
+                    remaining.push((*card).clone());
+                }
+            }
+
+            (p, sum, remaining)
+        })
+        .collect())
+}
+
+// Groups sorted cards into each distinct card and its number of copies.
+fn kinds(cards: &[IBig]) -> Vec<(&IBig, usize)> {
+    cards
+        .iter()
+        .group_by(|card| *card, |card, copies| (card, copies.len() + 1))
+        .collect()
+}
+
+fn decks(e: &Expression) -> BTreeSet<&Deck> {
+    let mut used = e
+        .operands()
+        .into_iter()
+        .flat_map(decks)
+        .collect::<BTreeSet<_>>();
+
+    if let Expression::Draw { deck, .. } = e {
+        used.insert(deck);
+    }
+
+    used
+}
+
+fn shares_deck(e: &Expression, operands: &[&Expression]) -> bool {
+    let mut used = BTreeSet::new();
+
+    if let Expression::Draw { deck, .. } = e {
+        used.insert(deck);
+    }
+
+    operands
+        .iter()
+        .flat_map(|operand| decks(operand))
+        .any(|deck| !used.insert(deck))
 }
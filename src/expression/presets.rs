@@ -0,0 +1,233 @@
+use super::functions::Function;
+use super::{operators, Expression};
+use ibig::IBig;
+use num_traits::Zero;
+
+/// A named rule from a game system, e.g. rolling with advantage. Presets are called like
+/// functions and expand into ordinary expressions while parsing.
+#[derive(Debug)]
+pub struct Preset {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub example: &'static str,
+    expand: fn(Expression) -> Result<Expression, &'static str>,
+}
+
+pub const PRESETS: [Preset; 5] = [
+    Preset {
+        name: "adv",
+        usage: "adv(mod)",
+        description: "D&D 5e advantage: the highest of two d20s plus a modifier.",
+        example: "adv(+5)",
+        expand: |modifier| Ok(plus(highest(2, 20), modifier)),
+    },
+    Preset {
+        name: "dis",
+        usage: "dis(mod)",
+        description: "D&D 5e disadvantage: the lowest of two d20s plus a modifier.",
+        example: "dis(+5)",
+        expand: |modifier| Ok(plus(lowest(2, 20), modifier)),
+    },
+    Preset {
+        name: "sw",
+        usage: "sw(die)",
+        description: "Savage Worlds trait roll: the highest of an exploding trait die and an \
+            exploding d6 wild die.",
+        example: "sw(d8)",
+        expand: |die| match die {
+            Expression::Dice { .. } => Ok(Expression::Function {
+                function: Function::Max,
+                arguments: vec![explode(die), explode(dice(1, 6))],
+            }),
+            _ => Err("The sw preset expects a trait die, e.g. sw(d8)."),
+        },
+    },
+    Preset {
+        name: "blades",
+        usage: "blades(dice)",
+        description: "Blades in the Dark action roll: the highest of a pool of d6s, or the \
+            lowest of two d6s for an empty pool. Critical successes count as a 6.",
+        example: "blades(3)",
+        expand: |pool| match constant(&pool) {
+            Some(pool) if pool.is_zero() => Ok(lowest(2, 6)),
+            Some(pool) if pool > IBig::zero() && pool <= IBig::from(u8::MAX) => {
+                // Unwrap is safe because the pool was just bounded by u8::MAX.
+                Ok(highest(u8::try_from(&pool).unwrap(), 6))
+            }
+            _ => Err("The blades preset expects a constant pool of 0 to 255 dice, e.g. blades(3)."),
+        },
+    },
+    Preset {
+        name: "pbta",
+        usage: "pbta(stat)",
+        description: "Powered by the Apocalypse move: two d6s plus a stat. 6- misses, 7-9 is a \
+            partial success and 10+ is a full success.",
+        example: "pbta(+1)",
+        expand: |stat| Ok(plus(dice(2, 6), stat)),
+    },
+];
+
+impl Preset {
+    pub fn find(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|preset| preset.name == name)
+    }
+
+    pub fn expand(&self, argument: Expression) -> Result<Expression, &'static str> {
+        (self.expand)(argument)
+    }
+}
+
+fn dice(n: u8, sides: u8) -> Expression {
+    Expression::Dice {
+        left: Box::from(Expression::Literal(IBig::from(n))),
+        right: Box::from(Expression::Literal(IBig::from(sides))),
+        operator: operators::DICE,
+    }
+}
+
+fn explode(dice: Expression) -> Expression {
+    Expression::Function {
+        function: Function::Explode,
+        arguments: vec![dice],
+    }
+}
+
+fn highest(n: u8, sides: u8) -> Expression {
+    Expression::Function {
+        function: Function::Max,
+        arguments: (0..n).map(|_| dice(1, sides)).collect(),
+    }
+}
+
+fn lowest(n: u8, sides: u8) -> Expression {
+    Expression::Function {
+        function: Function::Min,
+        arguments: (0..n).map(|_| dice(1, sides)).collect(),
+    }
+}
+
+// Adds a modifier, folding constant modifiers so `adv(-1)` reads `... - 1` instead of `... + -1`.
+fn plus(e: Expression, modifier: Expression) -> Expression {
+    match constant(&modifier) {
+        Some(n) if n.is_zero() => e,
+        Some(n) if n < IBig::zero() => Expression::Difference {
+            left: Box::from(e),
+            right: Box::from(Expression::Literal(-n)),
+            operator: operators::DIFFERENCE,
+        },
+        Some(n) => Expression::Sum {
+            left: Box::from(e),
+            right: Box::from(Expression::Literal(n)),
+            operator: operators::SUM,
+        },
+        None => Expression::Sum {
+            left: Box::from(e),
+            right: Box::from(modifier),
+            operator: operators::SUM,
+        },
+    }
+}
+
+fn constant(e: &Expression) -> Option<IBig> {
+    match e {
+        Expression::Literal(n) => Some(n.clone()),
+        Expression::Minus { operand, .. } => constant(operand).map(|n| -n),
+        Expression::Plus { operand, .. } => constant(operand),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pmf::Pmf;
+    use std::str::FromStr;
+
+    #[test]
+    fn advantage() {
+        let pmf = pmf("adv(+5)");
+
+        assert_eq!(
+            Expression::from_str("adv(+5)").unwrap().to_string(),
+            "max(1d20, 1d20) + 5"
+        );
+        assert_p(&pmf, 6, 1.0 / 400.0);
+        assert_p(&pmf, 15, 19.0 / 400.0);
+        assert_p(&pmf, 25, 39.0 / 400.0);
+    }
+
+    #[test]
+    fn disadvantage() {
+        let pmf = pmf("dis(-1)");
+
+        assert_eq!(
+            Expression::from_str("dis(-1)").unwrap().to_string(),
+            "min(1d20, 1d20) - 1"
+        );
+        assert_p(&pmf, 0, 39.0 / 400.0);
+        assert_p(&pmf, 19, 1.0 / 400.0);
+    }
+
+    #[test]
+    fn savage_worlds() {
+        let pmf = pmf("sw(d4)");
+
+        // Both dice show a 1.
+        assert_p(&pmf, 1, 1.0 / 24.0);
+        // The d4 aces into a 1 and the d6 shows at most a 5, or the d6 shows a 5 and the d4 does
+        // not ace.
+        assert_p(&pmf, 5, 1.0 / 16.0 * 5.0 / 6.0 + 1.0 / 6.0 * 3.0 / 4.0);
+        // The d6 aces into a 1 and the d4 aces at most once, or the d4 aces into a 3 and the d6
+        // does not ace.
+        assert_p(&pmf, 7, 1.0 / 36.0 * 15.0 / 16.0 + 1.0 / 16.0 * 5.0 / 6.0);
+    }
+
+    #[test]
+    fn blades() {
+        let pmf = pmf("blades(2)");
+
+        assert_p(&pmf, 6, 11.0 / 36.0);
+        assert_p(&pmf, 4, 7.0 / 36.0);
+        assert_p(&pmf, 1, 1.0 / 36.0);
+
+        let pmf = self::pmf("blades(0)");
+
+        assert_p(&pmf, 6, 1.0 / 36.0);
+        assert_p(&pmf, 1, 11.0 / 36.0);
+    }
+
+    #[test]
+    fn powered_by_the_apocalypse() {
+        let pmf = pmf("pbta(+1)");
+        let full_success = pmf
+            .iter()
+            .filter(|outcome| outcome.value >= IBig::from(10))
+            .map(|outcome| outcome.p)
+            .sum::<f64>();
+
+        assert_p(&pmf, 3, 1.0 / 36.0);
+        float_eq::assert_float_eq!(full_success, 10.0 / 36.0, abs <= 0.000001);
+    }
+
+    #[test]
+    fn preset_errors() {
+        assert!(Expression::from_str("sw(8)").is_err());
+        assert!(Expression::from_str("blades(1d4)").is_err());
+        assert!(Expression::from_str("adv(1, 2)").is_err());
+    }
+
+    fn pmf(expression: &str) -> Pmf<IBig> {
+        Expression::from_str(expression).unwrap().pmf().unwrap()
+    }
+
+    fn assert_p(pmf: &Pmf<IBig>, value: i64, p: f64) {
+        let actual = pmf
+            .iter()
+            .find(|outcome| outcome.value == IBig::from(value))
+            .map(|outcome| outcome.p)
+            .unwrap_or(0.0);
+
+        float_eq::assert_float_eq!(actual, p, abs <= 0.000001);
+    }
+}
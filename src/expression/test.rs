@@ -1,5 +1,6 @@
 use super::*;
 use crate::pmf::Pmf;
+use crate::probability::Probability;
 use ibig::{ubig, UBig};
 use quickcheck_macros::quickcheck;
 use std::collections::HashSet;
@@ -72,7 +73,7 @@ fn exponent_precedence_minus(seed: u64) -> bool {
 fn exponent_precedence_dice(seed: u64) -> bool {
     let expression = Expression::from_str("1d4 ** 2").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 1, 16)
         && all_in_range(&rolls, HashSet::from([ubig!(4)]), (1, 1))
@@ -83,7 +84,7 @@ fn exponent_precedence_dice(seed: u64) -> bool {
 fn dice(seed: u64) -> bool {
     let expression = Expression::from_str("d4").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 1, 4)
         && all_in_range(&rolls, HashSet::from([ubig!(4)]), (1, 1))
@@ -94,7 +95,7 @@ fn dice(seed: u64) -> bool {
 fn n_dice(seed: u64) -> bool {
     let expression = Expression::from_str("3d6").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 3, 18)
         && all_in_range(&rolls, HashSet::from([ubig!(6)]), (3, 3))
@@ -105,7 +106,7 @@ fn n_dice(seed: u64) -> bool {
 fn n_dice_dice(seed: u64) -> bool {
     let expression = Expression::from_str("1d4d6").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 1, 24)
         && all_in_range(&rolls, HashSet::from([ubig!(4), ubig!(6)]), (2, 5))
@@ -116,7 +117,7 @@ fn n_dice_dice(seed: u64) -> bool {
 fn dice_precedence(seed: u64) -> bool {
     let expression = Expression::from_str("-3d6").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, -18, -3)
         && all_in_range(&rolls, HashSet::from([ubig!(6)]), (3, 3))
@@ -172,7 +173,7 @@ fn sum_literals(seed: u64) -> bool {
 fn plus_mod(seed: u64) -> bool {
     let expression = Expression::from_str("2d8+1").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 3, 17)
         && all_in_range(&rolls, HashSet::from([ubig!(8)]), (2, 2))
@@ -183,7 +184,7 @@ fn plus_mod(seed: u64) -> bool {
 fn minus_mod(seed: u64) -> bool {
     let expression = Expression::from_str("1d2 - 1").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 0, 1)
         && all_in_range(&rolls, HashSet::from([ubig!(2)]), (1, 1))
@@ -194,7 +195,7 @@ fn minus_mod(seed: u64) -> bool {
 fn sum_dice(seed: u64) -> bool {
     let expression = Expression::from_str("1d10+1d12").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 2, 22)
         && all_in_range(&rolls, HashSet::from([ubig!(10), ubig!(12)]), (2, 2))
@@ -214,7 +215,7 @@ fn left_associative_left_grouping(seed: u64) -> bool {
 fn left_associative_right_grouping(seed: u64) -> bool {
     let expression = Expression::from_str("1d(20 + 10)").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 1, 30)
         && all_in_range(&rolls, HashSet::from([ubig!(30)]), (1, 1))
@@ -243,13 +244,334 @@ fn right_associative_right_grouping(seed: u64) -> bool {
 fn trailing_space(seed: u64) -> bool {
     let expression = Expression::from_str("1d20      ").unwrap();
     let pmf = pmf(&expression).unwrap();
-    let Evaluand { rolls, value } = expression.eval(seed).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
 
     in_range(&pmf, value, 1, 20)
         && all_in_range(&rolls, HashSet::from([UBig::from(20_u8)]), (1, 1))
         && expression.to_string() == "1d20"
 }
 
+#[quickcheck]
+fn draw(seed: u64) -> bool {
+    let expression = Expression::from_str("draw(2, [1..4])").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let Evaluand { draws, value, .. } = expression.eval(seed).unwrap();
+
+    in_range(&pmf, value, 3, 7)
+        && draws.len() == 1
+        && draws[0].len() == 2
+        && draws[0][0] != draws[0][1]
+        && expression.to_string() == "draw(2, [1..4])"
+}
+
+#[quickcheck]
+fn draw_shared_deck(seed: u64) -> bool {
+    let expression = Expression::from_str("draw(1, [1, 2]) - draw(1, [1..2])").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let Evaluand { draws, value, .. } = expression.eval(seed).unwrap();
+
+    let values = pmf
+        .iter()
+        .map(|outcome| outcome.value.clone())
+        .collect::<Vec<_>>();
+
+    values == vec![IBig::from(-1), IBig::from(1)]
+        && value != IBig::from(0)
+        && draws.concat().len() == 2
+        && expression.to_string() == "draw(1, [1, 2]) - draw(1, [1..2])"
+}
+
+#[quickcheck]
+fn trace_keeps_each_die(seed: u64) -> bool {
+    let expression = Expression::from_str("1d6 + 1d6 * 2").unwrap();
+    let Evaluand {
+        rolls,
+        value,
+        trace,
+        ..
+    } = expression.eval(seed).unwrap();
+    let (first, doubled) = (&trace.operands[0], &trace.operands[1].operands[0]);
+    let (first, doubled) = (&first.rolls[0], &doubled.rolls[0]);
+
+    trace.value == value
+        && IBig::from(first) + IBig::from(doubled) * 2 == value
+        && rolls[&ubig!(6)] == vec![first.clone(), doubled.clone()]
+        && trace.to_string() == format!("1d6 ({}) + 1d6 ({}) * 2 = {}", first, doubled, value)
+}
+
+#[test]
+fn trace_parenthesizes_rolls_before_dice() {
+    let expression = Expression::from_str("1d4d6").unwrap();
+    let Evaluand { trace, .. } = expression.eval(0).unwrap();
+    let count = &trace.operands[0];
+
+    assert_eq!(count.expression.to_string(), "1d4");
+    assert_eq!(count.rolls.len(), 1);
+    assert!(trace
+        .to_string()
+        .starts_with(&format!("(1d4 ({}))d6 (", count.rolls[0])));
+}
+
+#[quickcheck]
+fn replaying_a_trace_rolls_the_same(seed: u64) -> bool {
+    let expression =
+        Expression::from_str("explode(2d6) + draw(2, [1..10]) - uniform(1, 4) + 1d4d6").unwrap();
+    let evaluand = expression.eval(seed).unwrap();
+
+    expression.replay(evaluand.trace.faces()).unwrap() == evaluand
+}
+
+#[quickcheck]
+fn eval_with_rng_matches_eval(seed: u64) -> bool {
+    let expression = Expression::from_str("3d6 + explode(1d4) - uniform(1, 4)").unwrap();
+    let mut rng = <rand::rngs::StdRng as rand::SeedableRng>::seed_from_u64(seed);
+
+    expression.eval_with_rng(&mut rng).unwrap() == expression.eval(seed).unwrap()
+}
+
+#[test]
+fn replay_faces() {
+    let faces = |faces: &[i64]| faces.iter().copied().map(IBig::from).collect::<Vec<_>>();
+    let expression = Expression::from_str("1d20 + 2d6 * 2").unwrap();
+    let Evaluand { value, trace, .. } = expression.replay(faces(&[14, 3, 5])).unwrap();
+
+    assert_eq!(value, IBig::from(30));
+    assert_eq!(trace.to_string(), "1d20 (14) + 2d6 (3, 5) * 2 = 30");
+
+    let error = |faces: Vec<IBig>| expression.replay(faces).unwrap_err().to_string();
+    assert_eq!(
+        error(faces(&[14, 7, 5])),
+        "Face 2, 7, is not a possible roll of 2d6."
+    );
+    assert_eq!(
+        error(faces(&[14, 3])),
+        "Ran out of faces to replay after 2: 2d6 needs another."
+    );
+    assert_eq!(
+        error(faces(&[14, 3, 5, 1])),
+        "1 face was left over after replaying 1d20 + 2d6 * 2."
+    );
+
+    let draw = Expression::from_str("draw(2, [1..3])").unwrap();
+    assert!(draw.replay(faces(&[2, 2])).is_err());
+    assert_eq!(
+        draw.replay(faces(&[2, 3])).unwrap().draws,
+        vec![faces(&[2, 3])]
+    );
+}
+
+#[quickcheck]
+fn max_min(seed: u64) -> bool {
+    let expression = Expression::from_str("max(1d4, 2) - min(3, 1d6)").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
+
+    in_range(&pmf, value, -1, 3)
+        && all_in_range(&rolls, HashSet::from([ubig!(4), ubig!(6)]), (2, 2))
+        && expression.to_string() == "max(1d4, 2) - min(3, 1d6)"
+}
+
+#[quickcheck]
+fn explode(seed: u64) -> bool {
+    let expression = Expression::from_str("explode(2d2)").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
+    let max = 2 * 2 * (functions::MAX_EXPLOSIONS as i64 + 1);
+
+    in_range(&pmf, value, 2, max)
+        && all_in_range(&rolls, HashSet::from([ubig!(2)]), (2, max as usize))
+        && expression.to_string() == "explode(2d2)"
+}
+
+#[test]
+fn explode_distribution() {
+    let expression = Expression::from_str("explode(1d6)").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let p = pmf.iter().map(|outcome| outcome.p).sum::<f64>();
+
+    float_eq::assert_float_eq!(p, 1.0, abs <= 0.000001);
+    // Every multiple of six explodes, except for the last.
+    assert!(pmf.iter().all(|outcome| {
+        outcome.value.clone() % IBig::from(6) != IBig::from(0)
+            || outcome.value == IBig::from(6 * (functions::MAX_EXPLOSIONS + 1))
+    }));
+    float_eq::assert_float_eq!(pmf.expected_value(), 4.2, abs <= 0.000001);
+}
+
+#[quickcheck]
+fn distributions(seed: u64) -> bool {
+    let expression =
+        Expression::from_str("binomial(1d4, 35%) + uniform(-3, 3) * poisson(2.5) - geometric(0.5)")
+            .unwrap();
+    let Evaluand { rolls, value, .. } = expression.eval(seed).unwrap();
+
+    all_in_range(&rolls, HashSet::from([ubig!(4)]), (1, 1))
+        && pmf(&expression)
+            .unwrap()
+            .iter()
+            .any(|outcome| outcome.value == value)
+        && expression.to_string()
+            == "binomial(1d4, 35%) + uniform(-3, 3) * poisson(2.5) - geometric(0.5)"
+}
+
+#[test]
+fn binomial_distribution() {
+    let expression = Expression::from_str("binomial(2d1, 0.5) + 1").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let p = pmf.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+    float_eq::assert_float_eq!(p, vec![0.25, 0.5, 0.25], rmax_all <= 0.000001);
+    float_eq::assert_float_eq!(pmf.expected_value(), 2.0, abs <= 0.000001);
+}
+
+#[test]
+fn uniform_distribution() {
+    let expression = Expression::from_str("uniform(1d2, 3)").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let p = pmf.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+    // Half the time 1..3, otherwise 2..3.
+    float_eq::assert_float_eq!(
+        p,
+        vec![1.0 / 6.0, 5.0 / 12.0, 5.0 / 12.0],
+        rmax_all <= 0.000001
+    );
+}
+
+#[test]
+fn distribution_errors() {
+    assert!(Expression::from_str("binomial(8, 1.5)").is_err());
+    assert!(Expression::from_str("binomial(8)").is_err());
+    assert!(Expression::from_str("geometric(0%)").is_err());
+    assert!(Expression::from_str("poisson(1d4)").is_err());
+    assert!(Expression::from_str("max(0.5)").is_err());
+
+    let expression = Expression::from_str("uniform(3, 1)").unwrap();
+    assert!(pmf(&expression).is_err());
+    assert!(expression.eval(0).is_err());
+
+    let expression = Expression::from_str("binomial(1 - 1d4, 50%)").unwrap();
+    assert!(expression.eval(0).is_err());
+}
+
+#[test]
+fn draw_hypergeometric() {
+    let expression = Expression::from_str("draw(5, [0, 0, 0, 0, 0, 0, 1..4])").unwrap();
+    let pmf = pmf(&expression).unwrap();
+    let p = pmf.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+    // A hand of five zeros, then a hand of four zeros and the one.
+    float_eq::assert_float_eq!(p[0], 6.0 / 252.0, abs <= 0.000001);
+    float_eq::assert_float_eq!(p[1], 15.0 / 252.0, abs <= 0.000001);
+}
+
+#[test]
+fn draw_too_many_cards() {
+    let expression = Expression::from_str("draw(2, [1]) + 1d4").unwrap();
+
+    assert!(pmf(&expression).is_err());
+    assert!(expression.eval(0).is_err());
+}
+
+#[test]
+fn draw_empty_range() {
+    let expression = Expression::from_str("draw(1, [4..1])");
+
+    assert!(expression
+        .unwrap_err()
+        .to_string()
+        .contains("A deck range is empty."));
+}
+
+#[test]
+fn draw_huge_deck() {
+    let expression = Expression::from_str("draw(1, [1..100000000000])");
+
+    assert!(expression
+        .unwrap_err()
+        .to_string()
+        .contains("A deck may hold at most 1000000 cards."));
+}
+
+#[test]
+fn draw_shared_full_deck() {
+    let exact = |text: &str| {
+        Expression::from_str(text)
+            .unwrap()
+            .exact_pmf()
+            .unwrap()
+            .iter()
+            .map(|outcome| (outcome.value.clone(), outcome.p.to_string()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        exact("draw(3, [1..52]) + draw(3, [1..52])"),
+        exact("draw(6, [1..52])")
+    );
+
+    let expression = Expression::from_str("draw(3, [1..52]) - draw(3, [1..52])").unwrap();
+    assert!(pmf(&expression)
+        .unwrap_err()
+        .to_string()
+        .contains("--simulate"));
+}
+
+#[test]
+fn exact_distributions() {
+    let exact = |text: &str| {
+        Expression::from_str(text)
+            .unwrap()
+            .exact_pmf()
+            .unwrap()
+            .iter()
+            .map(|outcome| (outcome.value.to_string(), outcome.p.to_string()))
+            .collect::<Vec<_>>()
+    };
+    let outcomes = |outcomes: &[(&str, &str)]| {
+        outcomes
+            .iter()
+            .map(|(value, p)| (value.to_string(), p.to_string()))
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        exact("2d4 / 3"),
+        outcomes(&[("0", "1/16"), ("1", "9/16"), ("2", "3/8")])
+    );
+    assert_eq!(
+        exact("binomial(2, 50%)"),
+        outcomes(&[("0", "1/4"), ("1", "1/2"), ("2", "1/4")])
+    );
+    assert_eq!(
+        exact("draw(1, [1, 1, 2]) + draw(1, [1, 1, 2])"),
+        outcomes(&[("2", "1/3"), ("3", "2/3")])
+    );
+    assert_eq!(
+        exact("explode(1d2)")[0],
+        (String::from("1"), String::from("1/2"))
+    );
+    assert!(Expression::from_str("geometric(0.5)")
+        .unwrap()
+        .exact_pmf()
+        .is_err());
+}
+
+#[quickcheck]
+fn exact_matches_f64(n: u8, sides: u8) -> bool {
+    let expression = Expression::from_str(&format!("{}d{} - 1d4", n % 4, sides % 12)).unwrap();
+    let exact = expression.exact_pmf().unwrap();
+    let approximate = pmf(&expression).unwrap();
+
+    exact.len() == approximate.len()
+        && exact
+            .iter()
+            .zip(approximate.iter())
+            .all(|(exact, approximate)| {
+                exact.value == approximate.value
+                    && float_eq::float_eq!(exact.p.to_f64(), approximate.p, abs <= 1e-12)
+            })
+}
+
 #[test]
 fn unexpected_end_empty_string() {
     let expression = Expression::from_str("");
@@ -300,6 +622,82 @@ fn unexpected_token_erroneous_character() {
         .contains("Unexpected token at position 8."),);
 }
 
+#[test]
+fn variables_are_substituted() {
+    let expression = Expression::from_str("($n)d6 + $m + $n").unwrap();
+    assert_eq!(expression.variables(), vec!["m", "n"]);
+    assert!(expression.pmf().is_err());
+
+    // A variable before `d` keeps its parentheses, or it would run into the `d`.
+    assert_eq!(expression.to_string(), "($n)d6 + $m + $n");
+    assert_eq!(
+        Expression::from_str(&expression.to_string())
+            .unwrap()
+            .to_string(),
+        expression.to_string()
+    );
+
+    let substituted = expression
+        .substitute("n", &IBig::from(2))
+        .substitute("m", &IBig::from(-1));
+    assert!(substituted.variables().is_empty());
+    assert_eq!(substituted.to_string(), "2d6 + -1 + 2");
+    let expected = pmf(&Expression::from_str("2d6 + 1").unwrap()).unwrap();
+    for (outcome, expected) in pmf(&substituted).unwrap().iter().zip(expected.iter()) {
+        assert_eq!(outcome.value, expected.value);
+        assert!((outcome.p - expected.p).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn double_dice() {
+    let double = |expression: &str| {
+        Expression::from_str(expression)
+            .unwrap()
+            .double_dice()
+            .to_string()
+    };
+
+    assert_eq!(double("1d8 + 4"), "2d8 + 4");
+    assert_eq!(double("d6 + 2d4"), "2d6 + 4d4");
+    assert_eq!(double("max(1d20, 3)"), "max(2d20, 3)");
+    assert_eq!(double("(1d4)d6"), "(2 * 1d4)d6");
+    assert_eq!(double("1d(1d6)"), "2d(1d6)");
+    assert_eq!(double("7"), "7");
+}
+
+#[test]
+fn simulation_estimates_pmf() {
+    let expression = Expression::from_str("2d6").unwrap();
+    let simulation = expression.simulate(20_000, 7).unwrap();
+    let pmf = expression.pmf().unwrap();
+
+    assert_eq!(simulation, expression.simulate(20_000, 7).unwrap());
+    assert_eq!(simulation.samples(), 20_000);
+    for (estimate, outcome) in simulation.pmf().iter().zip(pmf.iter()) {
+        assert_eq!(estimate.value, outcome.value);
+        assert!((estimate.p - outcome.p).abs() < 0.01);
+    }
+}
+
+#[test]
+fn evaluator_matches_pmf() {
+    for expression in [
+        "3d6",
+        "max(1d20, 1d20)",
+        "explode(1d6)",
+        "binomial(10, 30%)",
+        "draw(2, [1..13])",
+    ] {
+        let verification = Expression::from_str(expression)
+            .unwrap()
+            .verify(20_000, 11, 0.001)
+            .unwrap();
+
+        assert!(verification.passed(), "{} {:?}", expression, verification);
+    }
+}
+
 fn all_in_range(
     rolls: &HashMap<UBig, Vec<UBig>>,
     dice: HashSet<UBig>,
@@ -308,7 +706,7 @@ fn all_in_range(
     let min_rolls = n_rolls.0;
     let max_rolls = n_rolls.1;
 
-    let n_rolls = rolls.iter().map(|(_, rolls)| rolls.len()).sum::<usize>();
+    let n_rolls = rolls.values().map(|rolls| rolls.len()).sum::<usize>();
 
     rolls.iter().all(|(sides, rolls)| {
         dice.contains(sides) && rolls.iter().all(|roll| *roll >= ubig!(1) && roll <= sides)
@@ -332,3 +730,45 @@ fn pmf(expression: &Expression) -> Result<Pmf<IBig>, anyhow::Error> {
 
     super::pmf::pmf(expression, &mut combinations)
 }
+
+#[test]
+fn pmf_trees_keep_each_subexpression() {
+    let expression = Expression::from_str("max(2d6, 1d12) + 3").unwrap();
+    let tree = expression.pmf_tree().unwrap();
+
+    let root = pmf(&expression).unwrap();
+    assert_eq!(tree.expression.to_string(), "max(2d6, 1d12) + 3");
+    assert_eq!(tree.pmf.len(), root.len());
+    for (outcome, expected) in tree.pmf.iter().zip(root.iter()) {
+        assert_eq!(outcome.value, expected.value);
+        assert!((outcome.p - expected.p).abs() < 1e-12);
+    }
+
+    let children = |tree: &PmfTree| {
+        tree.operands
+            .iter()
+            .map(|operand| operand.expression.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(children(&tree), vec!["max(2d6, 1d12)", "3"]);
+    assert_eq!(children(&tree.operands[0]), vec!["2d6", "1d12"]);
+    assert_eq!(children(&tree.operands[0].operands[0]), vec!["2", "6"]);
+    assert!((tree.operands[0].operands[0].pmf.expected_value() - 7.0).abs() < 1e-12);
+    assert!(tree.operands[1].operands.is_empty());
+}
+
+#[test]
+fn exact_pmf_trees_match_exact_pmfs() {
+    let expression = Expression::from_str("1d4 * 1d6").unwrap();
+    let tree = expression.exact_pmf_tree().unwrap();
+    let exact = expression.exact_pmf().unwrap();
+
+    let fractions = |pmf: &Pmf<IBig, Ratio>| {
+        pmf.iter()
+            .map(|outcome| outcome.p.to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(fractions(&tree.pmf), fractions(&exact));
+    assert_eq!(fractions(&tree.operands[1].pmf), vec!["1/6"; 6]);
+    assert!(Expression::from_str("$n + 1").unwrap().pmf_tree().is_err());
+}
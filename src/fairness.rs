@@ -0,0 +1,138 @@
+use crate::distributions::{chi_squared_survival, kolmogorov_survival};
+use crate::pmf::Pmf;
+use crate::simulation::Simulation;
+use crate::verification::{self, Bucket};
+use ibig::IBig;
+
+/// Whether observed results, e.g. the rolls of a physical die, fit a distribution, by a
+/// chi-square and a Kolmogorov–Smirnov test. A fair die passes both, but passing does not prove
+/// a die is fair; too few results may hide a small bias.
+#[derive(Clone, Debug)]
+pub struct Fairness {
+    pub results: u64,
+    /// The significance level both tests and each bucket are tested at.
+    pub alpha: f64,
+    pub chi_squared: f64,
+    pub degrees_of_freedom: usize,
+    pub chi_squared_p_value: f64,
+    /// The largest difference between the observed and expected P(X <= x).
+    pub kolmogorov_smirnov: f64,
+    /// Conservative for discrete distributions: the true p-value is no larger.
+    pub kolmogorov_smirnov_p_value: f64,
+    pub buckets: Vec<Bucket>,
+    /// Results the distribution says are impossible, with their counts.
+    pub impossible: Vec<(IBig, u64)>,
+}
+
+impl Fairness {
+    /// Tests the `observed` results against `pmf` at the significance level `alpha`.
+    pub fn new(observed: &Simulation, pmf: &Pmf<IBig>, alpha: f64) -> Self {
+        let results = observed.samples();
+        let buckets = verification::buckets(observed, pmf, alpha);
+        let impossible = verification::impossible(observed, pmf);
+
+        let chi_squared = buckets
+            .iter()
+            .map(|bucket| (bucket.observed as f64 - bucket.expected).powi(2) / bucket.expected)
+            .sum::<f64>();
+        let degrees_of_freedom = buckets.len().saturating_sub(1);
+        let chi_squared_p_value = match (impossible.is_empty(), degrees_of_freedom) {
+            (false, _) => 0.0,
+            (true, 0) => 1.0,
+            (true, degrees_of_freedom) => {
+                chi_squared_survival(chi_squared, degrees_of_freedom as f64)
+            }
+        };
+
+        let kolmogorov_smirnov = kolmogorov_smirnov(observed, pmf);
+        let n = (results as f64).sqrt();
+        let kolmogorov_smirnov_p_value = match impossible.is_empty() {
+            true => kolmogorov_survival((n + 0.12 + 0.11 / n) * kolmogorov_smirnov),
+            false => 0.0,
+        };
+
+        Fairness {
+            results,
+            alpha,
+            chi_squared,
+            degrees_of_freedom,
+            chi_squared_p_value,
+            kolmogorov_smirnov,
+            kolmogorov_smirnov_p_value,
+            buckets,
+            impossible,
+        }
+    }
+
+    /// Whether neither test finds a significant deviation from the distribution.
+    pub fn fair(&self) -> bool {
+        self.chi_squared_p_value >= self.alpha
+            && self.kolmogorov_smirnov_p_value >= self.alpha
+            && self.impossible.is_empty()
+    }
+}
+
+// The largest difference between the observed and expected cumulative distributions, which
+// can only change at outcomes either of them has.
+fn kolmogorov_smirnov(observed: &Simulation, pmf: &Pmf<IBig>) -> f64 {
+    let n = observed.samples() as f64;
+    let mut values = pmf
+        .iter()
+        .map(|outcome| outcome.value.clone())
+        .chain(observed.counts().map(|(value, _)| value.clone()))
+        .collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+
+    let (mut observed_cdf, mut expected_cdf, mut distance) = (0.0, 0.0, 0.0_f64);
+    let mut counts = observed.counts().peekable();
+    let mut outcomes = pmf.iter().peekable();
+    for value in values.iter() {
+        if let Some((_, count)) = counts.next_if(|(observed, _)| *observed == value) {
+            observed_cdf += count as f64 / n;
+        }
+        if let Some(outcome) = outcomes.next_if(|outcome| &outcome.value == value) {
+            expected_cdf += outcome.p;
+        }
+
+        distance = distance.max((observed_cdf - expected_cdf).abs());
+    }
+
+    distance
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+
+    fn d20() -> Pmf<IBig> {
+        (1..=20).map(|side| (0.05, IBig::from(side))).collect()
+    }
+
+    #[test]
+    fn fair_die() {
+        let results = (0..2000).map(|i| IBig::from(i % 20 + 1));
+        let fairness = Fairness::new(&Simulation::from_samples(results), &d20(), 0.05);
+
+        assert!(fairness.fair());
+        assert_float_eq!(fairness.chi_squared, 0.0, abs <= 1e-9);
+        assert_eq!(fairness.degrees_of_freedom, 19);
+        assert!(fairness.kolmogorov_smirnov < 1e-9);
+    }
+
+    #[test]
+    fn die_that_rolls_high() {
+        // Every roll below 6 is rerolled as a 20, so 20 comes up 6 times as often.
+        let results = (0..2000).map(|i| match i % 20 + 1 {
+            roll if roll < 6 => IBig::from(20),
+            roll => IBig::from(roll),
+        });
+        let fairness = Fairness::new(&Simulation::from_samples(results), &d20(), 0.05);
+
+        assert!(!fairness.fair());
+        assert!(fairness.chi_squared_p_value < 1e-9);
+        assert_float_eq!(fairness.kolmogorov_smirnov, 0.25, abs <= 1e-9);
+        assert!(fairness.kolmogorov_smirnov_p_value < 1e-9);
+    }
+}
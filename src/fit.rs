@@ -0,0 +1,206 @@
+use crate::expression::Expression;
+use crate::pmf::Pmf;
+use ibig::IBig;
+
+/// The dice searched by default, those of a standard set plus a d2 and d3.
+pub const SIDES: [u32; 9] = [2, 3, 4, 6, 8, 10, 12, 20, 100];
+
+/// What a fit aims for: some statistics, or the outcomes of a distribution.
+#[derive(Clone, Debug)]
+pub enum Target {
+    /// A mean or a standard deviation, or both.
+    Statistics {
+        mean: Option<f64>,
+        standard_deviation: Option<f64>,
+    },
+    /// A distribution, e.g. the results of a die, which candidates are compared with by total
+    /// variation distance.
+    Pmf(Pmf<IBig>),
+}
+
+/// The expressions a fit searches: sums of up to `max_dice` dice of the same number of sides,
+/// the higher or lower of two such sums, and sums of two kinds of dice, each plus a constant.
+///
+/// Keeping the highest or lowest dice of a roll, e.g. the highest 3 of 4d6, is not searched
+/// beyond the higher or lower of two sums, since expressions have no operator to keep dice.
+#[derive(Clone, Debug)]
+pub struct Space {
+    pub max_dice: u32,
+    pub sides: Vec<u32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub expression: Expression,
+    pub pmf: Pmf<IBig>,
+    /// How far the candidate is from the target, lower is better.
+    pub distance: f64,
+}
+
+impl Default for Space {
+    fn default() -> Self {
+        Self {
+            max_dice: 6,
+            sides: SIDES.to_vec(),
+        }
+    }
+}
+
+impl Space {
+    // The expressions of the space without a constant, from the simplest.
+    fn expressions(&self) -> Vec<Expression> {
+        let mut expressions = vec![];
+
+        for n in 1..=self.max_dice {
+            for sides in self.sides.iter() {
+                expressions.push(Expression::dice(n, *sides));
+            }
+        }
+        for n in 1..=self.max_dice {
+            for sides in self.sides.iter() {
+                let pair = || vec![Expression::dice(n, *sides), Expression::dice(n, *sides)];
+                expressions.push(Expression::max(pair()));
+                expressions.push(Expression::min(pair()));
+            }
+        }
+        for (i, left) in self.sides.iter().enumerate() {
+            for right in self.sides[i + 1..].iter() {
+                for n in 1..self.max_dice {
+                    for m in 1..=self.max_dice - n {
+                        expressions.push(Expression::sum(
+                            Expression::dice(n, *left),
+                            Expression::dice(m, *right),
+                        ));
+                    }
+                }
+            }
+        }
+
+        expressions
+    }
+}
+
+/// The `count` candidates of `space` nearest `target`, nearest first. Candidates that are as near
+/// as each other keep the order of `Space`, so simpler expressions come first.
+pub fn fit(target: &Target, space: &Space, count: usize) -> Result<Vec<Candidate>, anyhow::Error> {
+    let mut candidates = vec![];
+
+    for expression in space.expressions() {
+        let base = expression.pmf()?;
+
+        // The constant that moves the mean nearest the target's, and for distributions its
+        // neighbours, which may overlap it better.
+        let (shift, spread) = match target {
+            Target::Statistics {
+                mean: Some(mean), ..
+            } => ((mean - base.expected_value()).round(), 0),
+            Target::Statistics { mean: None, .. } => (0.0, 0),
+            Target::Pmf(pmf) => ((pmf.expected_value() - base.expected_value()).round(), 1),
+        };
+
+        for offset in -spread..=spread {
+            let constant = IBig::from(shift as i64 + offset);
+            let pmf = base.clone().map(|value| value + &constant);
+
+            candidates.push(Candidate {
+                distance: distance(target, &pmf),
+                expression: expression.clone().offset(&constant),
+                pmf,
+            });
+        }
+    }
+
+    candidates.sort_by(|left, right| left.distance.total_cmp(&right.distance));
+    candidates.truncate(count);
+
+    Ok(candidates)
+}
+
+/// How far `pmf` is from `target`: the Euclidean distance between the statistics, or the total
+/// variation distance between the distributions.
+pub fn distance(target: &Target, pmf: &Pmf<IBig>) -> f64 {
+    match target {
+        Target::Statistics {
+            mean,
+            standard_deviation,
+        } => {
+            let mean = mean.map(|mean| mean - pmf.expected_value()).unwrap_or(0.0);
+            let standard_deviation = standard_deviation
+                .map(|standard_deviation| standard_deviation - pmf.standard_deviation())
+                .unwrap_or(0.0);
+
+            (mean * mean + standard_deviation * standard_deviation).sqrt()
+        }
+        Target::Pmf(target) => target.total_variation(pmf),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    fn space() -> Space {
+        Space {
+            max_dice: 4,
+            sides: vec![4, 6, 8, 20],
+        }
+    }
+
+    #[test]
+    fn statistics() {
+        let target = Target::Statistics {
+            mean: Some(10.5),
+            standard_deviation: Some(5.766),
+        };
+        let candidates = fit(&target, &space(), 3).unwrap();
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].expression.to_string(), "1d20");
+        assert!(candidates[0].distance < 0.01);
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].distance <= pair[1].distance));
+    }
+
+    #[test]
+    fn constants() {
+        let target = Target::Statistics {
+            mean: Some(7.5),
+            standard_deviation: Some(1.118),
+        };
+        let candidates = fit(&target, &space(), 1).unwrap();
+
+        assert_eq!(candidates[0].expression.to_string(), "1d4 + 5");
+    }
+
+    #[test]
+    fn expressions() {
+        let expressions = space()
+            .expressions()
+            .iter()
+            .map(Expression::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(expressions[0], "1d4");
+        assert!(expressions.contains(&String::from("min(3d8, 3d8)")));
+        assert!(expressions.contains(&String::from("1d6 + 3d20")));
+        assert_eq!(
+            Expression::dice(2, 6).offset(&IBig::from(-3)).to_string(),
+            "2d6 - 3"
+        );
+    }
+
+    #[test]
+    fn distributions() {
+        let target = Expression::from_str("max(1d20, 1d20) - 2")
+            .unwrap()
+            .pmf()
+            .unwrap();
+        let candidates = fit(&Target::Pmf(target), &space(), 2).unwrap();
+
+        assert_eq!(candidates[0].expression.to_string(), "max(1d20, 1d20) - 2");
+        assert!(candidates[0].distance < 1e-9);
+        assert!(candidates[1].distance > 0.01);
+    }
+}
@@ -89,10 +89,7 @@ where
                 self.current_left = self.left.next();
                 self.right = self.original_right.clone();
 
-                match self.right.next() {
-                    None => return None,
-                    Some(right) => right,
-                }
+                self.right.next()?
             }
         };
 
@@ -1,4 +1,12 @@
+pub mod attack;
 pub mod combinatorics;
+pub mod distributions;
 pub mod expression;
+pub mod fairness;
+pub mod fit;
 pub mod iter_func;
 pub mod pmf;
+pub mod probability;
+pub mod query;
+pub mod simulation;
+pub mod verification;
@@ -1,36 +1,64 @@
 use crate::iter_func::IterFunc;
-use ibig::IBig;
+use crate::probability::Probability;
+use ibig::{IBig, UBig};
 use std::cmp::Ord;
 
+/// A probability mass function. Probabilities are `f64`s unless they are exact, see
+/// `probability::Ratio`.
 #[derive(Clone, Debug)]
-pub struct Pmf<A> {
-    values: Vec<Outcome<A>>,
+pub struct Pmf<A, P = f64> {
+    values: Vec<Outcome<A, P>>,
 }
 
 #[derive(Clone, Debug)]
-pub struct Outcome<A> {
-    pub p: f64,
+pub struct Outcome<A, P = f64> {
+    pub p: P,
     pub value: A,
 }
 
 #[derive(Clone, Debug)]
-pub struct PmfIterator<'a, A> {
-    slice_iter: std::slice::Iter<'a, Outcome<A>>,
+pub struct PmfIterator<'a, A, P = f64> {
+    slice_iter: std::slice::Iter<'a, Outcome<A, P>>,
 }
 
-impl<A> Pmf<A>
+/// A range of consecutive outcomes, `min..=max`, of a binned distribution. See `Pmf::bin`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Bin {
+    pub min: IBig,
+    pub max: IBig,
+    /// The negligible tail the bin collapses, if it collapses one.
+    pub tail: Option<Tail>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tail {
+    Below,
+    Above,
+}
+
+/// How wide the bins of a distribution are.
+#[derive(Clone, Debug)]
+pub enum Binning {
+    /// At most this many bins of equal width.
+    Count(usize),
+    /// Bins this many outcomes wide.
+    Width(UBig),
+}
+
+impl<A, P> Pmf<A, P>
 where
     A: Ord,
+    P: Probability,
 {
     pub fn constant(value: A) -> Self {
         Self {
-            values: vec![Outcome { value, p: 1.0 }],
+            values: vec![Outcome { value, p: P::one() }],
         }
     }
 
     pub fn from_mass_function<I>(iter: I) -> Self
     where
-        I: IntoIterator<Item = (f64, A)>,
+        I: IntoIterator<Item = (P, A)>,
     {
         let values = iter
             .into_iter()
@@ -39,11 +67,13 @@ where
             .group_by(
                 |outcome| &outcome.value,
                 |outcome, values| Outcome {
-                    p: values.iter().fold(outcome.p, |left, right| left + right.p),
+                    p: values
+                        .into_iter()
+                        .fold(outcome.p, |left, right| left + right.p),
                     value: outcome.value,
                 },
             )
-            .collect::<Vec<Outcome<A>>>();
+            .collect::<Vec<Outcome<A, P>>>();
 
         let mut pmf = Self { values };
         pmf.normalize();
@@ -51,7 +81,7 @@ where
         pmf
     }
 
-    pub fn iter(&self) -> PmfIterator<A> {
+    pub fn iter(&self) -> PmfIterator<'_, A, P> {
         PmfIterator {
             slice_iter: self.values.iter(),
         }
@@ -65,7 +95,7 @@ where
         self.values.is_empty()
     }
 
-    pub fn map<F, B>(&mut self, f: F) -> Pmf<B>
+    pub fn map<F, B>(&mut self, f: F) -> Pmf<B, P>
     where
         F: Fn(&A) -> B,
         B: Ord,
@@ -75,7 +105,7 @@ where
             .iter()
             .map(|Outcome { value, p }| Outcome {
                 value: f(value),
-                p: *p,
+                p: p.clone(),
             })
             .sorted_by(|left, right| left.value.cmp(&right.value))
             .collect();
@@ -83,59 +113,411 @@ where
         Pmf { values: new_values }
     }
 
-    pub fn cartesian_product<F, B, C>(&self, right: &Pmf<B>, f: F) -> Pmf<C>
+    pub fn cartesian_product<F, B, C>(&self, right: &Pmf<B, P>, f: F) -> Pmf<C, P>
     where
         F: Fn(&A, &B) -> C,
         B: Clone + Ord,
-        Pmf<C>: std::iter::FromIterator<(f64, C)>,
+        C: Ord,
     {
         self.iter()
-            .cartesian_product_by(right.iter(), |l, r| (l.p * r.p, f(&l.value, &r.value)))
+            .cartesian_product_by(right.iter(), |l, r| {
+                (l.p.clone() * r.p.clone(), f(&l.value, &r.value))
+            })
+            .collect()
+    }
+
+    /// The probability of rolling any outcome that satisfies `predicate`.
+    pub fn probability_where<F>(&self, predicate: F) -> P
+    where
+        F: Fn(&A) -> bool,
+    {
+        let p = self
+            .values
+            .iter()
+            .filter(|outcome| predicate(&outcome.value))
+            .fold(P::zero(), |p, outcome| p + outcome.p.clone());
+
+        at_most_one(p)
+    }
+
+    /// The probability of each outcome or any lower outcome, P(X <= x), in order.
+    pub fn cdf(&self) -> Vec<P> {
+        self.values
+            .iter()
+            .scan(P::zero(), |cumulative, outcome| {
+                *cumulative = cumulative.clone() + outcome.p.clone();
+                Some(at_most_one(cumulative.clone()))
+            })
             .collect()
     }
 
+    /// The probability of each outcome or any higher outcome, P(X >= x), in order. It is summed
+    /// from the highest outcome so small tails keep their precision.
+    pub fn at_least(&self) -> Vec<P> {
+        let mut at_least = self
+            .values
+            .iter()
+            .rev()
+            .scan(P::zero(), |cumulative, outcome| {
+                *cumulative = cumulative.clone() + outcome.p.clone();
+                Some(at_most_one(cumulative.clone()))
+            })
+            .collect::<Vec<_>>();
+        at_least.reverse();
+
+        at_least
+    }
+
+    /// The smallest outcome `x` where P(X <= x) >= `q`. `q` is clamped to [0, 1].
+    pub fn quantile(&self, q: f64) -> Option<&A> {
+        // Tolerates rounding in the cumulative sum, e.g. 0.49999999999999994 for a median.
+        let q = q.clamp(0.0, 1.0) - 1e-12;
+
+        self.iter()
+            .zip(self.cdf())
+            .find(|(_, cumulative)| cumulative.to_f64() >= q)
+            .or_else(|| self.iter().zip(self.cdf()).last())
+            .map(|(outcome, _)| &outcome.value)
+    }
+
+    pub fn median(&self) -> Option<&A> {
+        self.quantile(0.5)
+    }
+
     fn normalize(&mut self) {
-        let normalizing_constant: f64 = self.values.iter().map(|Outcome { p, .. }| p).sum();
+        let normalizing_constant = self
+            .values
+            .iter()
+            .fold(P::zero(), |sum, outcome| sum + outcome.p.clone());
 
-        if normalizing_constant == 0.0 && !self.values.is_empty() {
+        if normalizing_constant.is_zero() && !self.values.is_empty() {
             self.values = vec![];
-        } else if normalizing_constant != 1.0 {
+        } else if !normalizing_constant.is_one() {
             self.values
                 .iter_mut()
-                .map(|outcome| outcome.p /= normalizing_constant)
+                .map(|outcome| outcome.p = outcome.p.clone() / normalizing_constant.clone())
                 .for_each(drop)
         }
     }
 }
 
+impl<A> Pmf<A>
+where
+    A: Ord,
+{
+    /// The most likely outcomes, in order. Outcomes within rounding error of the most likely
+    /// outcome are also modes.
+    pub fn modes(&self) -> Vec<&A> {
+        let max_p = self
+            .values
+            .iter()
+            .map(|outcome| outcome.p)
+            .fold(0.0, f64::max);
+
+        self.values
+            .iter()
+            .filter(|outcome| outcome.p >= max_p * (1.0 - 1e-9))
+            .map(|outcome| &outcome.value)
+            .collect()
+    }
+
+    /// The Shannon entropy, in bits.
+    pub fn entropy(&self) -> f64 {
+        self.values
+            .iter()
+            .filter(|outcome| outcome.p > 0.0)
+            .map(|outcome| outcome.p * (1.0 / outcome.p).log2())
+            .sum::<f64>()
+    }
+
+    /// The probability that an outcome of this distribution exceeds an independent outcome of
+    /// `other`, P(A > B).
+    pub fn probability_greater(&self, other: &Self) -> f64 {
+        let mut others = other.values.iter().peekable();
+        let mut below = 0.0;
+        let mut p = 0.0;
+
+        for outcome in self.values.iter() {
+            while let Some(lower) = others.next_if(|lower| lower.value < outcome.value) {
+                below += lower.p;
+            }
+
+            p += outcome.p * below;
+        }
+
+        f64::min(p, 1.0)
+    }
+
+    /// The largest difference between the probabilities the distributions assign to any event.
+    pub fn total_variation(&self, other: &Self) -> f64 {
+        let distance = self
+            .aligned(other)
+            .into_iter()
+            .map(|(p, q)| (p - q).abs())
+            .sum::<f64>()
+            / 2.0;
+
+        f64::min(distance, 1.0)
+    }
+
+    /// The Kullback–Leibler divergence of `other` from this distribution, in bits. It is
+    /// infinite when this distribution has outcomes that `other` does not.
+    pub fn kl_divergence(&self, other: &Self) -> f64 {
+        let divergence = self
+            .aligned(other)
+            .into_iter()
+            .filter(|(p, _)| *p > 0.0)
+            .map(|(p, q)| p * (p / q).log2())
+            .sum::<f64>();
+
+        f64::max(divergence, 0.0)
+    }
+
+    // The probability of every outcome of either distribution in both distributions, in order.
+    fn aligned(&self, other: &Self) -> Vec<(f64, f64)> {
+        let mut left = self.values.iter().peekable();
+        let mut right = other.values.iter().peekable();
+        let mut aligned = vec![];
+
+        loop {
+            let pair = match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => match l.value.cmp(&r.value) {
+                    std::cmp::Ordering::Less => (left.next().unwrap().p, 0.0),
+                    std::cmp::Ordering::Greater => (0.0, right.next().unwrap().p),
+                    std::cmp::Ordering::Equal => (left.next().unwrap().p, right.next().unwrap().p),
+                },
+                (Some(_), None) => (left.next().unwrap().p, 0.0),
+                (None, Some(_)) => (0.0, right.next().unwrap().p),
+                (None, None) => return aligned,
+            };
+
+            aligned.push(pair);
+        }
+    }
+}
+
+impl<A, P> Pmf<A, P>
+where
+    A: Clone + Ord,
+    P: Probability,
+{
+    /// The distribution of the outcomes that satisfy `predicate`, i.e. P(X = x | predicate(X)).
+    /// The distribution is empty when no outcome satisfies `predicate`.
+    pub fn condition<F>(&self, predicate: F) -> Self
+    where
+        F: Fn(&A) -> bool,
+    {
+        self.iter()
+            .filter(|outcome| predicate(&outcome.value))
+            .map(|outcome| (outcome.p.clone(), outcome.value.clone()))
+            .collect()
+    }
+
+    /// The distribution conditioned on its outcomes falling in `range`.
+    pub fn truncate<R>(&self, range: R) -> Self
+    where
+        R: std::ops::RangeBounds<A>,
+    {
+        self.condition(|value| range.contains(value))
+    }
+
+    /// Picks one of the `components` at random, in proportion to its non-negative weight, then
+    /// takes its outcome.
+    pub fn mixture(components: &[(P, Pmf<A, P>)]) -> Self {
+        components
+            .iter()
+            .flat_map(|(weight, pmf)| {
+                pmf.iter()
+                    .map(move |outcome| (weight.clone() * outcome.p.clone(), outcome.value.clone()))
+            })
+            .collect()
+    }
+
+    /// The same distribution with the nearest `f64` to each probability.
+    pub fn to_f64(&self) -> Pmf<A> {
+        Pmf {
+            values: self
+                .values
+                .iter()
+                .map(|outcome| Outcome {
+                    p: outcome.p.to_f64(),
+                    value: outcome.value.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<P> Pmf<IBig, P>
+where
+    P: Probability,
+{
+    /// The distribution of the sum of `k` independent outcomes.
+    pub fn convolve_n(&self, k: usize) -> Self {
+        let mut sum = Pmf::constant(IBig::from(0u8));
+        let mut power = self.clone();
+        let mut k = k;
+
+        // Sums by repeated doubling, so only a logarithmic number of convolutions are needed.
+        while k > 0 {
+            if k % 2 == 1 {
+                sum = sum.cartesian_product(&power, |l, r| l + r);
+            }
+
+            k /= 2;
+            if k > 0 {
+                power = power.cartesian_product(&power, |l, r| l + r);
+            }
+        }
+
+        sum
+    }
+
+    /// Groups the outcomes into bins, starting from the smallest outcome. The outcomes at either
+    /// end whose total probability is at most `tail` are each collapsed into one bin instead, and
+    /// the others are binned by `binning`. Bins without any outcomes are left out.
+    pub fn bin(&self, binning: &Binning, tail: f64) -> Pmf<Bin, P> {
+        let values = &self.values;
+        if values.is_empty() {
+            return Pmf { values: vec![] };
+        }
+
+        let tail_len = |outcomes: &mut dyn Iterator<Item = &Outcome<IBig, P>>| {
+            outcomes
+                .scan(P::zero(), |sum, outcome| {
+                    *sum = sum.clone() + outcome.p.clone();
+                    Some(sum.to_f64())
+                })
+                .take_while(|p| *p <= tail)
+                .count()
+        };
+        // At least one outcome is left between the tails.
+        let below = tail_len(&mut values.iter()).min(values.len() - 1);
+        let above = tail_len(&mut values.iter().rev()).min(values.len() - below - 1);
+        let body = &values[below..values.len() - above];
+
+        let (first, last) = (&body[0].value, &body[body.len() - 1].value);
+        let width = match binning {
+            Binning::Count(count) => {
+                let count = IBig::from(*count.max(&1));
+                (last - first + &count) / count
+            }
+            Binning::Width(width) => IBig::from(width.clone()),
+        }
+        .max(IBig::from(1u8));
+
+        let collapse = |outcomes: &[Outcome<IBig, P>], tail| Outcome {
+            value: Bin {
+                min: outcomes[0].value.clone(),
+                max: outcomes[outcomes.len() - 1].value.clone(),
+                tail: Some(tail),
+            },
+            p: outcomes
+                .iter()
+                .fold(P::zero(), |sum, outcome| sum + outcome.p.clone()),
+        };
+
+        let mut bins = Vec::new();
+        if below > 0 {
+            bins.push(collapse(&values[..below], Tail::Below));
+        }
+        for outcome in body {
+            let min = first + (&outcome.value - first) / &width * &width;
+            match bins.last_mut() {
+                Some(bin) if bin.value.min == min => bin.p = bin.p.clone() + outcome.p.clone(),
+                _ => bins.push(Outcome {
+                    value: Bin {
+                        max: (&min + &width - IBig::from(1u8)).min(last.clone()),
+                        min,
+                        tail: None,
+                    },
+                    p: outcome.p.clone(),
+                }),
+            }
+        }
+        if above > 0 {
+            bins.push(collapse(&values[values.len() - above..], Tail::Above));
+        }
+
+        Pmf { values: bins }
+    }
+}
+
 impl Pmf<IBig> {
     pub fn expected_value(&self) -> f64 {
         self.values.iter().fold(0.0, |mean, outcome| {
             mean + outcome.value.to_f64() * outcome.p
         })
     }
+
+    pub fn variance(&self) -> f64 {
+        self.central_moment(2)
+    }
+
+    pub fn standard_deviation(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// The third standardized moment. Constant distributions have no skewness.
+    pub fn skewness(&self) -> Option<f64> {
+        self.standardized_moment(3)
+    }
+
+    /// The excess kurtosis, i.e. the fourth standardized moment less the normal distribution's
+    /// kurtosis of 3. Constant distributions have no kurtosis.
+    pub fn kurtosis(&self) -> Option<f64> {
+        self.standardized_moment(4).map(|kurtosis| kurtosis - 3.0)
+    }
+
+    fn central_moment(&self, k: i32) -> f64 {
+        let mean = self.expected_value();
+
+        self.values.iter().fold(0.0, |moment, outcome| {
+            moment + (outcome.value.to_f64() - mean).powi(k) * outcome.p
+        })
+    }
+
+    fn standardized_moment(&self, k: i32) -> Option<f64> {
+        let variance = self.variance();
+
+        if variance <= 0.0 {
+            return None;
+        }
+
+        Some(self.central_moment(k) / variance.powf(f64::from(k) / 2.0))
+    }
 }
 
-impl<'a, A> Iterator for PmfIterator<'a, A> {
-    type Item = &'a Outcome<A>;
+impl<'a, A, P> Iterator for PmfIterator<'a, A, P> {
+    type Item = &'a Outcome<A, P>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.slice_iter.next()
     }
 }
 
-impl<A> FromIterator<(f64, A)> for Pmf<A>
+impl<A, P> FromIterator<(P, A)> for Pmf<A, P>
 where
     A: Ord,
+    P: Probability,
 {
     fn from_iter<T>(iter: T) -> Self
     where
-        T: IntoIterator<Item = (f64, A)>,
+        T: IntoIterator<Item = (P, A)>,
     {
         Pmf::from_mass_function(iter)
     }
 }
 
+// Rounding error can push sums of probabilities above one.
+fn at_most_one<P: Probability>(p: P) -> P {
+    if p > P::one() {
+        P::one()
+    } else {
+        p
+    }
+}
+
 #[cfg(test)]
 impl<A> quickcheck::Arbitrary for Pmf<A>
 where
@@ -161,7 +543,7 @@ mod test {
     fn mapping_over_constant(n: i8) -> bool {
         let n = Into::<i16>::into(n);
 
-        let pmf = Pmf::constant(n).map(|n| -n);
+        let pmf = Pmf::<_>::constant(n).map(|n| -n);
         let outcomes = pmf.iter().collect::<Vec<_>>();
 
         outcomes.len() == 1 && outcomes[0].value == -n
@@ -190,6 +572,238 @@ mod test {
         TestResult::from_bool(float_eq::float_eq!(p, 1.0, abs <= 0.000001))
     }
 
+    #[quickcheck]
+    fn cdf_complements_at_least(a: Pmf<u8>) -> bool {
+        let p = a.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+        a.cdf()
+            .iter()
+            .zip(a.at_least().iter())
+            .zip(p.iter())
+            .all(|((at_most, at_least), p)| {
+                float_eq::float_eq!(at_most + at_least - p, 1.0, abs <= 0.000001)
+            })
+    }
+
+    #[quickcheck]
+    fn probability_where_partitions(a: Pmf<u8>, n: u8) -> TestResult {
+        if a.is_empty() {
+            return TestResult::discard();
+        }
+
+        let below = a.probability_where(|value| *value < n);
+        let rest = a.probability_where(|value| *value >= n);
+
+        TestResult::from_bool(float_eq::float_eq!(below + rest, 1.0, abs <= 0.000001))
+    }
+
+    #[test]
+    fn at_least_two_dee_six() {
+        let pmf = (2..=12_i8)
+            .map(|sum| (f64::from(6 - (sum - 7).abs()), sum))
+            .collect::<Pmf<_>>();
+
+        float_eq::assert_float_eq!(pmf.at_least()[8], 6.0 / 36.0, abs <= 0.000001);
+        float_eq::assert_float_eq!(pmf.cdf()[8], 33.0 / 36.0, abs <= 0.000001);
+    }
+
+    #[quickcheck]
+    fn quantiles_are_monotonic(a: Pmf<u8>, q: u8, r: u8) -> TestResult {
+        if a.is_empty() {
+            return TestResult::discard();
+        }
+
+        let (q, r) = (f64::from(q.min(r)) / 255.0, f64::from(q.max(r)) / 255.0);
+
+        TestResult::from_bool(a.quantile(q) <= a.quantile(r))
+    }
+
+    #[quickcheck]
+    fn variance_is_shift_invariant(mut a: Pmf<i8>, shift: i8) -> TestResult {
+        if a.is_empty() {
+            return TestResult::discard();
+        }
+
+        let mut a = a.map(|value| IBig::from(*value));
+        let shifted = a.map(|value| value + IBig::from(shift));
+
+        TestResult::from_bool(float_eq::float_eq!(
+            a.variance(),
+            shifted.variance(),
+            abs <= 0.0001
+        ))
+    }
+
+    #[test]
+    fn two_dee_six_statistics() {
+        let pmf = (2..=12_i8)
+            .map(|sum| (f64::from(6 - (sum - 7).abs()), ibig!(1) * sum))
+            .collect::<Pmf<_>>();
+
+        float_eq::assert_float_eq!(pmf.variance(), 35.0 / 6.0, abs <= 0.000001);
+        float_eq::assert_float_eq!(pmf.standard_deviation(), 2.415229, abs <= 0.000001);
+        float_eq::assert_float_eq!(pmf.skewness().unwrap(), 0.0, abs <= 0.000001);
+        float_eq::assert_float_eq!(pmf.kurtosis().unwrap(), -0.634286, abs <= 0.000001);
+        float_eq::assert_float_eq!(pmf.entropy(), 3.274401, abs <= 0.000001);
+        assert_eq!(pmf.median(), Some(&ibig!(7)));
+        assert_eq!(pmf.quantile(0.9), Some(&ibig!(10)));
+        assert_eq!(pmf.modes(), vec![&ibig!(7)]);
+    }
+
+    #[test]
+    fn constant_statistics() {
+        let pmf = Pmf::constant(ibig!(4));
+
+        assert_eq!(pmf.variance(), 0.0);
+        assert_eq!(pmf.skewness(), None);
+        assert_eq!(pmf.kurtosis(), None);
+        assert_eq!(pmf.entropy(), 0.0);
+        assert_eq!(pmf.quantile(0.0), Some(&ibig!(4)));
+    }
+
+    #[quickcheck]
+    fn comparisons_partition(a: Pmf<u8>, b: Pmf<u8>) -> TestResult {
+        if a.is_empty() || b.is_empty() {
+            return TestResult::discard();
+        }
+
+        let equal = a
+            .iter()
+            .map(|l| l.p * b.probability_where(|value| *value == l.value))
+            .sum::<f64>();
+        let p = a.probability_greater(&b) + b.probability_greater(&a) + equal;
+
+        TestResult::from_bool(float_eq::float_eq!(p, 1.0, abs <= 0.000001))
+    }
+
+    #[quickcheck]
+    fn distances_from_itself(a: Pmf<u8>) -> bool {
+        a.total_variation(&a) == 0.0 && float_eq::float_eq!(a.kl_divergence(&a), 0.0, abs <= 1e-9)
+    }
+
+    #[test]
+    fn coin_comparisons() {
+        let fair = [(1.0, 0), (1.0, 1)].into_iter().collect::<Pmf<_>>();
+        let biased = [(1.0, 0), (3.0, 1)].into_iter().collect::<Pmf<_>>();
+        let heads = Pmf::constant(1);
+
+        float_eq::assert_float_eq!(biased.probability_greater(&fair), 0.375, abs <= 0.000001);
+        float_eq::assert_float_eq!(fair.total_variation(&biased), 0.25, abs <= 0.000001);
+        float_eq::assert_float_eq!(fair.kl_divergence(&biased), 0.207519, abs <= 0.000001);
+        float_eq::assert_float_eq!(heads.kl_divergence(&fair), 1.0, abs <= 0.000001);
+        assert_eq!(fair.kl_divergence(&heads), f64::INFINITY);
+    }
+
+    #[quickcheck]
+    fn conditioning_partitions(a: Pmf<u8>, n: u8) -> TestResult {
+        let below = a.condition(|value| *value < n);
+        let rest = a.truncate(n..);
+        let p = a.probability_where(|value| *value < n);
+
+        if below.is_empty() || rest.is_empty() {
+            return TestResult::discard();
+        }
+
+        let mixture = Pmf::mixture(&[(p, below), (1.0 - p, rest)]);
+
+        TestResult::from_bool(
+            mixture.len() == a.len()
+                && mixture
+                    .iter()
+                    .zip(a.iter())
+                    .all(|(l, r)| l.value == r.value && float_eq::float_eq!(l.p, r.p, abs <= 1e-9)),
+        )
+    }
+
+    #[test]
+    fn conditioning_on_nothing_is_empty() {
+        let pmf = (1..=6_u8).map(|n| (1.0, n)).collect::<Pmf<_>>();
+
+        assert!(pmf.condition(|value| *value > 6).is_empty());
+        assert_eq!(
+            pmf.truncate(3..=4).iter().map(|o| o.p).collect::<Vec<_>>(),
+            [0.5, 0.5]
+        );
+    }
+
+    #[quickcheck]
+    fn convolve_n_matches_repeated_sums(mut a: Pmf<i8>, k: u8) -> TestResult {
+        if a.is_empty() || a.len() > 8 {
+            return TestResult::discard();
+        }
+
+        let k = usize::from(k % 6);
+        let a = a.map(|value| IBig::from(*value));
+        let expected = (0..k).fold(Pmf::constant(IBig::from(0)), |sum, _| {
+            sum.cartesian_product(&a, |l, r| l + r)
+        });
+        let actual = a.convolve_n(k);
+
+        TestResult::from_bool(
+            actual.len() == expected.len()
+                && actual
+                    .iter()
+                    .zip(expected.iter())
+                    .all(|(l, r)| l.value == r.value && float_eq::float_eq!(l.p, r.p, abs <= 1e-9)),
+        )
+    }
+
+    fn bins(pmf: &Pmf<Bin>) -> Vec<(i32, i32, Option<Tail>)> {
+        pmf.iter()
+            .map(|outcome| {
+                let bin = &outcome.value;
+                (
+                    i32::try_from(&bin.min).unwrap(),
+                    i32::try_from(&bin.max).unwrap(),
+                    bin.tail,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bins_of_a_width() {
+        let pmf = (1..=10)
+            .map(|value| (1.0, IBig::from(value)))
+            .collect::<Pmf<_>>();
+        let binned = pmf.bin(&Binning::Width(UBig::from(3u8)), 0.0);
+
+        assert_eq!(
+            bins(&binned),
+            vec![(1, 3, None), (4, 6, None), (7, 9, None), (10, 10, None)]
+        );
+        float_eq::assert_float_eq!(binned.iter().next().unwrap().p, 0.3, abs <= 1e-12);
+    }
+
+    #[test]
+    fn bins_collapse_tails() {
+        // 3d6, where 3 and 4 have 4/216 of the probability, as do 17 and 18.
+        let d6 = (1..=6)
+            .map(|value| (1.0, IBig::from(value)))
+            .collect::<Pmf<_>>();
+        let binned = d6.convolve_n(3).bin(&Binning::Count(3), 0.02);
+
+        assert_eq!(
+            bins(&binned),
+            vec![
+                (3, 4, Some(Tail::Below)),
+                (5, 8, None),
+                (9, 12, None),
+                (13, 16, None),
+                (17, 18, Some(Tail::Above))
+            ]
+        );
+        let total = binned.iter().map(|outcome| outcome.p).sum::<f64>();
+        float_eq::assert_float_eq!(total, 1.0, abs <= 1e-12);
+    }
+
+    #[test]
+    fn bins_keep_an_outcome_between_tails() {
+        let binned = Pmf::<_>::constant(IBig::from(7)).bin(&Binning::Count(10), 1.0);
+
+        assert_eq!(bins(&binned), vec![(7, 7, None)]);
+    }
+
     #[test]
     fn expected_value_roulette() {
         let pmf = [(1.0 / 38.0, ibig!(36)), (37.0 / 38.0, ibig!(-1))]
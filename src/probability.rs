@@ -0,0 +1,251 @@
+use ibig::UBig;
+use num_traits::{One, Zero};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Sub};
+
+/// The type of the probabilities in a `Pmf`: either an `f64`, which is fast, or a `Ratio`, which
+/// is exact.
+pub trait Probability:
+    Clone + Debug + PartialOrd + Zero + One + Sub<Output = Self> + Div<Output = Self>
+{
+    /// The probability `numerator / denominator`.
+    fn ratio(numerator: &UBig, denominator: &UBig) -> Self;
+
+    /// The nearest `f64`. Probabilities smaller than the smallest `f64` are zero.
+    fn to_f64(&self) -> f64;
+
+    /// The base 10 logarithm, which is finite even when the probability is too small for an `f64`.
+    fn log10(&self) -> f64;
+}
+
+impl Probability for f64 {
+    fn ratio(numerator: &UBig, denominator: &UBig) -> Self {
+        ratio_to_f64(numerator, denominator)
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    fn log10(&self) -> f64 {
+        f64::log10(*self)
+    }
+}
+
+/// An exact, non-negative rational number in lowest terms.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ratio {
+    numerator: UBig,
+    denominator: UBig,
+}
+
+impl Ratio {
+    /// Panics if `denominator` is zero.
+    pub fn new(numerator: UBig, denominator: UBig) -> Self {
+        assert!(denominator != UBig::from(0_u8), "The denominator is zero.");
+
+        let divisor = gcd(numerator.clone(), denominator.clone());
+
+        Self {
+            numerator: numerator / &divisor,
+            denominator: denominator / &divisor,
+        }
+    }
+
+    pub fn numerator(&self) -> &UBig {
+        &self.numerator
+    }
+
+    pub fn denominator(&self) -> &UBig {
+        &self.denominator
+    }
+}
+
+impl Probability for Ratio {
+    fn ratio(numerator: &UBig, denominator: &UBig) -> Self {
+        Ratio::new(numerator.clone(), denominator.clone())
+    }
+
+    fn to_f64(&self) -> f64 {
+        ratio_to_f64(&self.numerator, &self.denominator)
+    }
+
+    fn log10(&self) -> f64 {
+        log10(&self.numerator) - log10(&self.denominator)
+    }
+}
+
+impl Add for Ratio {
+    type Output = Ratio;
+
+    fn add(self, right: Ratio) -> Self::Output {
+        if self.denominator == right.denominator {
+            return Ratio::new(self.numerator + right.numerator, self.denominator);
+        }
+
+        Ratio::new(
+            self.numerator * &right.denominator + right.numerator * &self.denominator,
+            self.denominator * right.denominator,
+        )
+    }
+}
+
+/// Probabilities are never negative, so subtraction saturates at zero.
+impl Sub for Ratio {
+    type Output = Ratio;
+
+    fn sub(self, right: Ratio) -> Self::Output {
+        let left = &self.numerator * &right.denominator;
+        let right_numerator = &right.numerator * &self.denominator;
+
+        match left.cmp(&right_numerator) {
+            Ordering::Greater => {
+                Ratio::new(left - right_numerator, self.denominator * right.denominator)
+            }
+            Ordering::Less | Ordering::Equal => Ratio::zero(),
+        }
+    }
+}
+
+impl Mul for Ratio {
+    type Output = Ratio;
+
+    fn mul(self, right: Ratio) -> Self::Output {
+        Ratio::new(
+            self.numerator * right.numerator,
+            self.denominator * right.denominator,
+        )
+    }
+}
+
+/// Panics when dividing by zero.
+impl Div for Ratio {
+    type Output = Ratio;
+
+    fn div(self, right: Ratio) -> Self::Output {
+        Ratio::new(
+            self.numerator * right.denominator,
+            self.denominator * right.numerator,
+        )
+    }
+}
+
+impl Zero for Ratio {
+    fn zero() -> Self {
+        Ratio::ratio(&UBig::from(0_u8), &UBig::from(1_u8))
+    }
+
+    fn is_zero(&self) -> bool {
+        self.numerator == UBig::from(0_u8)
+    }
+}
+
+impl One for Ratio {
+    fn one() -> Self {
+        Ratio::ratio(&UBig::from(1_u8), &UBig::from(1_u8))
+    }
+}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, right: &Self) -> Option<Ordering> {
+        Some(self.cmp(right))
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, right: &Self) -> Ordering {
+        (&self.numerator * &right.denominator).cmp(&(&right.numerator * &self.denominator))
+    }
+}
+
+impl std::fmt::Display for Ratio {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.denominator == UBig::from(1_u8) {
+            formatter.write_fmt(format_args!("{}", self.numerator))
+        } else {
+            formatter.write_fmt(format_args!("{}/{}", self.numerator, self.denominator))
+        }
+    }
+}
+
+/// The nearest `f64` to `numerator / denominator`. Unlike dividing two `f64`s, it does not
+/// overflow to infinity or NaN when both are too large for an `f64`.
+pub fn ratio_to_f64(numerator: &UBig, denominator: &UBig) -> f64 {
+    // Only the 64 most significant bits of each affect the result.
+    let numerator_shift = numerator.bit_len().saturating_sub(64);
+    let denominator_shift = denominator.bit_len().saturating_sub(64);
+    let quotient =
+        (numerator >> numerator_shift).to_f64() / (denominator >> denominator_shift).to_f64();
+    let exponent = numerator_shift as i64 - denominator_shift as i64;
+
+    match i32::try_from(exponent) {
+        Ok(exponent) => quotient * 2_f64.powi(exponent),
+        Err(_) if exponent < 0 => 0.0,
+        Err(_) => f64::INFINITY,
+    }
+}
+
+fn log10(n: &UBig) -> f64 {
+    let shift = n.bit_len().saturating_sub(64);
+
+    (n >> shift).to_f64().log10() + shift as f64 * std::f64::consts::LOG10_2
+}
+
+fn gcd(mut a: UBig, mut b: UBig) -> UBig {
+    while b != UBig::from(0_u8) {
+        let remainder = &a % &b;
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use quickcheck_macros::quickcheck;
+
+    #[test]
+    fn ratios_are_reduced() {
+        let ratio = Ratio::ratio(&UBig::from(6_u8), &UBig::from(36_u8));
+
+        assert_eq!(ratio.numerator(), &UBig::from(1_u8));
+        assert_eq!(ratio.denominator(), &UBig::from(6_u8));
+        assert_eq!(ratio.to_string(), "1/6");
+        assert_eq!(Ratio::one().to_string(), "1");
+    }
+
+    #[quickcheck]
+    fn arithmetic_matches_f64(a: u16, b: u16, c: u16, d: u16) -> bool {
+        let (b, d) = (u32::from(b) + 1, u32::from(d) + 1);
+        let left = Ratio::ratio(&UBig::from(a), &UBig::from(b));
+        let right = Ratio::ratio(&UBig::from(c), &UBig::from(d));
+        let (x, y) = (f64::from(a) / f64::from(b), f64::from(c) / f64::from(d));
+
+        let close = |ratio: Ratio, expected: f64| {
+            float_eq::float_eq!(ratio.to_f64(), expected, rmax <= 1e-12)
+        };
+
+        close(left.clone() + right.clone(), x + y)
+            && close(left.clone() * right.clone(), x * y)
+            && close(left.clone() - right.clone(), f64::max(x - y, 0.0))
+            && (c == 0 || close(left.clone() / right.clone(), x / y))
+            && (left.cmp(&right) == x.partial_cmp(&y).unwrap() || x == y)
+    }
+
+    #[test]
+    fn huge_ratios() {
+        let sides = UBig::from(100_u8).pow(200);
+        let count = UBig::from(3_u8) * UBig::from(100_u8).pow(199);
+
+        float_eq::assert_float_eq!(ratio_to_f64(&count, &sides), 0.03, rmax <= 1e-12);
+        assert_eq!(ratio_to_f64(&UBig::from(1_u8), &sides), 0.0);
+        float_eq::assert_float_eq!(
+            Ratio::ratio(&UBig::from(1_u8), &sides).log10(),
+            -400.0,
+            abs <= 1e-9
+        );
+    }
+}
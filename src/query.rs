@@ -0,0 +1,295 @@
+use crate::expression::ParseError;
+use crate::pmf::Pmf;
+use crate::probability::Probability;
+use ibig::IBig;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while},
+    character::complete::one_of,
+    combinator::{all_consuming, map, map_res, opt, recognize},
+    error::VerboseError,
+    multi::{many1, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    IResult,
+};
+
+/// A question about the outcomes of a distribution, e.g. `>= 15`, `1..4 or 20` or
+/// `P(>= 15 | > 10)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    Event(Event),
+    Conditional { event: Event, given: Event },
+}
+
+/// A set of outcomes: the union of its conditions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event(Vec<Condition>);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Equal(IBig),
+    NotEqual(IBig),
+    Greater(IBig),
+    GreaterOrEqual(IBig),
+    Less(IBig),
+    LessOrEqual(IBig),
+    /// An inclusive range, e.g. `1..4`.
+    Range(IBig, IBig),
+}
+
+impl Query {
+    pub fn probability<P: Probability>(&self, pmf: &Pmf<IBig, P>) -> Result<P, anyhow::Error> {
+        match self {
+            Query::Event(event) => Ok(pmf.probability_where(|value| event.contains(value))),
+            Query::Conditional { event, given } => {
+                let pmf = given.condition(pmf)?;
+
+                Ok(pmf.probability_where(|value| event.contains(value)))
+            }
+        }
+    }
+}
+
+impl Event {
+    pub fn contains(&self, value: &IBig) -> bool {
+        self.0.iter().any(|condition| condition.contains(value))
+    }
+
+    /// The distribution of `pmf` given this event occurs. It is an error if it never occurs.
+    pub fn condition<P: Probability>(
+        &self,
+        pmf: &Pmf<IBig, P>,
+    ) -> Result<Pmf<IBig, P>, anyhow::Error> {
+        let pmf = pmf.condition(|value| self.contains(value));
+
+        if pmf.is_empty() {
+            return Err(anyhow::anyhow!(
+                "The condition {} never occurs, so the probability is undefined.",
+                self
+            ));
+        }
+
+        Ok(pmf)
+    }
+}
+
+impl Condition {
+    pub fn contains(&self, value: &IBig) -> bool {
+        match self {
+            Condition::Equal(n) => value == n,
+            Condition::NotEqual(n) => value != n,
+            Condition::Greater(n) => value > n,
+            Condition::GreaterOrEqual(n) => value >= n,
+            Condition::Less(n) => value < n,
+            Condition::LessOrEqual(n) => value <= n,
+            Condition::Range(min, max) => value >= min && value <= max,
+        }
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(i: &str) -> Result<Self, Self::Err> {
+        match nom::Finish::finish(all_consuming(delimited(space, query, space))(i)) {
+            Ok((_, query)) => Ok(query),
+            Err(err) => Err(crate::expression::convert_error(i, err)),
+        }
+    }
+}
+
+impl std::str::FromStr for Event {
+    type Err = ParseError;
+
+    fn from_str(i: &str) -> Result<Self, Self::Err> {
+        match nom::Finish::finish(all_consuming(delimited(space, event, space))(i)) {
+            Ok((_, event)) => Ok(event),
+            Err(err) => Err(crate::expression::convert_error(i, err)),
+        }
+    }
+}
+
+impl std::fmt::Display for Query {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Query::Event(event) => formatter.write_fmt(format_args!("P({})", event)),
+            Query::Conditional { event, given } => {
+                formatter.write_fmt(format_args!("P({} | {})", event, given))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let conditions = self
+            .0
+            .iter()
+            .map(|condition| condition.to_string())
+            .collect::<Vec<_>>();
+
+        formatter.write_str(&conditions.join(" or "))
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            Condition::Equal(n) => formatter.write_fmt(format_args!("{}", n)),
+            Condition::NotEqual(n) => formatter.write_fmt(format_args!("!= {}", n)),
+            Condition::Greater(n) => formatter.write_fmt(format_args!("> {}", n)),
+            Condition::GreaterOrEqual(n) => formatter.write_fmt(format_args!(">= {}", n)),
+            Condition::Less(n) => formatter.write_fmt(format_args!("< {}", n)),
+            Condition::LessOrEqual(n) => formatter.write_fmt(format_args!("<= {}", n)),
+            Condition::Range(min, max) => formatter.write_fmt(format_args!("{}..{}", min, max)),
+        }
+    }
+}
+
+// Queries may be wrapped in `P(...)`, as they are written in probability notation.
+fn query(i: &str) -> IResult<&str, Query, VerboseError<&str>> {
+    alt((
+        delimited(pair(tag("P("), space), unwrapped, pair(space, tag(")"))),
+        unwrapped,
+    ))(i)
+}
+
+fn unwrapped(i: &str) -> IResult<&str, Query, VerboseError<&str>> {
+    alt((
+        map(
+            separated_pair(event, delimited(space, tag("|"), space), event),
+            |(event, given)| Query::Conditional { event, given },
+        ),
+        map(event, Query::Event),
+    ))(i)
+}
+
+fn event(i: &str) -> IResult<&str, Event, VerboseError<&str>> {
+    map(
+        separated_list1(
+            alt((
+                delimited(space, tag(","), space),
+                delimited(space, tag("or"), space),
+            )),
+            condition,
+        ),
+        Event,
+    )(i)
+}
+
+fn condition(i: &str) -> IResult<&str, Condition, VerboseError<&str>> {
+    alt((
+        map(separated_pair(integer, tag(".."), integer), |(min, max)| {
+            Condition::Range(min, max)
+        }),
+        map(preceded(terminated(tag(">="), space), integer), |n| {
+            Condition::GreaterOrEqual(n)
+        }),
+        map(preceded(terminated(tag("<="), space), integer), |n| {
+            Condition::LessOrEqual(n)
+        }),
+        map(preceded(terminated(tag("!="), space), integer), |n| {
+            Condition::NotEqual(n)
+        }),
+        map(preceded(terminated(tag(">"), space), integer), |n| {
+            Condition::Greater(n)
+        }),
+        map(preceded(terminated(tag("<"), space), integer), |n| {
+            Condition::Less(n)
+        }),
+        map(
+            preceded(terminated(alt((tag("=="), tag("="))), space), integer),
+            Condition::Equal,
+        ),
+        map(integer, Condition::Equal),
+    ))(i)
+}
+
+fn integer(i: &str) -> IResult<&str, IBig, VerboseError<&str>> {
+    map_res(
+        recognize(tuple((opt(tag("-")), many1(one_of("0123456789"))))),
+        |out: &str| str::parse(out).map_err(|_| ()),
+    )(i)
+}
+
+fn space(i: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    let chars = " \t\r\n";
+
+    take_while(move |c| chars.contains(c))(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn comparisons() {
+        let pmf = d20();
+
+        assert_p(&pmf, ">= 15", 0.30);
+        assert_p(&pmf, "> 15", 0.25);
+        assert_p(&pmf, "<=5", 0.25);
+        assert_p(&pmf, "< 5", 0.20);
+        assert_p(&pmf, "= 20", 0.05);
+        assert_p(&pmf, "!= 20", 0.95);
+    }
+
+    #[test]
+    fn ranges_and_unions() {
+        let pmf = d20();
+
+        assert_p(&pmf, "1..4", 0.20);
+        assert_p(&pmf, "1 or 20", 0.10);
+        assert_p(&pmf, "1..4, >= 19", 0.30);
+        assert_p(&pmf, "P(1..10 or 5..15)", 0.75);
+    }
+
+    #[test]
+    fn conditional() {
+        let pmf = d20();
+
+        assert_p(&pmf, "P(>= 15 | > 10)", 0.60);
+        assert_p(&pmf, "20 | 20", 1.0);
+        assert!(Query::from_str("P(1 | > 20)")
+            .unwrap()
+            .probability(&pmf)
+            .is_err());
+    }
+
+    #[test]
+    fn conditioned_events() {
+        let pmf = Event::from_str(">= 11").unwrap().condition(&d20()).unwrap();
+
+        assert_eq!(pmf.len(), 10);
+        assert_p(&pmf, ">= 15", 0.60);
+        assert!(Event::from_str("> 20").unwrap().condition(&d20()).is_err());
+        assert!(Event::from_str("P(> 20)").is_err());
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            Query::from_str(" >=15,1..2|  !=-3 ").unwrap().to_string(),
+            "P(>= 15 or 1..2 | != -3)"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Query::from_str("").is_err());
+        assert!(Query::from_str(">= ").is_err());
+        assert!(Query::from_str("P(>= 15").is_err());
+        assert!(Query::from_str("1 | 2 | 3").is_err());
+    }
+
+    fn d20() -> Pmf<IBig> {
+        (1..=20).map(|n| (1.0, IBig::from(n))).collect()
+    }
+
+    fn assert_p(pmf: &Pmf<IBig>, query: &str, p: f64) {
+        let actual = Query::from_str(query).unwrap().probability(pmf).unwrap();
+
+        float_eq::assert_float_eq!(actual, p, abs <= 0.000001);
+    }
+}
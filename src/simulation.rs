@@ -0,0 +1,145 @@
+use crate::pmf::Pmf;
+use ibig::IBig;
+use std::collections::BTreeMap;
+
+// The 97.5th percentile of the standard normal distribution, for 95% confidence intervals.
+const Z: f64 = 1.959_963_984_540_054;
+
+/// An estimate of a distribution from random samples, for expressions whose distribution is too
+/// expensive to compute.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Simulation {
+    counts: BTreeMap<IBig, u64>,
+    samples: u64,
+}
+
+impl Simulation {
+    pub fn from_samples<I>(samples: I) -> Self
+    where
+        I: IntoIterator<Item = IBig>,
+    {
+        let mut simulation = Simulation {
+            counts: BTreeMap::new(),
+            samples: 0,
+        };
+
+        for sample in samples {
+            *simulation.counts.entry(sample).or_insert(0) += 1;
+            simulation.samples += 1;
+        }
+
+        simulation
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// The number of samples of each outcome, in ascending order.
+    pub fn counts(&self) -> impl Iterator<Item = (&IBig, u64)> {
+        self.counts.iter().map(|(value, count)| (value, *count))
+    }
+
+    /// Keeps only the samples where `predicate` holds, as if the others were never rolled.
+    pub fn condition<F>(&self, predicate: F) -> Self
+    where
+        F: Fn(&IBig) -> bool,
+    {
+        let counts = self
+            .counts
+            .iter()
+            .filter(|(value, _)| predicate(value))
+            .map(|(value, count)| (value.clone(), *count))
+            .collect::<BTreeMap<_, _>>();
+
+        Simulation {
+            samples: counts.values().sum(),
+            counts,
+        }
+    }
+
+    /// The empirical distribution: the fraction of samples of each outcome.
+    pub fn pmf(&self) -> Pmf<IBig> {
+        self.counts
+            .iter()
+            .map(|(value, count)| (*count as f64 / self.samples as f64, value.clone()))
+            .collect()
+    }
+
+    /// The 95% Wilson score interval of the probability of each outcome, in ascending order of
+    /// outcome. Unlike the normal approximation, it stays within [0, 1] for rare outcomes.
+    pub fn confidence_intervals(&self) -> Vec<(f64, f64)> {
+        let n = self.samples as f64;
+
+        self.counts
+            .values()
+            .map(|count| {
+                let p = *count as f64 / n;
+                let scale = 1.0 + Z * Z / n;
+                let center = (p + Z * Z / (2.0 * n)) / scale;
+                let half_width = Z / scale * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt();
+
+                (
+                    (center - half_width).max(0.0),
+                    (center + half_width).min(1.0),
+                )
+            })
+            .collect()
+    }
+
+    /// Half the width of the widest confidence interval, a bound on how far any estimated
+    /// probability is likely to be from the truth.
+    pub fn max_error(&self) -> f64 {
+        self.confidence_intervals()
+            .iter()
+            .map(|(low, high)| (high - low) / 2.0)
+            .fold(0.0, f64::max)
+    }
+
+    /// The standard error of the sample mean, which shrinks with the square root of the number
+    /// of samples.
+    pub fn standard_error(&self) -> f64 {
+        (self.pmf().variance() / self.samples as f64).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use float_eq::assert_float_eq;
+
+    #[test]
+    fn empirical_distribution() {
+        let simulation = Simulation::from_samples([1, 2, 2, 3].iter().map(|n| IBig::from(*n)));
+        let pmf = simulation.pmf();
+        let p = pmf.iter().map(|outcome| outcome.p).collect::<Vec<_>>();
+
+        assert_eq!(simulation.samples(), 4);
+        assert_eq!(p, vec![0.25, 0.5, 0.25]);
+
+        let conditioned = simulation.condition(|value| value > &IBig::from(1));
+        assert_eq!(conditioned.samples(), 3);
+        assert_eq!(
+            conditioned
+                .counts()
+                .map(|(_, count)| count)
+                .collect::<Vec<_>>(),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn intervals_contain_estimates() {
+        let samples = (0..1000).map(|i| IBig::from(i % 10 / 9));
+        let simulation = Simulation::from_samples(samples);
+        let intervals = simulation.confidence_intervals();
+
+        for (outcome, (low, high)) in simulation.pmf().iter().zip(intervals.iter()) {
+            assert!(*low < outcome.p && outcome.p < *high);
+        }
+
+        // The interval of a probability of 0.1 from 1000 samples is about ±1.9%.
+        assert_float_eq!(intervals[1].1 - intervals[1].0, 0.0378, abs <= 1e-3);
+        assert_float_eq!(simulation.max_error(), 0.0189, abs <= 1e-3);
+    }
+}
@@ -0,0 +1,210 @@
+use crate::distributions::chi_squared_survival;
+use crate::pmf::Pmf;
+use crate::simulation::Simulation;
+use ibig::IBig;
+use std::collections::BTreeMap;
+
+// The G-test is only reliable when each bucket expects at least this many samples, so rarer
+// outcomes are merged with their neighbours.
+const MIN_EXPECTED: f64 = 5.0;
+
+/// How well samples of an expression fit its distribution, by a G-test. A low p-value means the
+/// sampler and the distribution disagree, so one of them is wrong.
+#[derive(Clone, Debug)]
+pub struct Verification {
+    pub samples: u64,
+    /// The significance level the p-value and each bucket are tested at.
+    pub alpha: f64,
+    pub g: f64,
+    pub degrees_of_freedom: usize,
+    pub p_value: f64,
+    pub buckets: Vec<Bucket>,
+    /// Samples of outcomes the distribution says are impossible, with their counts.
+    pub impossible: Vec<(IBig, u64)>,
+}
+
+/// A range of consecutive outcomes of the distribution, and how often they were sampled.
+#[derive(Clone, Debug)]
+pub struct Bucket {
+    pub min: IBig,
+    pub max: IBig,
+    pub observed: u64,
+    pub expected: f64,
+    /// The standardized residual, (observed - expected) / sqrt(expected * (1 - p)), which is
+    /// roughly standard normal when the sampler is right.
+    pub residual: f64,
+    /// Whether the bucket deviates significantly, with a Bonferroni correction for the number of
+    /// buckets.
+    pub significant: bool,
+}
+
+impl Verification {
+    /// Tests `simulation` against `pmf` at the significance level `alpha`.
+    pub fn new(simulation: &Simulation, pmf: &Pmf<IBig>, alpha: f64) -> Self {
+        let buckets = buckets(simulation, pmf, alpha);
+        let impossible = impossible(simulation, pmf);
+
+        let g = 2.0
+            * buckets
+                .iter()
+                .filter(|bucket| bucket.observed > 0)
+                .map(|bucket| {
+                    let observed = bucket.observed as f64;
+                    observed * (observed / bucket.expected).ln()
+                })
+                .sum::<f64>();
+        let degrees_of_freedom = buckets.len().saturating_sub(1);
+        let p_value = match (impossible.is_empty(), degrees_of_freedom) {
+            (false, _) => 0.0,
+            (true, 0) => 1.0,
+            (true, degrees_of_freedom) => chi_squared_survival(g, degrees_of_freedom as f64),
+        };
+
+        Verification {
+            samples: simulation.samples(),
+            alpha,
+            g,
+            degrees_of_freedom,
+            p_value,
+            buckets,
+            impossible,
+        }
+    }
+
+    /// Whether the samples are consistent with the distribution.
+    pub fn passed(&self) -> bool {
+        self.p_value >= self.alpha && self.impossible.is_empty()
+    }
+}
+
+/// Groups the outcomes of `pmf` into buckets of consecutive outcomes that each expect at least a
+/// few of the samples, and compares them with the samples.
+pub fn buckets(simulation: &Simulation, pmf: &Pmf<IBig>, alpha: f64) -> Vec<Bucket> {
+    let n = simulation.samples() as f64;
+    let counts = simulation
+        .counts()
+        .map(|(value, count)| (value.clone(), count))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut ranges: Vec<(IBig, IBig, f64)> = vec![];
+    let mut pending: Option<(IBig, IBig, f64)> = None;
+    for outcome in pmf.iter() {
+        let (min, p) = match pending.take() {
+            Some((min, _, p)) => (min, p + outcome.p),
+            None => (outcome.value.clone(), outcome.p),
+        };
+
+        match p * n >= MIN_EXPECTED {
+            true => ranges.push((min, outcome.value.clone(), p)),
+            false => pending = Some((min, outcome.value.clone(), p)),
+        }
+    }
+    if let Some((min, max, p)) = pending {
+        match ranges.last_mut() {
+            Some(last) => {
+                last.1 = max;
+                last.2 += p;
+            }
+            None => ranges.push((min, max, p)),
+        }
+    }
+
+    let buckets_len = ranges.len().max(1) as f64;
+    ranges
+        .into_iter()
+        .map(|(min, max, p)| {
+            let observed = counts
+                .range(&min..=&max)
+                .map(|(_, count)| count)
+                .sum::<u64>();
+            let expected = p * n;
+            let residual = match expected * (1.0 - p) {
+                variance if variance > 0.0 => (observed as f64 - expected) / variance.sqrt(),
+                _ => 0.0,
+            };
+
+            Bucket {
+                min,
+                max,
+                observed,
+                expected,
+                residual,
+                significant: chi_squared_survival(residual * residual, 1.0) < alpha / buckets_len,
+            }
+        })
+        .collect()
+}
+
+/// The samples of outcomes `pmf` says are impossible, with their counts.
+pub fn impossible(simulation: &Simulation, pmf: &Pmf<IBig>) -> Vec<(IBig, u64)> {
+    simulation
+        .counts()
+        .filter(|(value, _)| !pmf.iter().any(|outcome| &outcome.value == *value))
+        .map(|(value, count)| (value.clone(), count))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn die(sides: i64) -> Pmf<IBig> {
+        (1..=sides)
+            .map(|side| (1.0 / sides as f64, IBig::from(side)))
+            .collect()
+    }
+
+    #[test]
+    fn fair_samples_pass() {
+        let samples = (0..6000).map(|i| IBig::from(i % 6 + 1));
+        let verification = Verification::new(&Simulation::from_samples(samples), &die(6), 0.01);
+
+        assert!(verification.passed());
+        assert_eq!(verification.degrees_of_freedom, 5);
+        assert!(verification
+            .buckets
+            .iter()
+            .all(|bucket| !bucket.significant));
+    }
+
+    #[test]
+    fn loaded_samples_fail() {
+        // A six is rolled 1150 times in 6000, where 1000 are expected.
+        let samples = (0..6000).map(|i| IBig::from((i / 970 + 1).min(6)));
+        let verification = Verification::new(&Simulation::from_samples(samples), &die(6), 0.01);
+
+        assert!(!verification.passed());
+        let significant = verification
+            .buckets
+            .iter()
+            .filter(|bucket| bucket.significant)
+            .map(|bucket| bucket.min.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(significant, vec![IBig::from(6)]);
+    }
+
+    #[test]
+    fn rare_outcomes_are_merged() {
+        let samples = (0..20).map(|i| IBig::from(i % 6 + 1));
+        let verification = Verification::new(&Simulation::from_samples(samples), &die(6), 0.01);
+
+        // Each bucket expects at least 5 of the 20 samples.
+        assert!(verification
+            .buckets
+            .iter()
+            .all(|bucket| bucket.expected >= MIN_EXPECTED));
+        assert_eq!(
+            verification.buckets.iter().map(|b| b.observed).sum::<u64>(),
+            20
+        );
+    }
+
+    #[test]
+    fn impossible_outcomes_fail() {
+        let samples = (0..600).map(|i| IBig::from(i % 7 + 1));
+        let verification = Verification::new(&Simulation::from_samples(samples), &die(6), 0.01);
+
+        assert!(!verification.passed());
+        assert_eq!(verification.impossible, vec![(IBig::from(7), 85)]);
+    }
+}
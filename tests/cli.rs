@@ -8,7 +8,7 @@ fn roll_json() -> Result<(), Box<dyn std::error::Error>> {
     cmd.arg("--json").arg("--seed").arg("45").arg("3d6");
     cmd.assert()
         .success()
-        .stdout("{\"rolls\":{\"d6\":[1,6,4]},\"value\":11}\n");
+        .stdout("{\"rolls\":{\"d6\":[1,6,4]},\"value\":11,\"trace\":{\"expression\":\"3d6\",\"value\":11,\"rolls\":[1,6,4],\"operands\":[{\"expression\":\"3\",\"value\":3,\"operands\":[]},{\"expression\":\"6\",\"value\":6,\"operands\":[]}]},\"rng\":{\"backend\":\"seeded\",\"seed\":45}}\n");
 
     Ok(())
 }
@@ -20,7 +20,8 @@ fn roll_tty() -> Result<(), Box<dyn std::error::Error>> {
     cmd.arg("--colors").arg("--seed").arg("45").arg("3d6");
     cmd.assert()
         .success()
-        .stdout("\u{1b}[1m\u{1b}[35mExpression:\u{1b}[39m\u{1b}[0m \u{1b}[34m3d6\u{1b}[39m\n\u{1b}[1m\u{1b}[35mRolls:\u{1b}[39m\u{1b}[0m\n  d6: {\u{1b}[31m1\u{1b}[0m, 4, \u{1b}[32m6\u{1b}[0m}\n\n\u{1b}[34m11\u{1b}[39m\n");
+        .stderr("\u{1b}[1m\u{1b}[35mRng:\u{1b}[39m\u{1b}[0m \u{1b}[34mseeded (seed 45)\u{1b}[39m\n")
+        .stdout("\u{1b}[1m\u{1b}[35mExpression:\u{1b}[39m\u{1b}[0m \u{1b}[34m3d6\u{1b}[39m\n\u{1b}[1m\u{1b}[35mRolls:\u{1b}[39m\u{1b}[0m\n  3d6 (\u{1b}[31m1\u{1b}[0m, \u{1b}[32m6\u{1b}[0m, 4) = \u{1b}[34m11\u{1b}[39m\n");
 
     Ok(())
 }
@@ -35,6 +36,20 @@ fn roll_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn roll_trace_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("--seed").arg("65").arg("--trace").arg("1d100 + 10");
+    cmd.assert().success().stdout("1d100 (13) + 10 = 23\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("--trace").arg("--pmf").arg("1d6");
+    let output = cmd.assert().failure().get_output().stderr.clone();
+    assert!(String::from_utf8(output)?.contains("--trace applies only to rolls"));
+
+    Ok(())
+}
+
 #[test]
 fn pmf_json() -> Result<(), Box<dyn std::error::Error>> {
     let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
@@ -65,3 +80,1092 @@ fn pmf_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
     cmd.assert().success().stdout("   2  2.78%\n   3  5.56%\n   4  8.33%\n   5 11.11%\n   6 13.89%\n   7 16.67%\n   8 13.89%\n   9 11.11%\n  10  8.33%\n  11  5.56%\n  12  2.78%\n\n");
     Ok(())
 }
+
+#[test]
+fn pmf_statistics_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--colors")
+        .arg("--stats")
+        .arg("median,p90")
+        .arg("1d4");
+    cmd.assert().success().stdout("\u{1b}[1m\u{1b}[35mExpression:\u{1b}[39m\u{1b}[0m \u{1b}[34m1d4\u{1b}[39m\n  \u{1b}[1m\u{1b}[36mMedian:\u{1b}[39m\u{1b}[0m 2\n  \u{1b}[1m\u{1b}[36m90th percentile:\u{1b}[39m\u{1b}[0m 4\n\n  1 25.00%\n  2 25.00%\n  3 25.00%\n  4 25.00%\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn pmf_statistics_without_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--stats").arg("median,p90").arg("1d4");
+    cmd.assert().success().stdout(
+        "  Median: 2\n  90th percentile: 4\n\n  1 25.00%\n  2 25.00%\n  3 25.00%\n  4 25.00%\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pmf_statistics_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--stats")
+        .arg("modes,variance,skewness")
+        .arg("--json")
+        .arg("5");
+    cmd.assert().success().stdout(
+        "{\"pmf\":[{\"value\":5,\"p\":1.0}],\"statistics\":{\"modes\":[5],\"variance\":0.0,\"skewness\":null}}\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pmf_statistics_unknown() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--stats").arg("mean,p101").arg("1d4");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_statistics_unused() -> Result<(), Box<dyn std::error::Error>> {
+    for args in [
+        vec!["fairness", "--observed", "tests/rolls/d6.txt", "1d6"],
+        vec!["verify", "--samples", "100", "1d6"],
+        vec!["dpr", "--bonus", "5", "--damage", "1d8"],
+        vec!["fit", "--mean", "7"],
+        vec!["table", "--pmf", "tests/tables/loot.json"],
+        vec!["1d6", "--query", ">= 4"],
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        cmd.args(args).arg("--stats").arg("mean");
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        assert!(String::from_utf8(output)?
+            .contains("--stats applies only to distributions, comparisons and sweeps"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn pmf_cumulative_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--cdf").arg("--at-least").arg("--json").arg("1d4");
+    cmd.assert()
+        .success()
+        .stdout("{\"pmf\":[{\"value\":1,\"p\":0.25,\"cdf\":0.25,\"at_least\":1.0},{\"value\":2,\"p\":0.25,\"cdf\":0.5,\"at_least\":0.75},{\"value\":3,\"p\":0.25,\"cdf\":0.75,\"at_least\":0.5},{\"value\":4,\"p\":0.25,\"cdf\":1.0,\"at_least\":0.25}],\"statistics\":{\"min\":1,\"mean\":2.5,\"max\":4}}\n");
+
+    Ok(())
+}
+
+#[test]
+fn pmf_at_least_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--at-least").arg("1d4 + 1");
+    cmd.assert().success().stdout(
+        "  2 25.00%  100.00%\n  3 25.00%   75.00%\n  4 25.00%   50.00%\n  5 25.00%   25.00%\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn query_conditional() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--query").arg("P(>= 15 | > 10)").arg("1d20");
+    cmd.assert().success().stdout("60.00%\n");
+
+    Ok(())
+}
+
+#[test]
+fn query_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--json").arg("--query").arg("1..2 or 4").arg("1d4");
+    cmd.assert()
+        .success()
+        .stdout("{\"query\":\"P(1..2 or 4)\",\"p\":0.75}\n");
+
+    Ok(())
+}
+
+#[test]
+fn table_nested() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--seed")
+        .arg("2")
+        .arg("table")
+        .arg("tests/tables/encounters.json");
+    cmd.assert()
+        .success()
+        .stdout("A merchant carrying 9 gold\n");
+
+    Ok(())
+}
+
+#[test]
+fn table_pmf_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--pmf")
+        .arg("table")
+        .arg("tests/tables/encounters.json");
+    cmd.assert().success().stdout("   1..10  50.00%  {1d4 + 1} wolves\n  11..19  45.00%  A merchant carrying {@loot.json}\n      20   5.00%  A dragon\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn compare_missing_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("compare").arg("1d2").arg("2");
+    cmd.assert().success().stdout("Expressions:\n  A 1d2\n  B 2\n\nStatistics:\n                A       B\n  Min           1       2\n  Max           2       2\n  Mean       1.50    2.00\n  Std dev    0.50    0.00\n\nP(row > column):\n          A       B\n  A       -   0.00%\n  B  50.00%       -\n\nTotal variation distance:\n          A       B\n  A       -  0.5000\n  B  0.5000       -\n\nKL divergence of column from row (bits):\n          A       B\n  A       -     inf\n  B  1.0000       -\n\nDistributions:\n  1 A  50.00%\n    B   0.00%\n  2 A  50.00%\n    B 100.00%\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn compare_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("compare")
+        .arg("1d4")
+        .arg("2")
+        .arg("--stats")
+        .arg("mean")
+        .arg("--json");
+    cmd.assert().success().stdout("{\"expressions\":[{\"expression\":\"1d4\",\"pmf\":[{\"value\":1,\"p\":0.25},{\"value\":2,\"p\":0.25},{\"value\":3,\"p\":0.25},{\"value\":4,\"p\":0.25}],\"statistics\":{\"mean\":2.5}},{\"expression\":\"2\",\"pmf\":[{\"value\":2,\"p\":1.0}],\"statistics\":{\"mean\":2.0}}],\"pairs\":[{\"left\":0,\"right\":1,\"p_greater\":0.5,\"total_variation\":0.75,\"kl_divergence\":null},{\"left\":1,\"right\":0,\"p_greater\":0.25,\"total_variation\":0.75,\"kl_divergence\":2.0}]}\n");
+
+    Ok(())
+}
+
+#[test]
+fn compare_requires_two_expressions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("compare").arg("1d4");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_given_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--given").arg("2, 4").arg("--json").arg("1d4");
+    cmd.assert().success().stdout("{\"given\":\"2 or 4\",\"pmf\":[{\"value\":2,\"p\":0.5},{\"value\":4,\"p\":0.5}],\"statistics\":{\"min\":2,\"mean\":3.0,\"max\":4}}\n");
+
+    Ok(())
+}
+
+#[test]
+fn query_given() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d20")
+        .arg("--query")
+        .arg(">= 15")
+        .arg("--given")
+        .arg("> 10");
+    cmd.assert().success().stdout("60.00%\n");
+
+    Ok(())
+}
+
+#[test]
+fn pmf_given_never_occurs() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("--given").arg("> 6").arg("1d6");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_given_unused() -> Result<(), Box<dyn std::error::Error>> {
+    for args in [
+        vec!["fairness", "--observed", "tests/rolls/d6.txt", "1d6"],
+        vec!["verify", "--samples", "100", "1d6"],
+        vec!["dpr", "--bonus", "5", "--damage", "1d8"],
+        vec!["fit", "--mean", "7"],
+        vec!["table", "tests/tables/loot.json"],
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        cmd.args(args).arg("--given").arg(">= 4");
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        assert!(String::from_utf8(output)?
+            .contains("--given applies only to distributions, queries, comparisons and sweeps"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn pmf_exact_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4 / 3").arg("--exact").arg("--json");
+    cmd.assert().success().stdout(
+        "{\"pmf\":[{\"value\":0,\"p\":0.5,\"exact\":\"1/2\"},{\"value\":1,\"p\":0.5,\"exact\":\"1/2\"}],\"statistics\":{\"min\":0,\"mean\":0.5,\"max\":1}}\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn query_exact() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d20").arg("--query").arg(">= 15").arg("--exact");
+    cmd.assert().success().stdout("30.00% (3/10)\n");
+
+    Ok(())
+}
+
+#[test]
+fn exact_geometric_fails() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("geometric(50%)").arg("--exact");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn tiny_probabilities_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("400d6").arg("--query").arg("400").arg("--exact");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    assert!(String::from_utf8(output)?.starts_with("5.49e-310% (1/"));
+
+    Ok(())
+}
+
+#[test]
+fn pmf_simulate_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d1").arg("--simulate").arg("10");
+    cmd.assert()
+        .success()
+        .stdout("  1 100.00%  [72.25%, 100.00%]\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn pmf_simulate_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("2d6")
+        .arg("--simulate")
+        .arg("1000")
+        .arg("--seed")
+        .arg("3")
+        .arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+
+    assert_eq!(json["simulation"]["samples"], 1000);
+    assert!(json["pmf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|outcome| outcome["interval"][0].as_f64() <= outcome["p"].as_f64()));
+
+    Ok(())
+}
+
+#[test]
+fn simulate_conflicts_with_exact() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d6").arg("--simulate").arg("10").arg("--exact");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_simulate_unused() -> Result<(), Box<dyn std::error::Error>> {
+    for args in [
+        vec!["fairness", "--observed", "tests/rolls/d6.txt", "1d6"],
+        vec!["verify", "--samples", "100", "1d6"],
+        vec!["dpr", "--bonus", "5", "--damage", "1d8"],
+        vec!["fit", "--mean", "7"],
+        vec!["table", "--pmf", "tests/tables/loot.json"],
+        vec!["compare", "1d6", "2d4"],
+    ] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        cmd.args(args).arg("--simulate").arg("10");
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        assert!(String::from_utf8(output)?.contains("--simulate applies only to distributions"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verify_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("verify")
+        .arg("1d1")
+        .arg("--samples")
+        .arg("10")
+        .arg("--seed")
+        .arg("0");
+    cmd.assert().success().stdout(
+        "Expression: 1d1\nSamples: 10 (seed 0)\nG-test: G = 0.00, 0 degrees of freedom, p = 1.0000\nResult: passed at a significance level of 0.001\n\nBuckets:\n  outcomes    observed      expected  residual\n         1          10         10.00     +0.00\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn verify_failure() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    // Almost every sample fails a test at a significance level this high.
+    cmd.arg("verify")
+        .arg("1d6")
+        .arg("--samples")
+        .arg("100")
+        .arg("--seed")
+        .arg("0")
+        .arg("--alpha")
+        .arg("0.99");
+    let output = cmd.assert().failure().get_output().stdout.clone();
+    assert!(String::from_utf8(output)?.contains("Result: FAILED"));
+
+    Ok(())
+}
+
+#[test]
+fn verify_invalid_alpha() -> Result<(), Box<dyn std::error::Error>> {
+    for alpha in ["5", "0", "1", "-0.1"] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        cmd.arg("verify")
+            .arg("1d6")
+            .arg("--samples")
+            .arg("100")
+            .arg(format!("--alpha={}", alpha));
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        assert!(String::from_utf8(output)?
+            .contains("--alpha must be a significance level between 0 and 1"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn verify_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("verify")
+        .arg("2d6")
+        .arg("--samples")
+        .arg("5000")
+        .arg("--seed")
+        .arg("4")
+        .arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+
+    assert_eq!(json["passed"], true);
+    assert_eq!(json["degrees_of_freedom"], 10);
+    assert_eq!(json["impossible"].as_array().map(Vec::len), Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn fairness_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fairness")
+        .arg("1d6")
+        .arg("--observed")
+        .arg("tests/rolls/d6.txt");
+    cmd.assert().success().stdout("Expression: 1d6\nObserved: 24 results from tests/rolls/d6.txt\nChi-square: χ² = 0.00, 2 degrees of freedom, p = 1.0000\nKolmogorov-Smirnov: D = 0.0000, p = 1.0000\nVerdict: fair at a significance level of 0.05\n\nBuckets:\n  outcomes    observed      expected  residual\n      1..2           8          8.00     +0.00\n      3..4           8          8.00     +0.00\n      5..6           8          8.00     +0.00\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn fairness_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fairness")
+        .arg("1d6")
+        .arg("--observed")
+        .arg("tests/rolls/loaded_d6.txt")
+        .arg("--json");
+    // An unfair die fails after its report is printed.
+    let output = String::from_utf8(cmd.assert().failure().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+
+    assert_eq!(json["fair"], false);
+    assert_eq!(json["results"], 40);
+    assert_eq!(json["buckets"][5]["significant"], true);
+    assert_eq!(json["buckets"][0]["significant"], false);
+
+    Ok(())
+}
+
+#[test]
+fn fairness_invalid_results() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fairness")
+        .arg("1d6")
+        .arg("--observed")
+        .arg("tests/rolls/invalid.txt");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn fairness_invalid_alpha() -> Result<(), Box<dyn std::error::Error>> {
+    for alpha in ["5", "0", "1", "-0.1"] {
+        let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+        cmd.arg("fairness")
+            .arg("1d6")
+            .arg("--observed")
+            .arg("tests/rolls/d6.txt")
+            .arg(format!("--alpha={}", alpha));
+        let output = cmd.assert().failure().get_output().stderr.clone();
+        assert!(String::from_utf8(output)?
+            .contains("--alpha must be a significance level between 0 and 1"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sweep_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d20 + $m")
+        .arg("--sweep")
+        .arg("m=0..2")
+        .arg("--query")
+        .arg(">= 15")
+        .arg("--stats")
+        .arg("mean");
+    cmd.assert().success().stdout(
+        "Expression: 1d20 + $m\n\n  m  P(>= 15)   Mean\n  0    30.00%  10.50\n  1    35.00%  11.50\n  2    40.00%  12.50\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sweep_heatmap_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("($n)d6 + $m")
+        .arg("--sweep")
+        .arg("n=1,2")
+        .arg("--sweep")
+        .arg("m=0..1");
+    cmd.assert().success().stdout(
+        "Expression: ($n)d6 + $m\n\nMean:\n  n \\ m     0     1\n      1  3.50  4.50\n      2  7.00  8.00\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sweep_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d20 + $m")
+        .arg("--sweep")
+        .arg("m=0,5")
+        .arg("--stats")
+        .arg("min,max")
+        .arg("--json");
+    cmd.assert().success().stdout(
+        "{\"expression\":\"1d20 + $m\",\"variables\":[{\"name\":\"m\",\"values\":[0,5]}],\"rows\":[{\"variables\":{\"m\":0},\"statistics\":{\"min\":1,\"max\":20}},{\"variables\":{\"m\":5},\"statistics\":{\"min\":6,\"max\":25}}]}\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sweep_unbound_variable() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d20 + $m + $n").arg("--sweep").arg("m=0..10");
+    let output = cmd.assert().failure().get_output().stderr.clone();
+    assert!(String::from_utf8(output)?.contains("The variable $n has no values."));
+
+    Ok(())
+}
+
+#[test]
+fn dpr_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("dpr")
+        .arg("--bonus")
+        .arg("7")
+        .arg("--damage")
+        .arg("1d8 + 4")
+        .arg("--crit")
+        .arg("19")
+        .arg("--ac")
+        .arg("14,15")
+        .arg("--attacks")
+        .arg("2");
+    cmd.assert().success().stdout(
+        "Attack: +7 to hit, 1d8 + 4 damage, critical on 19-20 for 2d8 + 4\nAttacks: 2 per round\n\n  AC     hit  critical    DPR\n  14  70.00%    10.00%  12.80\n  15  65.00%    10.00%  11.95\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dpr_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("dpr")
+        .arg("--bonus")
+        .arg("5")
+        .arg("--damage")
+        .arg("1")
+        .arg("--ac")
+        .arg("16")
+        .arg("--attacks")
+        .arg("2")
+        .arg("--rider")
+        .arg("10")
+        .arg("--json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output)?;
+
+    let target = &json["targets"][0];
+    assert_eq!(target["ac"], 16);
+    assert_eq!(target["hit"], 0.5);
+    // The rider is dealt once, on the first of the two attacks to hit.
+    let values = target["pmf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|outcome| outcome["value"].as_i64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![0, 11, 12]);
+
+    Ok(())
+}
+
+#[test]
+fn dpr_invalid_crit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("dpr")
+        .arg("--bonus")
+        .arg("5")
+        .arg("--damage")
+        .arg("1d6")
+        .arg("--crit")
+        .arg("21");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn fit_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fit")
+        .arg("--mean")
+        .arg("7")
+        .arg("--stddev")
+        .arg("2.415")
+        .arg("--max-dice")
+        .arg("3")
+        .arg("--top")
+        .arg("2");
+    cmd.assert().success().stdout(
+        "Target: mean 7, std dev 2.415\n\n  rank  expression     mean  std dev  distance\n     1  2d6            7.00     2.42    0.0002\n     2  1d2 + 1d8 + 1  7.00     2.35    0.0698\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fit_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fit")
+        .arg("--observed")
+        .arg("tests/rolls/d6.txt")
+        .arg("--max-dice")
+        .arg("2")
+        .arg("--top")
+        .arg("1")
+        .arg("--json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let json: serde_json::Value = serde_json::from_slice(&output)?;
+
+    assert_eq!(json["results"], 24);
+    assert_eq!(json["candidates"][0]["expression"], "1d6");
+    assert_eq!(json["candidates"][0]["distance"], 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn fit_without_target() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("fit");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_csv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4").arg("--pmf").arg("--format").arg("csv");
+    cmd.assert().success().stdout(
+        "value,probability,cumulative,survival\n1,0.250000,0.250000,0.750000\n2,0.250000,0.500000,0.500000\n3,0.250000,0.750000,0.250000\n4,0.250000,1.000000,0.000000\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn pmf_markdown_exact() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    // Values too large for an f64 are written exactly.
+    cmd.arg("2 ** 70 + 1d2")
+        .arg("--exact")
+        .arg("--format")
+        .arg("markdown")
+        .arg("--precision")
+        .arg("2");
+    cmd.assert().success().stdout(
+        "| value | probability | cumulative | survival |\n| --- | ---: | ---: | ---: |\n| 1180591620717411303425 | 0.50 | 0.50 | 0.50 |\n| 1180591620717411303426 | 0.50 | 1.00 | 0.00 |\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn roll_tsv() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("2d6")
+        .arg("--seed")
+        .arg("1")
+        .arg("--format")
+        .arg("tsv");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+    let lines = output.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "die\tresult");
+    assert!(lines[1].starts_with("d6\t") && lines[2].starts_with("d6\t"));
+    assert!(lines[3].starts_with("total\t"));
+
+    Ok(())
+}
+
+#[test]
+fn format_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("compare")
+        .arg("1d6")
+        .arg("1d8")
+        .arg("--format")
+        .arg("csv");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn pmf_svg() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    let path = std::env::temp_dir().join(format!("roll-pmf-{}.svg", std::process::id()));
+
+    cmd.arg("2d6").arg("--svg").arg(&path).arg("--cdf");
+    cmd.assert().success();
+    let svg = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.ends_with("</svg>"));
+    assert!(svg.contains(">2d6</text>"));
+    assert!(svg.contains(">mean 7.00</text>"));
+    assert!(svg.contains(">p50 7</text>"));
+    assert_eq!(svg.matches("<polyline").count(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn compare_svg() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    let path = std::env::temp_dir().join(format!("roll-compare-{}.svg", std::process::id()));
+
+    cmd.arg("compare")
+        .arg("1d20")
+        .arg("2d10")
+        .arg("--svg")
+        .arg(&path);
+    cmd.assert().success();
+    let svg = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+
+    assert!(svg.contains(">1d20 vs 2d10</text>"));
+    assert!(svg.contains(">1d20</text>"));
+    assert!(svg.contains(">2d10</text>"));
+    assert!(svg.contains(">mean 10.50</text>"));
+    assert!(svg.contains(">mean 11.00</text>"));
+
+    Ok(())
+}
+
+#[test]
+fn svg_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    let path = std::env::temp_dir().join(format!("roll-query-{}.svg", std::process::id()));
+
+    cmd.arg("1d6")
+        .arg("--query")
+        .arg(">=4")
+        .arg("--svg")
+        .arg(&path);
+    cmd.assert().failure();
+    assert!(!path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn chart_horizontal_without_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4")
+        .arg("--chart")
+        .arg("horizontal")
+        .arg("--width")
+        .arg("20")
+        .arg("--ascii");
+    cmd.assert().success().stdout(
+        "  1 ########  25.00%\n  2 ########  25.00%\n  3 ########  25.00%\n  4 ########  25.00%\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn chart_vertical_ascii() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4 + 1d2")
+        .arg("--chart")
+        .arg("vertical")
+        .arg("--ascii")
+        .arg("--height")
+        .arg("3")
+        .arg("--width")
+        .arg("24");
+    cmd.assert().success().stdout(
+        "  25.00% |  # # #\n         |# # # # #\n         |# # # # #\n      0% +----------\n          2       6\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn chart_sparkline() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("2d6").arg("--chart").arg("sparkline");
+    cmd.assert().success().stdout("  2 ▂▃▅▆▇█▇▆▅▃▂ 12\n\n");
+
+    Ok(())
+}
+
+#[test]
+fn chart_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("compare")
+        .arg("1d4")
+        .arg("1d6")
+        .arg("--chart")
+        .arg("vertical");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn bins_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("3d6").arg("--bins").arg("3").arg("--tail").arg("2");
+    cmd.assert().success().stdout(
+        "     < 5  1.85%\n    5..8 24.07%\n   9..12 48.15%\n  13..16 24.07%\n    > 16  1.85%\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bins_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("3d6")
+        .arg("--bins")
+        .arg("3")
+        .arg("--tail")
+        .arg("2")
+        .arg("--exact")
+        .arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+
+    assert!(json.get("pmf").is_none());
+    assert_eq!(json["bins"].as_array().map(Vec::len), Some(5));
+    assert_eq!(json["bins"][0]["tail"], "below");
+    assert_eq!(json["bins"][0]["max"], 4);
+    assert_eq!(json["bins"][2]["min"], 9);
+    assert_eq!(json["bins"][2]["exact"], "13/27");
+    assert_eq!(json["bins"][4]["tail"], "above");
+
+    Ok(())
+}
+
+#[test]
+fn wide_distributions_are_binned() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d1000").arg("--pmf");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    assert!(output.starts_with("      1..25  2.50%\n     26..50  2.50%\n"));
+    assert_eq!(output.lines().filter(|line| !line.is_empty()).count(), 40);
+
+    // JSON keeps every outcome unless binning is asked for.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d1000").arg("--pmf").arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+    assert_eq!(json["pmf"].as_array().map(Vec::len), Some(1000));
+
+    Ok(())
+}
+
+#[test]
+fn bin_width_zero() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d6").arg("--bin-width").arg("0");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn tree_tty() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4 + 2d2 * 2")
+        .arg("--tree")
+        .arg("--stats")
+        .arg("min,max");
+    cmd.assert().success().stdout(
+        "  Min: 5\n  Max: 12\n\n   5  6.25%\n   6  6.25%\n   7 18.75%\n   8 18.75%\n   9 18.75%\n  10 18.75%\n  11  6.25%\n  12  6.25%\n\n                 min  max\n  1d4 + 2d2 * 2    5   12\n  ├─ 1d4           1    4\n  └─ 2d2 * 2       4    8\n     └─ 2d2        2    4\n\n",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tree_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+
+    cmd.arg("1d4 + 1d6")
+        .arg("--tree")
+        .arg("--exact")
+        .arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+
+    assert_eq!(json["tree"]["expression"], "1d4 + 1d6");
+    assert_eq!(json["tree"]["statistics"]["mean"], 6.0);
+    assert_eq!(json["tree"]["operands"][0]["expression"], "1d4");
+    assert_eq!(json["tree"]["operands"][1]["statistics"]["max"], 6);
+    assert_eq!(
+        json["tree"]["operands"][1]["pmf"].as_array().map(Vec::len),
+        Some(6)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn tree_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d6").arg("--tree").arg("--given").arg(">= 3");
+    cmd.assert().failure();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("compare").arg("1d4").arg("1d6").arg("--tree");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn query_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("compare")
+        .arg("1d4")
+        .arg("1d6")
+        .arg("--query")
+        .arg(">= 3");
+    let output = cmd.assert().failure().get_output().stderr.clone();
+    assert!(String::from_utf8(output)?.contains("--query applies only to rolls and sweeps"));
+
+    Ok(())
+}
+
+#[test]
+fn exact_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("verify").arg("1d6").arg("--exact");
+    let output = cmd.assert().failure().get_output().stderr.clone();
+    assert!(
+        String::from_utf8(output)?.contains("--exact applies only to distributions and queries")
+    );
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("compare").arg("1d4").arg("1d6").arg("--exact");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn cumulative_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("compare").arg("1d4").arg("1d6").arg("--cdf");
+    let output = cmd.assert().failure().get_output().stderr.clone();
+    assert!(String::from_utf8(output)?
+        .contains("--cdf and --at-least apply only to distributions and comparison charts"));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("verify").arg("1d6").arg("--at-least");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn roll_faces() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d20 + 2d6 * 2").arg("--faces").arg("14, 3, 5");
+    cmd.assert().success().stdout("30\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d20 + 2d6 * 2").arg("--faces").arg("14,7,5");
+    cmd.assert()
+        .failure()
+        .stderr("Error: Face 2, 7, is not a possible roll of 2d6.\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d6").arg("--pmf").arg("--faces").arg("3");
+    cmd.assert().failure();
+
+    Ok(())
+}
+
+#[test]
+fn roll_replay() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("roll-replay-{}.json", std::process::id()));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("explode(2d6) + draw(2, [1..10])")
+        .arg("--seed")
+        .arg("9")
+        .arg("--json");
+    let saved = cmd.assert().success().get_output().stdout.clone();
+    std::fs::write(&path, &saved)?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("--replay").arg(&path).arg("--json");
+    let replayed = cmd.assert().success().get_output().stdout.clone();
+    let saved: serde_json::Value = serde_json::from_slice(&saved)?;
+    let replayed: serde_json::Value = serde_json::from_slice(&replayed)?;
+    assert_eq!(replayed["trace"], saved["trace"]);
+    assert_eq!(replayed["rng"]["backend"], "replay");
+
+    // The faces of a saved roll may total another expression.
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("2d6 + 2d10").arg("--replay").arg(&path);
+    cmd.assert().success().stdout("14\n");
+    std::fs::remove_file(&path)?;
+
+    Ok(())
+}
+
+#[test]
+fn rng_os() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("3d6").arg("--rng").arg("os").arg("--json");
+    let output = String::from_utf8(cmd.assert().success().get_output().stdout.clone())?;
+    let json: serde_json::Value = serde_json::from_str(&output)?;
+    assert_eq!(json["rng"], serde_json::json!({ "backend": "os" }));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d1").arg("--rng").arg("os");
+    cmd.assert()
+        .success()
+        .stdout("1\n")
+        .stderr("Rng: os entropy\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("1d20 + 3").arg("--seed").arg("1");
+    cmd.assert()
+        .success()
+        .stdout("23\n")
+        .stderr("Rng: seeded (seed 1)\n");
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("3d6").arg("--rng").arg("os").arg("--seed").arg("1");
+    cmd.assert().failure();
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.arg("3d6").arg("--rng").arg("os").arg("--pmf");
+    cmd.assert().failure();
+
+    Ok(())
+}